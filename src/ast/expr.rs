@@ -1,4 +1,5 @@
-use ast::token::Token;
+use ast::token::{Literal, Token};
+use ast::token::Type as TokenType;
 use Boxer;
 
 /// An Expression Node
@@ -101,6 +102,166 @@ impl Expr {
                 v.visit_super(self, tkn, method),
         }
     }
+
+    /// The leftmost token of the expression, used to locate diagnostics
+    pub fn token(&self) -> &Token {
+        use ast::expr::Expr::*;
+
+        match *self {
+            Identifier(ref tkn) | Literal(ref tkn) | This(ref tkn) |
+            Unary(ref tkn, _) | Assignment(ref tkn, _) | Super(ref tkn, _) => tkn,
+            Grouping(ref expr) | Binary(ref expr, _, _) | Call(ref expr, _, _) |
+            Get(ref expr, _) | Set(ref expr, _, _) => expr.token(),
+        }
+    }
+}
+
+/// Constructors for expressions built by hand, such as by tests and code
+/// generators. Operators and keywords are provided as their lexemes, and all
+/// tokens are synthetic.
+///
+/// # Panics
+///
+/// `unary` and `binary` panic if the operator is not a keyword or
+/// punctuation lexeme.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::expr::Expr;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::printer::AstPrinter;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let expr = Expr::binary(Expr::number(3.0), "*", Expr::grouping(
+///     Expr::binary(Expr::identifier("x"), "+", Expr::string("1"))));
+/// assert_eq!(r#"3 * (x + "1")"#, AstPrinter::default().print_expr(&expr));
+///
+/// let call = Expr::call(Expr::get(Expr::this(), "area"), vec![Expr::nil()]);
+/// assert_eq!("this.area(nil)", AstPrinter::default().print_expr(&call));
+/// # }
+/// ```
+impl Expr {
+    pub fn number(n: f64) -> Expr {
+        let lit = Literal::Number(n);
+        Expr::Literal(Token::synthetic(TokenType::Number, &format!("{}", lit), Some(lit)))
+    }
+
+    pub fn string(s: &str) -> Expr {
+        let lexeme = format!("\"{}\"", s);
+        Expr::Literal(Token::synthetic(TokenType::String, &lexeme, Some(Literal::String(s.into()))))
+    }
+
+    pub fn boolean(b: bool) -> Expr {
+        Expr::Literal(Token::symbol(if b { "true" } else { "false" }))
+    }
+
+    pub fn nil() -> Expr {
+        Expr::Literal(Token::symbol("nil"))
+    }
+
+    pub fn identifier(name: &str) -> Expr {
+        Expr::Identifier(Token::identifier(name))
+    }
+
+    pub fn this() -> Expr {
+        Expr::This(Token::symbol("this"))
+    }
+
+    /// A call to the named method of the superclass
+    pub fn super_method(method: &str) -> Expr {
+        Expr::Super(Token::symbol("super"), Token::identifier(method))
+    }
+
+    pub fn grouping(inside: Expr) -> Expr {
+        Expr::Grouping(inside.boxed())
+    }
+
+    pub fn unary(op: &str, rhs: Expr) -> Expr {
+        Expr::Unary(Token::symbol(op), rhs.boxed())
+    }
+
+    pub fn binary(lhs: Expr, op: &str, rhs: Expr) -> Expr {
+        Expr::Binary(lhs.boxed(), Token::symbol(op), rhs.boxed())
+    }
+
+    pub fn assign(name: &str, val: Expr) -> Expr {
+        Expr::Assignment(Token::identifier(name), val.boxed())
+    }
+
+    pub fn call(callee: Expr, args: Vec<Expr>) -> Expr {
+        Expr::Call(callee.boxed(), Token::symbol(")"), args)
+    }
+
+    pub fn get(callee: Expr, prop: &str) -> Expr {
+        Expr::Get(callee.boxed(), Token::identifier(prop))
+    }
+
+    pub fn set(settee: Expr, prop: &str, val: Expr) -> Expr {
+        Expr::Set(settee.boxed(), Token::identifier(prop), val.boxed())
+    }
 }
 
 impl Boxer for Expr {}
+
+/// Visits each of the expression's direct children in evaluation order,
+/// returning the first error. Visitors can defer to `walk_expr` from
+/// `visit_expr`, overriding only the nodes they are interested in.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::expr::{walk_expr, Expr, Visitor};
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::token::Token;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::parser::StmtIterator;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::scanner::TokenIterator;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// struct Names(Vec<String>);
+///
+/// impl Visitor<Result<(), ()>> for Names {
+///     fn visit_expr(&mut self, expr: &Expr) -> Result<(), ()> {
+///         walk_expr(self, expr)
+///     }
+///
+///     fn visit_identifier(&mut self, _expr: &Expr, id: &Token) -> Result<(), ()> {
+///         self.0.push(id.lexeme.clone());
+///         Ok(())
+///     }
+/// }
+///
+/// let expr = "a + f(b, -c)".chars().tokens().statements().parse_expression().unwrap();
+/// let mut names = Names(Vec::new());
+/// expr.accept(&mut names).unwrap();
+/// assert_eq!(vec!["a", "f", "b", "c"], names.0);
+/// # }
+/// ```
+pub fn walk_expr<V, E>(v: &mut V, expr: &Expr) -> Result<(), E>
+    where V: Visitor<Result<(), E>> {
+    use ast::expr::Expr::*;
+
+    match *expr {
+        Identifier(_) | Literal(_) | This(_) | Super(_, _) => Ok(()),
+        Grouping(ref inside) | Unary(_, ref inside) | Assignment(_, ref inside) |
+        Get(ref inside, _) => inside.accept(v),
+        Binary(ref lhs, _, ref rhs) | Set(ref lhs, _, ref rhs) => {
+            lhs.accept(v)?;
+            rhs.accept(v)
+        }
+        Call(ref callee, _, ref args) => {
+            callee.accept(v)?;
+            args.iter().try_for_each(|a| a.accept(v))
+        }
+    }
+}
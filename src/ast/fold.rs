@@ -0,0 +1,115 @@
+//! A module describing transformations of syntax trees.
+
+use std::rc::Rc;
+
+use ast::expr::Expr;
+use ast::stmt::Stmt;
+use Boxer;
+
+/// Implements AST-to-AST transformations
+///
+/// A Folder consumes a tree and returns its replacement. By default, each
+/// node is rebuilt from its folded children, so implementors only override
+/// the nodes they rewrite, deferring to `fold_expr` or `fold_stmt` for the
+/// rest.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::expr::Expr;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::fold::{self, Folder};
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::printer::AstPrinter;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::parser::StmtIterator;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::scanner::TokenIterator;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// // removes redundant parentheses
+/// struct Ungroup;
+///
+/// impl Folder for Ungroup {
+///     fn fold_expr(&mut self, expr: Expr) -> Expr {
+///         match fold::fold_expr(self, expr) {
+///             Expr::Grouping(inside) => *inside,
+///             expr => expr,
+///         }
+///     }
+/// }
+///
+/// let expr = "((a)) + (b)".chars().tokens().statements().parse_expression().unwrap();
+/// let expr = Ungroup.fold_expr(expr);
+/// assert_eq!("a + b", AstPrinter::default().print_expr(&expr));
+/// # }
+/// ```
+pub trait Folder {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+}
+
+/// Rebuilds the expression from its folded children.
+pub fn fold_expr<F: Folder + ?Sized>(f: &mut F, expr: Expr) -> Expr {
+    use ast::expr::Expr::*;
+
+    match expr {
+        Grouping(inside) => Grouping(f.fold_expr(*inside).boxed()),
+        Unary(op, rhs) => Unary(op, f.fold_expr(*rhs).boxed()),
+        Binary(lhs, op, rhs) => {
+            let lhs = f.fold_expr(*lhs).boxed();
+            Binary(lhs, op, f.fold_expr(*rhs).boxed())
+        }
+        Assignment(id, val) => Assignment(id, f.fold_expr(*val).boxed()),
+        Call(callee, paren, args) => {
+            let callee = f.fold_expr(*callee).boxed();
+            Call(callee, paren, args.into_iter().map(|a| f.fold_expr(a)).collect())
+        }
+        Get(callee, prop) => Get(f.fold_expr(*callee).boxed(), prop),
+        Set(settee, prop, val) => {
+            let settee = f.fold_expr(*settee).boxed();
+            Set(settee, prop, f.fold_expr(*val).boxed())
+        }
+        expr => expr,
+    }
+}
+
+/// Rebuilds the statement from its folded children. Function bodies shared
+/// with a running closure are left as is.
+pub fn fold_stmt<F: Folder + ?Sized>(f: &mut F, stmt: Stmt) -> Stmt {
+    use ast::stmt::Stmt::*;
+
+    match stmt {
+        Expression(expr) => Expression(f.fold_expr(expr)),
+        Print(expr) => Print(f.fold_expr(expr)),
+        Declaration(id, init) => Declaration(id, init.map(|e| f.fold_expr(*e).boxed())),
+        Block(body) => Block(body.into_iter().map(|s| f.fold_stmt(s)).collect()),
+        If(cond, then, els) => {
+            let cond = f.fold_expr(cond);
+            let then = f.fold_stmt(*then).boxed();
+            If(cond, then, els.map(|s| f.fold_stmt(*s).boxed()))
+        }
+        While(cond, body) => {
+            let cond = f.fold_expr(cond);
+            While(cond, f.fold_stmt(*body).boxed())
+        }
+        Function(id, params, body, doc) => match Rc::try_unwrap(body) {
+            Ok(body) => Function(id, params, Rc::new(f.fold_stmt(body)), doc),
+            Err(body) => Function(id, params, body, doc),
+        },
+        Return(tkn, val) => Return(tkn, val.map(|e| f.fold_expr(*e).boxed())),
+        Class(id, parent, methods, doc) =>
+            Class(id, parent, methods.into_iter().map(|m| f.fold_stmt(m)).collect(), doc),
+        Export(tkn, decl) => Export(tkn, f.fold_stmt(*decl).boxed()),
+        stmt => stmt,
+    }
+}
@@ -1,5 +1,7 @@
 //! A module describing the Lox abstract syntax tree.
 
 pub mod expr;
+pub mod fold;
+pub mod printer;
 pub mod stmt;
 pub mod token;
@@ -0,0 +1,236 @@
+//! A module describing the printing of syntax trees back into Lox source.
+
+use std::rc::Rc;
+
+use ast::expr::{Expr, Visitor as ExprVisitor};
+use ast::stmt::{Stmt, Visitor as StmtVisitor};
+use ast::token::{Literal, Token};
+
+/// The indentation added per nested block
+const INDENT: &str = "  ";
+
+/// AstPrinter renders statements as formatted Lox source.
+///
+/// Only the tree is printed, so comments other than the doc comments of
+/// declarations and the original spacing are not retained. Grouping nodes
+/// are printed as written, and no others are added, so parsing the output
+/// produces an equivalent tree.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::printer::AstPrinter;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::parser::StmtIterator;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::scanner::TokenIterator;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let stmts: Vec<_> = "if(x)print (1+2)*3;".chars().tokens().statements()
+///     .collect::<Result<_, _>>().unwrap();
+///
+/// assert_eq!("if (x) print (1 + 2) * 3;\n", AstPrinter::default().print(&stmts));
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct AstPrinter {
+    depth: usize,
+}
+
+impl AstPrinter {
+    /// Renders the statements, one per line.
+    pub fn print(&mut self, stmts: &[Stmt]) -> String {
+        stmts.iter()
+            .map(|s| format!("{}{}\n", self.indent(), s.accept(self)))
+            .collect()
+    }
+
+    /// Renders the expression.
+    pub fn print_expr(&mut self, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    fn indent(&self) -> String {
+        INDENT.repeat(self.depth)
+    }
+
+    /// Renders the doc comment of a declaration on the lines preceding it.
+    fn doc(&self, decl: &Stmt) -> String {
+        decl.doc().map_or_else(String::new, |doc| doc.lines()
+            .map(|l| match l {
+                "" => format!("///\n{}", self.indent()),
+                l => format!("/// {}\n{}", l, self.indent()),
+            })
+            .collect())
+    }
+
+    fn function(&mut self, id: &Token, params: &[Token], body: &Stmt) -> String {
+        let params: Vec<&str> = params.iter().map(|p| p.lexeme.as_str()).collect();
+        format!("{}({}) {}", id.lexeme, params.join(", "), body.accept(self))
+    }
+
+    /// Renders the body of a control flow statement, following its header.
+    fn body(&mut self, body: &Stmt) -> String {
+        match *body {
+            Stmt::Empty => body.accept(self),
+            _ => format!(" {}", body.accept(self)),
+        }
+    }
+}
+
+impl ExprVisitor<String> for AstPrinter {
+    fn visit_identifier(&mut self, _expr: &Expr, id: &Token) -> String {
+        id.lexeme.to_owned()
+    }
+
+    fn visit_literal(&mut self, _expr: &Expr, lit: &Token) -> String {
+        literal(lit)
+    }
+
+    fn visit_grouping(&mut self, _expr: &Expr, inside: &Expr) -> String {
+        format!("({})", inside.accept(self))
+    }
+
+    fn visit_unary(&mut self, _expr: &Expr, op: &Token, rhs: &Expr) -> String {
+        format!("{}{}", op.lexeme, rhs.accept(self))
+    }
+
+    fn visit_binary(&mut self, _expr: &Expr, lhs: &Expr, op: &Token, rhs: &Expr) -> String {
+        format!("{} {} {}", lhs.accept(self), op.lexeme, rhs.accept(self))
+    }
+
+    fn visit_assignment(&mut self, _expr: &Expr, id: &Token, val: &Expr) -> String {
+        format!("{} = {}", id.lexeme, val.accept(self))
+    }
+
+    fn visit_call(&mut self, _expr: &Expr, callee: &Expr, _paren: &Token, args: &[Expr]) -> String {
+        let args: Vec<String> = args.iter().map(|a| a.accept(self)).collect();
+        format!("{}({})", callee.accept(self), args.join(", "))
+    }
+
+    fn visit_get(&mut self, _expr: &Expr, callee: &Expr, prop: &Token) -> String {
+        format!("{}.{}", callee.accept(self), prop.lexeme)
+    }
+
+    fn visit_set(&mut self, _expr: &Expr, settee: &Expr, prop: &Token, val: &Expr) -> String {
+        format!("{}.{} = {}", settee.accept(self), prop.lexeme, val.accept(self))
+    }
+
+    fn visit_this(&mut self, _expr: &Expr, tkn: &Token) -> String {
+        tkn.lexeme.to_owned()
+    }
+
+    fn visit_super(&mut self, _expr: &Expr, tkn: &Token, method: &Token) -> String {
+        format!("{}.{}", tkn.lexeme, method.lexeme)
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_empty(&mut self, _stmt: &Stmt) -> String {
+        ";".to_owned()
+    }
+
+    fn visit_break(&mut self, _stmt: &Stmt, _tkn: &Token) -> String {
+        "break;".to_owned()
+    }
+
+    fn visit_expr_stmt(&mut self, _stmt: &Stmt, expr: &Expr) -> String {
+        format!("{};", expr.accept(self))
+    }
+
+    fn visit_print(&mut self, _stmt: &Stmt, expr: &Expr) -> String {
+        format!("print {};", expr.accept(self))
+    }
+
+    fn visit_decl(&mut self, _stmt: &Stmt, id: &Token, init: Option<&Expr>) -> String {
+        match init {
+            Some(expr) => format!("var {} = {};", id.lexeme, expr.accept(self)),
+            None => format!("var {};", id.lexeme),
+        }
+    }
+
+    fn visit_block(&mut self, _stmt: &Stmt, body: &[Stmt]) -> String {
+        if body.is_empty() {
+            return "{}".to_owned();
+        }
+
+        self.depth += 1;
+        let inner = self.print(body);
+        self.depth -= 1;
+
+        format!("{{\n{}{}}}", inner, self.indent())
+    }
+
+    fn visit_if(&mut self, _stmt: &Stmt, cond: &Expr, then: &Stmt, els: Option<&Stmt>) -> String {
+        let mut out = format!("if ({}){}", cond.accept(self), self.body(then));
+
+        if let Some(els) = els {
+            out.push_str(" else");
+            out.push_str(&self.body(els));
+        }
+
+        out
+    }
+
+    fn visit_while(&mut self, _stmt: &Stmt, cond: &Expr, body: &Stmt) -> String {
+        format!("while ({}){}", cond.accept(self), self.body(body))
+    }
+
+    fn visit_func(&mut self, stmt: &Stmt, id: &Token, params: &[Token], body: Rc<Stmt>) -> String {
+        format!("{}fun {}", self.doc(stmt), self.function(id, params, &body))
+    }
+
+    fn visit_return(&mut self, _stmt: &Stmt, _tkn: &Token, val: Option<&Expr>) -> String {
+        match val {
+            Some(expr) => format!("return {};", expr.accept(self)),
+            None => "return;".to_owned(),
+        }
+    }
+
+    fn visit_class(&mut self, stmt: &Stmt, id: &Token, parent: Option<&Expr>, methods: &[Stmt]) -> String {
+        let mut out = format!("{}class {}", self.doc(stmt), id.lexeme);
+
+        if let Some(p) = parent {
+            out.push_str(&format!(" < {}", p.accept(self)));
+        }
+
+        if methods.is_empty() {
+            out.push_str(" {}");
+            return out;
+        }
+
+        out.push_str(" {\n");
+        self.depth += 1;
+        for m in methods {
+            if let Stmt::Function(ref id, ref params, ref body, _) = *m {
+                let method = self.function(id, params, body);
+                out.push_str(&format!("{}{}{}\n", self.indent(), self.doc(m), method));
+            }
+        }
+        self.depth -= 1;
+        out.push_str(&format!("{}}}", self.indent()));
+
+        out
+    }
+
+    fn visit_import(&mut self, _stmt: &Stmt, _tkn: &Token, path: &Token) -> String {
+        format!("import {};", literal(path))
+    }
+
+    fn visit_export(&mut self, _stmt: &Stmt, _tkn: &Token, decl: &Stmt) -> String {
+        // the declaration's doc comment precedes the export keyword
+        let doc = self.doc(decl);
+        format!("{}export {}", doc, &decl.accept(self)[doc.len()..])
+    }
+}
+
+fn literal(lit: &Token) -> String {
+    match lit.literal {
+        Some(Literal::String(ref s)) => format!("\"{}\"", s.replace('"', "\\\"")),
+        _ => lit.lexeme.to_owned(),
+    }
+}
@@ -1,5 +1,5 @@
 use ast::token::Token;
-use ast::expr::Expr;
+use ast::expr::{Expr, Visitor as ExprVisitor};
 use std::vec::Vec;
 use std::rc::Rc;
 use Boxer;
@@ -16,9 +16,13 @@ pub enum Stmt {
     Block(Vec<Stmt>),
     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
     While(Expr, Box<Stmt>),
-    Function(Token, Vec<Token>, Rc<Stmt>),
+    /// A function or method, with the text of any doc comment preceding it
+    Function(Token, Vec<Token>, Rc<Stmt>, Option<Rc<str>>),
     Return(Token, Option<Box<Expr>>),
-    Class(Token, Option<Box<Expr>>, Vec<Stmt>),
+    /// A class, with the text of any doc comment preceding it
+    Class(Token, Option<Box<Expr>>, Vec<Stmt>, Option<Rc<str>>),
+    Import(Token, Token),
+    Export(Token, Box<Stmt>),
 }
 
 pub trait Visitor<T> {
@@ -67,6 +71,14 @@ pub trait Visitor<T> {
     fn visit_class(&mut self, _stmt: &Stmt, _id: &Token, _parent: Option<&Expr>, _methods: &[Stmt]) -> T {
         self.visit_stmt(_stmt)
     }
+
+    fn visit_import(&mut self, _stmt: &Stmt, _tkn: &Token, _path: &Token) -> T {
+        self.visit_stmt(_stmt)
+    }
+
+    fn visit_export(&mut self, _stmt: &Stmt, _tkn: &Token, _decl: &Stmt) -> T {
+        self.visit_stmt(_stmt)
+    }
 }
 
 impl Stmt {
@@ -92,19 +104,152 @@ impl Stmt {
                     .map(|bs| bs.as_ref())),
             While(ref cond, ref body) =>
                 v.visit_while(self, cond, body.as_ref()),
-            Function(ref id, ref params, ref body) =>
+            Function(ref id, ref params, ref body, _) =>
                 v.visit_func(self, id, params, Rc::clone(body)),
             Return(ref tkn, ref val) =>
                 v.visit_return(self,
                                tkn,
                                val.as_ref().map(|e| e.as_ref())),
-            Class(ref id, ref parent, ref methods) =>
+            Class(ref id, ref parent, ref methods, _) =>
                 v.visit_class(self,
                               id,
                               parent.as_ref().map(|e| e.as_ref()),
                               methods),
+            Import(ref tkn, ref path) =>
+                v.visit_import(self, tkn, path),
+            Export(ref tkn, ref decl) =>
+                v.visit_export(self, tkn, decl),
+        }
+    }
+
+    /// The first token of the statement, if any, used to locate diagnostics
+    pub fn token(&self) -> Option<&Token> {
+        use ast::stmt::Stmt::*;
+
+        match *self {
+            Empty => None,
+            Break(ref tkn) | Declaration(ref tkn, _) | Function(ref tkn, _, _, _) |
+            Return(ref tkn, _) | Class(ref tkn, _, _, _) | Import(ref tkn, _) |
+            Export(ref tkn, _) => Some(tkn),
+            Expression(ref expr) | Print(ref expr) |
+            If(ref expr, _, _) | While(ref expr, _) => Some(expr.token()),
+            Block(ref body) => body.iter().filter_map(|s| s.token()).next(),
+        }
+    }
+
+    /// The text of the doc comment preceding a function, method, or class,
+    /// if any. An exported declaration carries its own doc comment.
+    pub fn doc(&self) -> Option<&Rc<str>> {
+        match *self {
+            Stmt::Function(_, _, _, ref doc) | Stmt::Class(_, _, _, ref doc) => doc.as_ref(),
+            Stmt::Export(_, ref decl) => decl.doc(),
+            _ => None,
         }
     }
 }
 
+/// Constructors for statements built by hand, such as by tests and code
+/// generators. All tokens are synthetic.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::expr::Expr;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::stmt::Stmt;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::printer::AstPrinter;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let stmts = vec![
+///     Stmt::function("double", &["n"], vec![
+///         Stmt::ret(Some(Expr::binary(Expr::identifier("n"), "*", Expr::number(2.0)))),
+///     ]),
+///     Stmt::print(Expr::call(Expr::identifier("double"), vec![Expr::number(21.0)])),
+/// ];
+///
+/// assert_eq!("fun double(n) {\n  return n * 2;\n}\nprint double(21);\n",
+///            AstPrinter::default().print(&stmts));
+/// # }
+/// ```
+impl Stmt {
+    pub fn expression(expr: Expr) -> Stmt {
+        Stmt::Expression(expr)
+    }
+
+    pub fn print(expr: Expr) -> Stmt {
+        Stmt::Print(expr)
+    }
+
+    pub fn var(name: &str, init: Option<Expr>) -> Stmt {
+        Stmt::Declaration(Token::identifier(name), init.map(Boxer::boxed))
+    }
+
+    pub fn block(body: Vec<Stmt>) -> Stmt {
+        Stmt::Block(body)
+    }
+
+    pub fn if_else(cond: Expr, then: Stmt, els: Option<Stmt>) -> Stmt {
+        Stmt::If(cond, then.boxed(), els.map(Boxer::boxed))
+    }
+
+    pub fn while_loop(cond: Expr, body: Stmt) -> Stmt {
+        Stmt::While(cond, body.boxed())
+    }
+
+    pub fn brk() -> Stmt {
+        Stmt::Break(Token::symbol("break"))
+    }
+
+    pub fn function(name: &str, params: &[&str], body: Vec<Stmt>) -> Stmt {
+        let params = params.iter().map(|p| Token::identifier(p)).collect();
+        Stmt::Function(Token::identifier(name), params, Rc::new(Stmt::Block(body)), None)
+    }
+
+    pub fn ret(val: Option<Expr>) -> Stmt {
+        Stmt::Return(Token::symbol("return"), val.map(Boxer::boxed))
+    }
+
+    /// A class declaration, whose methods are `Stmt::function`s
+    pub fn class(name: &str, parent: Option<&str>, methods: Vec<Stmt>) -> Stmt {
+        Stmt::Class(Token::identifier(name), parent.map(|p| Expr::identifier(p).boxed()), methods, None)
+    }
+}
+
 impl Boxer for Stmt {}
+
+/// Visits each of the statement's direct children in source order, returning
+/// the first error. Visitors can defer to `walk_stmt` from `visit_stmt`,
+/// overriding only the nodes they are interested in.
+pub fn walk_stmt<V, E>(v: &mut V, stmt: &Stmt) -> Result<(), E>
+    where V: Visitor<Result<(), E>> + ExprVisitor<Result<(), E>> {
+    use ast::stmt::Stmt::*;
+
+    match *stmt {
+        Empty | Break(_) | Declaration(_, None) | Return(_, None) | Import(_, _) => Ok(()),
+        Expression(ref expr) | Print(ref expr) => expr.accept(v),
+        Declaration(_, Some(ref expr)) | Return(_, Some(ref expr)) => expr.accept(v),
+        Block(ref body) => body.iter().try_for_each(|s| s.accept(v)),
+        Export(_, ref decl) => decl.accept(v),
+        If(ref cond, ref then, ref els) => {
+            cond.accept(v)?;
+            then.accept(v)?;
+            els.as_ref().map_or(Ok(()), |s| s.accept(v))
+        }
+        While(ref cond, ref body) => {
+            cond.accept(v)?;
+            body.accept(v)
+        }
+        Function(_, _, ref body, _) => body.accept(v),
+        Class(_, ref parent, ref methods, _) => {
+            if let Some(ref p) = *parent {
+                p.accept(v)?;
+            }
+            methods.iter().try_for_each(|m| m.accept(v))
+        }
+    }
+}
@@ -4,6 +4,9 @@ use std::fmt;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::cmp::Ordering;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 
 /// A Token read from source.
 ///
@@ -20,9 +23,77 @@ pub struct Token {
     pub line: u64,
     /// The character offset of the line where this token was read from
     pub offset: u64,
+    /// Identifies the source this token was read from, distinguishing tokens
+    /// at the same position in separately scanned sources (eg, REPL lines)
+    pub source: u64,
 }
 
+/// The source of tokens built by hand rather than scanned
+pub const SYNTHETIC_SOURCE: u64 = u64::MAX;
+
+/// The offset of the next synthetic token
+static SYNTHETIC_OFFSET: AtomicU64 = AtomicU64::new(0);
+
 impl Token {
+    /// Creates a token for syntax trees built by hand. Each synthetic token
+    /// has a distinct offset, so identical nodes built separately are not
+    /// conflated when resolving.
+    pub fn synthetic(typ: Type, lexeme: &str, literal: Option<Literal>) -> Token {
+        Token {
+            typ,
+            lexeme: lexeme.to_owned(),
+            literal,
+            line: 0,
+            offset: SYNTHETIC_OFFSET.fetch_add(1, AtomicOrdering::Relaxed),
+            source: SYNTHETIC_SOURCE,
+        }
+    }
+
+    /// Creates a synthetic identifier token
+    pub fn identifier(name: &str) -> Token {
+        Token::synthetic(Type::Identifier, name, None)
+    }
+
+    /// Creates a synthetic token for the keyword or punctuation.
+    ///
+    /// # Panics
+    ///
+    /// If the lexeme is neither a keyword nor punctuation. Only the AST
+    /// constructors call this, with lexemes they know to be valid.
+    pub(crate) fn symbol(lexeme: &str) -> Token {
+        let typ = match lexeme {
+            "(" => Type::LeftParen,
+            ")" => Type::RightParen,
+            "{" => Type::LeftBrace,
+            "}" => Type::RightBrace,
+            "," => Type::Comma,
+            "." => Type::Dot,
+            "-" => Type::Minus,
+            "+" => Type::Plus,
+            ";" => Type::Semicolon,
+            "/" => Type::Slash,
+            "*" => Type::Star,
+            "!" => Type::Bang,
+            "!=" => Type::BangEqual,
+            "=" => Type::Equal,
+            "==" => Type::EqualEqual,
+            ">" => Type::Greater,
+            ">=" => Type::GreaterEqual,
+            "<" => Type::Less,
+            "<=" => Type::LessEqual,
+            kw => *Type::reserved(kw).unwrap_or_else(|| panic!("'{}' is not a keyword or punctuation", kw)),
+        };
+
+        let literal = match typ {
+            Type::Nil => Some(Literal::Nil),
+            Type::True => Some(Literal::Boolean(true)),
+            Type::False => Some(Literal::Boolean(false)),
+            _ => None,
+        };
+
+        Token::synthetic(typ, lexeme, literal)
+    }
+
     pub fn in_types(&self, types: &[Type]) -> bool {
         for typ in types {
             if &self.typ == typ {
@@ -37,11 +108,12 @@ impl Token {
 impl Default for Token {
     fn default() -> Self {
         Token {
-            typ: Type::EOF,
+            typ: Type::Eof,
             lexeme: "".to_string(),
             literal: None,
             line: 0,
             offset: 0,
+            source: 0,
         }
     }
 }
@@ -52,13 +124,30 @@ impl fmt::Display for Token {
     }
 }
 
-/// Describes a literal string or number value
+/// A Token annotated with its exact position in source, as produced by a
+/// lossless Scanner.
+///
+/// Concatenating the trivia and text of every Lexeme reproduces the source.
+#[derive(Debug, Clone)]
+pub struct Lexeme<'a> {
+    /// The scanned token
+    pub token: Token,
+    /// The whitespace preceding the token
+    pub trivia: &'a str,
+    /// The token's exact source text
+    pub text: &'a str,
+    /// The byte range of the token's text in the source
+    pub span: Range<usize>,
+}
+
+/// Describes a literal string or number value. Strings are shared, so
+/// cloning a Literal never allocates.
 #[derive(Debug, Clone)]
 pub enum Literal {
     Nil,
     Boolean(bool),
     Number(f64),
-    String(String),
+    String(Arc<str>),
 }
 
 impl Eq for Literal {}
@@ -170,7 +259,13 @@ pub enum Type {
     Var,
     While,
     Break,
-    EOF,
+    Import,
+    Export,
+    Comment,
+    /// A `///` comment documenting the declaration that follows it, which is
+    /// kept even when other comments are discarded
+    DocComment,
+    Eof,
 }
 
 impl Type {
@@ -180,7 +275,11 @@ impl Type {
     ///
     /// ```
     /// # extern crate rlox;
+    /// # #[cfg(feature = "internals")]
     /// # use rlox::ast::token::*;
+    /// # #[cfg(not(feature = "internals"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "internals")]
     /// # fn main() {
     /// let t = Type::reserved("true").expect("'true' is a reserved keyword");
     /// assert_eq!(t, &Type::True);
@@ -212,5 +311,7 @@ lazy_static! {
         ("var", Type::Var),
         ("while", Type::While),
         ("break", Type::Break),
+        ("import", Type::Import),
+        ("export", Type::Export),
     ].iter().cloned().collect();
 }
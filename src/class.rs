@@ -1,12 +1,17 @@
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use functions::Callable;
-use result::{Result, Error};
+use result::{Result, Error, RuntimeKind};
 use object::Object;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use ast::token::Token;
 use ast::token::Type as TokenType;
-use std::collections::HashMap;
+use ordered::OrderedMap;
+use shape::Shape;
+use suggest;
+use stats::{self, Counter};
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Type {
@@ -18,34 +23,57 @@ pub enum Type {
 pub struct LoxClass {
     name: String,
     parent: Option<Rc<LoxClass>>,
-    methods: HashMap<String, Callable>,
+    /// Methods are looked up on every access, so those defined or replaced
+    /// by `defineMethod` apply to existing instances and subclasses at once
+    methods: RefCell<OrderedMap<Callable>>,
+    /// The shape new instances start with, so instances of the class
+    /// share the shapes they transition through
+    shape: Rc<Shape>,
+    doc: Option<Rc<str>>,
 }
 
+/// The number of superclasses named when displaying a class before the rest
+/// of its chain is elided
+const MAX_ANCESTORS: usize = 8;
+
 impl fmt::Debug for LoxClass {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.parent.as_ref() {
-            Some(p) => fmt::Display::fmt(&format!("{}::{}", self.name, p), f),
-            None => fmt::Display::fmt(&self.name, f),
-        }
+        fmt::Display::fmt(self, f)
     }
 }
 
 impl LoxClass {
-    pub fn new(name: &str, parent: Option<Rc<LoxClass>>, methods: HashMap<String, Callable>) -> LoxClass {
+    pub fn new(name: &str, parent: Option<Rc<LoxClass>>, methods: OrderedMap<Callable>, doc: Option<&Rc<str>>) -> LoxClass {
         let c = LoxClass {
             name: name.to_owned(),
             parent,
-            methods,
+            methods: RefCell::new(methods),
+            shape: Shape::root(),
+            doc: doc.cloned(),
         };
 
         debug_create!("{} Class", c);
+        stats::record(Counter::Class);
 
         c
     }
 
-    pub fn find_method(&self, name: &str) -> Option<&Callable> {
-        if let Some(method) = self.methods.get(name) {
-            return Some(method);
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The text of the class's doc comment, if any
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    pub fn parent(&self) -> Option<&Rc<LoxClass>> {
+        self.parent.as_ref()
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Callable> {
+        if let Some(method) = self.methods.borrow().get(name) {
+            return Some(method.clone());
         }
 
         if let Some(ref p) = self.parent {
@@ -54,14 +82,54 @@ impl LoxClass {
 
         None
     }
+
+    /// The names of the methods declared directly on this class, in
+    /// declaration order.
+    pub fn method_names(&self) -> Vec<String> {
+        self.methods.borrow().keys().map(|k| k.to_owned()).collect()
+    }
+
+    /// Defines the method on this class, replacing any of the same name.
+    pub fn define_method(&self, name: &str, method: Callable) {
+        // the replaced method is dropped once the table is released
+        let _replaced = self.methods.borrow_mut().insert(name.to_owned(), method);
+    }
+
+    /// Returns the method closest to the misspelled name, walking the
+    /// superclass chain.
+    pub fn suggest_method(&self, name: &str) -> Option<String> {
+        let mut names = Vec::new();
+        let mut cls = Some(self);
+        while let Some(c) = cls {
+            names.extend(c.method_names());
+            cls = c.parent.as_ref().map(|p| p.as_ref());
+        }
+
+        suggest::closest(name, names.iter().map(String::as_str))
+    }
 }
 
 impl fmt::Display for LoxClass {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.parent.as_ref() {
-            Some(p) => fmt::Display::fmt(&format!("{}::{}", self.name, p), f),
-            None => fmt::Display::fmt(&self.name, f),
+        let mut out = self.name.clone();
+        let mut parent = self.parent.as_ref();
+
+        for _ in 0..MAX_ANCESTORS {
+            match parent {
+                Some(p) => {
+                    out.push_str("::");
+                    out.push_str(&p.name);
+                    parent = p.parent.as_ref();
+                }
+                None => break,
+            }
+        }
+
+        if parent.is_some() {
+            out.push_str("::...");
         }
+
+        f.pad(&out)
     }
 }
 
@@ -76,15 +144,67 @@ impl Drop for LoxClass {
 }
 
 pub struct LoxInstance {
-    loc: Token,
+    /// The line and offset of the call that created the instance
+    loc: (u64, u64),
     class: Rc<LoxClass>,
-    fields: Rc<RefCell<HashMap<String, Object>>>,
+    fields: Rc<RefCell<Fields>>,
+}
+
+/// The instances awaiting a call to `finalize`, in the order they were
+/// released, each with the token of the call that created it
+pub type Finalizers = Rc<RefCell<VecDeque<(LoxInstance, Token)>>>;
+
+/// The number of fields tracked by an instance's shape. Fields added past
+/// this are kept by the instance itself, so large records, like parsed JSON
+/// objects, do not grow the shared transition tree.
+const MAX_SHAPE_FIELDS: usize = 64;
+
+/// The fields of an instance, stored in the slots given by its shape,
+/// followed by any fields past `MAX_SHAPE_FIELDS`
+struct Fields {
+    shape: Rc<Shape>,
+    slots: Vec<Object>,
+    overflow: OrderedMap<Object>,
+    /// Where the instance is queued once released, if its class declares
+    /// `finalize`, along with the token errors in `finalize` are reported
+    /// at. It is taken when queued, so each instance is queued once.
+    finalizers: Option<(Finalizers, Token)>,
+}
+
+impl Fields {
+    fn get(&self, name: &str) -> Option<&Object> {
+        match self.shape.slot(name) {
+            Some(idx) => Some(&self.slots[idx]),
+            None => self.overflow.get(name),
+        }
+    }
+
+    /// Assigns the field, transitioning to a new shape if it is added.
+    fn insert(&mut self, name: &str, val: Object) {
+        if let Some(idx) = self.shape.slot(name) {
+            self.slots[idx] = val;
+            return;
+        }
+
+        if self.slots.len() >= MAX_SHAPE_FIELDS {
+            self.overflow.insert(name.to_owned(), val);
+            return;
+        }
+
+        self.shape = self.shape.with(name);
+        self.slots.push(val);
+    }
+
+    /// Iterates over the field names in the order they were first assigned
+    fn names(&self) -> impl Iterator<Item = &str> {
+        self.shape.names().chain(self.overflow.keys())
+    }
 }
 
 impl Clone for LoxInstance {
     fn clone(&self) -> Self {
         let i = LoxInstance {
-            loc: self.loc.clone(),
+            loc: self.loc,
             class: Rc::clone(&self.class),
             fields: Rc::clone(&self.fields),
         };
@@ -100,12 +220,34 @@ impl Clone for LoxInstance {
 impl LoxInstance {
     pub fn new(class: &Rc<LoxClass>, loc: &Token) -> LoxInstance {
         let i = LoxInstance {
-            loc: loc.clone(),
+            loc: (loc.line, loc.offset),
             class: Rc::clone(class),
-            fields: Rc::new(RefCell::new(HashMap::new())),
+            fields: Rc::new(RefCell::new(Fields {
+                shape: Rc::clone(&class.shape),
+                slots: Vec::new(),
+                overflow: OrderedMap::new(),
+                finalizers: None,
+            })),
         };
 
         debug_create!("{:?} ({} class refs)", i, Rc::strong_count(&i.class));
+        stats::record(Counter::Instance);
+
+        i
+    }
+
+    /// Creates an instance of a method-less class with the provided fields,
+    /// allowing natives to return structured records.
+    pub fn record(name: &str, fields: Vec<(&str, Object)>) -> LoxInstance {
+        let cls = Rc::new(LoxClass::new(name, None, OrderedMap::new(), None));
+        let i = LoxInstance::new(&cls, &Token::default());
+
+        {
+            let mut fs = i.fields.borrow_mut();
+            for (k, v) in fields {
+                fs.insert(k, v);
+            }
+        }
 
         i
     }
@@ -119,23 +261,78 @@ impl LoxInstance {
             return Ok(Object::Func(method.bind(self)));
         }
 
+        let suggestion = {
+            let fields = self.fields.borrow();
+            let method = self.class.suggest_method(&field.lexeme);
+            let names = fields.names().chain(method.as_deref());
+            suggest::closest(&field.lexeme, names)
+        };
+
         Err(Error::Runtime(
             field.line,
-            format!("undefined property `{}`", field.lexeme),
+            RuntimeKind::PropertyNotFound { name: field.lexeme.to_owned(), suggestion },
             field.lexeme.to_owned()))
     }
 
+    /// Returns the named method bound to the instance, ignoring any field of
+    /// the same name.
+    pub fn method(&self, name: &str) -> Option<Callable> {
+        self.class.find_method(name).map(|m| m.bind(self))
+    }
+
     pub fn set(&self, field: &Token, val: Object) -> Result<Object> {
         self.fields.borrow_mut()
-            .insert(field.lexeme.clone(), val.clone());
+            .insert(&field.lexeme, val.clone());
         debug_assign!("{:?}.{} => {:?}", self, field.lexeme, val);
         Ok(val)
     }
+
+    /// Returns a copy of the instance's fields, in the order they were
+    /// first assigned. Reassigning a field keeps its original position.
+    ///
+    /// The instance is only borrowed while copying, so user code may run,
+    /// and modify the instance, while the snapshot is in use. Values are
+    /// reference counted, making the copy cheap.
+    pub fn snapshot_fields(&self) -> Vec<(String, Object)> {
+        let fields = self.fields.borrow();
+        fields.shape.names()
+            .zip(fields.slots.iter())
+            .chain(fields.overflow.iter().map(|(k, v)| (k.as_str(), v)))
+            .map(|(k, v)| (k.to_owned(), v.clone()))
+            .collect()
+    }
+
+    /// Queues the instance on the finalizers once the last reference to it
+    /// is dropped, to be finalized with errors reported at the token.
+    pub fn finalize_into(&self, finalizers: &Finalizers, tkn: &Token) {
+        self.fields.borrow_mut().finalizers = Some((Rc::clone(finalizers), tkn.clone()));
+    }
+
+    /// Returns true if both values refer to the same instance.
+    pub fn is(&self, other: &LoxInstance) -> bool {
+        Rc::ptr_eq(&self.fields, &other.fields)
+    }
+
+    /// The name of the instance's class
+    pub fn class_name(&self) -> &str {
+        &self.class.name
+    }
+
+    pub fn class(&self) -> &Rc<LoxClass> {
+        &self.class
+    }
+}
+
+/// Instances hash by identity, consistent with `is`.
+impl Hash for LoxInstance {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.fields).hash(state)
+    }
 }
 
 impl fmt::Debug for LoxInstance {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} instance<{}:{}>", self.class, self.loc.line, self.loc.offset)
+        write!(f, "{} instance<{}:{}>", self.class, self.loc.0, self.loc.1)
     }
 }
 
@@ -159,11 +356,27 @@ lazy_static! {
     };
 }
 
-#[cfg(feature = "debug-destructors")]
 impl Drop for LoxInstance {
     fn drop(&mut self) {
+        if Rc::strong_count(&self.fields) == 1 {
+            // revives the released instance for its finalizer to be called
+            // once the current statement completes, rather than here
+            let finalizers = self.fields.try_borrow_mut().ok().and_then(|mut f| f.finalizers.take());
+            if let Some((finalizers, tkn)) = finalizers {
+                finalizers.borrow_mut().push_back((LoxInstance {
+                    loc: self.loc,
+                    class: Rc::clone(&self.class),
+                    fields: Rc::clone(&self.fields),
+                }, tkn));
+            }
+        }
+
+        if !cfg!(feature = "debug-destructors") {
+            return;
+        }
+
         match Rc::strong_count(&self.fields) {
-            1 => debug_drop!("{:?} with fields {:?}", self, self.fields.borrow().keys()),
+            1 => debug_drop!("{:?} with fields {:?}", self, self.fields.borrow().names().collect::<Vec<_>>()),
             refs => debug_drop!("{:?} reference ({} class refs)", self, refs -1),
         }
     }
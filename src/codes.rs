@@ -0,0 +1,372 @@
+//! A module describing the stable codes identifying each kind of diagnostic.
+//!
+//! Codes are grouped by the phase raising them: `E00xx` at runtime, `E01xx`
+//! while resolving, `E02xx` while parsing, `E03xx` while scanning, and
+//! `W01xx` for lints. A code is never reused once assigned.
+
+use lint::{Lint, Warning};
+use result::{Error, LexicalKind, ParseKind, RuntimeKind};
+
+/// Code describes a kind of diagnostic, with an explanation and an example
+/// raising it. Codes that scripts cannot raise have no example.
+#[derive(Debug, PartialEq)]
+pub struct Code {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+    pub example: &'static str,
+}
+
+impl Code {
+    /// Renders the code's extended description, as printed by `rlox explain`.
+    pub fn describe(&self) -> String {
+        if self.example.is_empty() {
+            return format!("{}: {}\n\n{}\n", self.code, self.title, self.explanation);
+        }
+
+        let example: Vec<String> = self.example.lines().map(|l| format!("    {}", l)).collect();
+        format!("{}: {}\n\n{}\n\nFor example:\n\n{}\n",
+                self.code, self.title, self.explanation, example.join("\n"))
+    }
+}
+
+/// Every code, in order
+pub static CODES: &[Code] = &[
+    Code {
+        code: "E0001",
+        title: "undefined variable",
+        explanation: "A variable was read or assigned before it was declared with `var`, \
+            or the name is misspelled. Globals may be declared after the functions \
+            using them, but must be declared before those functions are called.",
+        example: "print count;\nvar count = 1;",
+    },
+    Code {
+        code: "E0002",
+        title: "variable already defined",
+        explanation: "A local variable was defined twice in the same scope at runtime. \
+            Only globals may be redefined. Scripts report this as E0108 before \
+            running, so it arises only from embedders defining locals directly.",
+        example: "",
+    },
+    Code {
+        code: "E0003",
+        title: "type mismatch",
+        explanation: "An operator or callee was given a value of the wrong type, such as \
+            subtracting a string or calling a number.",
+        example: "print 1 - \"one\";",
+    },
+    Code {
+        code: "E0004",
+        title: "wrong number of arguments",
+        explanation: "A function or class was called with a different number of arguments \
+            than it declares parameters. A class takes the parameters of its `init` method.",
+        example: "fun add(a, b) { return a + b; }\nprint add(1);",
+    },
+    Code {
+        code: "E0005",
+        title: "undefined property",
+        explanation: "The property is neither a field assigned to the instance nor a \
+            method of its class or superclasses. Fields exist only once assigned.",
+        example: "class Point {}\nprint Point().x;",
+    },
+    Code {
+        code: "E0006",
+        title: "division by zero",
+        explanation: "A number was divided by zero while strict division is enabled.",
+        example: "print 1 / 0;",
+    },
+    Code {
+        code: "E0007",
+        title: "interrupted",
+        explanation: "Execution was stopped by an interrupt, such as ctrl+c at the REPL.",
+        example: "while (true) {}",
+    },
+    Code {
+        code: "E0008",
+        title: "stack overflow",
+        explanation: "Calls were nested past the maximum depth, usually by recursion \
+            without a base case. The error lists the calls, innermost first.",
+        example: "fun forever() { return forever(); }\nforever();",
+    },
+    Code {
+        code: "E0009",
+        title: "invalid argument",
+        explanation: "A native function rejected one of its arguments, such as an \
+            unknown format specifier or a value it cannot convert.",
+        example: "print hash(clock);",
+    },
+    Code {
+        code: "E0010",
+        title: "module not found",
+        explanation: "An imported module was not found in the script's directory or any \
+            import path. The error lists every path tried. Add paths with `-I dir`, \
+            `LOX_PATH`, or the manifest's `path`.",
+        example: "import \"missing\";",
+    },
+    Code {
+        code: "E0011",
+        title: "superclass must be a class",
+        explanation: "A class may only inherit from another class.",
+        example: "var NotAClass = 1;\nclass A < NotAClass {}",
+    },
+    Code {
+        code: "E0101",
+        title: "local read in its own initializer",
+        explanation: "A local variable's initializer refers to the variable itself, which \
+            is not yet defined. Rename one of them if an outer variable was meant.",
+        example: "{\n  var a = a + 1;\n}",
+    },
+    Code {
+        code: "E0102",
+        title: "return outside function",
+        explanation: "A `return` statement appears in top-level code, outside of any \
+            function or method.",
+        example: "return 1;",
+    },
+    Code {
+        code: "E0103",
+        title: "return value from initializer",
+        explanation: "An `init` method always returns the instance, so it may only use a \
+            bare `return;`.",
+        example: "class A {\n  init() { return 1; }\n}",
+    },
+    Code {
+        code: "E0104",
+        title: "this outside class",
+        explanation: "`this` refers to the instance a method was called on, so may only \
+            be used within a method.",
+        example: "print this;",
+    },
+    Code {
+        code: "E0105",
+        title: "super outside class",
+        explanation: "`super` refers to a superclass's method, so may only be used within \
+            a method.",
+        example: "print super.init;",
+    },
+    Code {
+        code: "E0106",
+        title: "super without superclass",
+        explanation: "`super` was used in a method of a class that does not inherit from \
+            another class.",
+        example: "class A {\n  f() { return super.f(); }\n}",
+    },
+    Code {
+        code: "E0107",
+        title: "class inherits from itself",
+        explanation: "A class cannot be its own superclass.",
+        example: "class A < A {}",
+    },
+    Code {
+        code: "E0108",
+        title: "local already declared",
+        explanation: "A local variable was declared twice in the same scope. Assign to the \
+            existing variable instead, or declare the second in a nested block.",
+        example: "fun f() {\n  var a = 1;\n  var a = 2;\n}",
+    },
+    Code {
+        code: "E0109",
+        title: "wrong number of arguments to a local function",
+        explanation: "A function declared in the same scope was called with a different \
+            number of arguments than it declares parameters. This is checked before \
+            running, unless the function is reassigned.",
+        example: "fun f() {\n  fun g(a) {}\n  g(1, 2);\n}",
+    },
+    Code {
+        code: "E0110",
+        title: "declaration must be at the top level",
+        explanation: "Imports and exports may only appear in a file's top-level code, \
+            outside of any block or function.",
+        example: "{\n  import \"greet\";\n}",
+    },
+    Code {
+        code: "E0201",
+        title: "unexpected token",
+        explanation: "The parser expected a particular token, such as a closing \
+            parenthesis or a semicolon, and found another. The message names both.",
+        example: "print (1 + 2;",
+    },
+    Code {
+        code: "E0202",
+        title: "invalid assignment target",
+        explanation: "Only variables and properties may be assigned to.",
+        example: "1 + 2 = 3;",
+    },
+    Code {
+        code: "E0203",
+        title: "too many arguments or parameters",
+        explanation: "Functions are limited to 8 parameters, and calls to 8 arguments.",
+        example: "f(1, 2, 3, 4, 5, 6, 7, 8, 9);",
+    },
+    Code {
+        code: "E0204",
+        title: "too deeply nested",
+        explanation: "Expressions and statements nested past the parser's maximum depth, \
+            256 by default, are rejected rather than risking a stack overflow.",
+        example: "// nested past the default maximum depth of 256\n\
+            print ((((((((((((((((((((((((((((((((((((((((((((((((((((\n\
+            ((((((((((((((((((((((((((((((((((((((((((((((((((((\n\
+            ((((((((((((((((((((((((((((((((((((((((((((((((((((\n\
+            ((((((((((((((((((((((((((((((((((((((((((((((((((((\n\
+            ((((((((((((((((((((((((((((((((((((((((((((((((((((\n\
+            1\n\
+            ))))))))))))))))))))))))))))))))))))))))))))))))))))\n\
+            ))))))))))))))))))))))))))))))))))))))))))))))))))))\n\
+            ))))))))))))))))))))))))))))))))))))))))))))))))))))\n\
+            ))))))))))))))))))))))))))))))))))))))))))))))))))))\n\
+            ))))))))))))))))))))))))))))))))))))))))))))))))))));",
+    },
+    Code {
+        code: "E0205",
+        title: "declaration in a control flow body",
+        explanation: "The body of an `if`, `while`, or `for` may not be a bare \
+            declaration, as it would only be in scope for that body. Enclose it in a \
+            block. Imports and exports are rejected likewise, as they may only appear \
+            at the top level.",
+        example: "if (true) var a = 1;",
+    },
+    Code {
+        code: "E0206",
+        title: "token not allowed here",
+        explanation: "An expression was expected, but the token found cannot begin one.",
+        example: "print );",
+    },
+    Code {
+        code: "E0301",
+        title: "unexpected character",
+        explanation: "The character is not part of Lox's syntax, outside of a string or \
+            comment.",
+        example: "var a = 1 @ 2;",
+    },
+    Code {
+        code: "E0302",
+        title: "unterminated string",
+        explanation: "A string literal was not closed before the end of the file.",
+        example: "print \"hello;",
+    },
+    Code {
+        code: "E0303",
+        title: "invalid number",
+        explanation: "A numeric literal could not be read as a number, such as one with \
+            two decimal points.",
+        example: "print 1.2.3;",
+    },
+    Code {
+        code: "E0304",
+        title: "invalid UTF-8",
+        explanation: "Source files must be UTF-8 encoded. The error names the offending \
+            byte.",
+        example: "print \"\\xFF\"; // saved as a raw 0xFF byte",
+    },
+    Code {
+        code: "W0101",
+        title: "shadowed declaration",
+        explanation: "A local declaration hides a local variable or parameter of the \
+            same name from an enclosing scope. Globals may be shadowed freely. \
+            Reported with `--warn`; silence with `--allow=shadowing`.",
+        example: "fun f(a) {\n  {\n    var a = 2;\n  }\n}",
+    },
+    Code {
+        code: "W0102",
+        title: "dead store",
+        explanation: "A value assigned to a local is overwritten by another assignment \
+            before it is read. Reported with `--warn`; silence with \
+            `--allow=dead-stores`.",
+        example: "fun f() {\n  var a;\n  a = 1;\n  a = 2;\n  print a;\n}",
+    },
+    Code {
+        code: "W0103",
+        title: "unreachable statement",
+        explanation: "A statement follows an unconditional `return` or `break`, so never \
+            runs. Reported with `--warn`; silence with `--allow=unreachable`.",
+        example: "fun f() {\n  return 1;\n  print \"never\";\n}",
+    },
+    Code {
+        code: "W0104",
+        title: "constant condition",
+        explanation: "An `if` or `while` condition is built solely from literals, so \
+            always takes the same branch. Reported with `--warn`; silence with \
+            `--allow=constant-conditions`.",
+        example: "if (1 > 2) print \"never\";",
+    },
+];
+
+/// Returns the code, ignoring case, if it exists.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::codes;
+/// # fn main() {
+/// assert_eq!("return outside function", codes::find("e0102").unwrap().title);
+/// assert!(codes::find("E9999").is_none());
+/// # }
+/// ```
+pub fn find(code: &str) -> Option<&'static Code> {
+    CODES.iter().find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+/// Returns the code of the error, if it has one. Usage, IO, manifest, and
+/// uncommon runtime errors do not. A denied warning has its lint's code.
+pub fn of_error(e: &Error) -> Option<&'static Code> {
+    let code = match *e {
+        Error::Usage | Error::IO(_) => return None,
+        Error::Lexical(_, ref kind, _) => match *kind {
+            LexicalKind::UnexpectedCharacter => "E0301",
+            LexicalKind::UnterminatedString => "E0302",
+            LexicalKind::InvalidNumber => "E0303",
+            LexicalKind::InvalidUtf8(_) => "E0304",
+        },
+        Error::Parse(_, ref kind, _) => match *kind {
+            ParseKind::InvalidSuperclass => "E0011",
+            ParseKind::OwnInitializer => "E0101",
+            ParseKind::TopLevelReturn => "E0102",
+            ParseKind::InitializerReturn => "E0103",
+            ParseKind::ThisOutsideClass => "E0104",
+            ParseKind::SuperOutsideClass => "E0105",
+            ParseKind::SuperWithoutSuperclass => "E0106",
+            ParseKind::InheritsFromItself => "E0107",
+            ParseKind::AlreadyDeclared => "E0108",
+            ParseKind::ArityMismatch { .. } => "E0109",
+            ParseKind::NotTopLevel(_) => "E0110",
+            ParseKind::Expected { .. } => "E0201",
+            ParseKind::InvalidAssignment => "E0202",
+            ParseKind::TooManyArguments(_) => "E0203",
+            ParseKind::TooDeeplyNested(_) => "E0204",
+            ParseKind::UnenclosedDeclaration(_) => "E0205",
+            ParseKind::ExpectedExpression(_) | ParseKind::UnexpectedToken => "E0206",
+            ParseKind::Denied(lint, _) => of_lint(lint),
+            ParseKind::Other(_) => return None,
+        },
+        Error::Runtime(_, ref kind, _) => match *kind {
+            RuntimeKind::UndefinedVariable { .. } => "E0001",
+            RuntimeKind::AlreadyDefined(_) => "E0002",
+            RuntimeKind::TypeMismatch { .. } => "E0003",
+            RuntimeKind::ArityMismatch { .. } => "E0004",
+            RuntimeKind::PropertyNotFound { .. } => "E0005",
+            RuntimeKind::DivisionByZero => "E0006",
+            RuntimeKind::Interrupted => "E0007",
+            RuntimeKind::StackOverflow(_) => "E0008",
+            RuntimeKind::InvalidArgument(_) => "E0009",
+            RuntimeKind::ModuleNotFound { .. } => "E0010",
+            RuntimeKind::Other(_) => return None,
+        },
+    };
+
+    find(code)
+}
+
+/// Returns the code of the warning's lint.
+pub fn of_warning(w: &Warning) -> &'static Code {
+    find(of_lint(w.lint)).expect("every lint has a code")
+}
+
+fn of_lint(lint: Lint) -> &'static str {
+    match lint {
+        Lint::Shadowing => "W0101",
+        Lint::DeadStores => "W0102",
+        Lint::Unreachable => "W0103",
+        Lint::ConstantConditions => "W0104",
+    }
+}
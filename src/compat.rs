@@ -0,0 +1,38 @@
+//! A module describing the language semantics followed by the interpreter.
+
+/// Compat selects between the reference semantics of Lox, as described in
+/// Crafting Interpreters, and the extensions made by rlox.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::Compat;
+/// # fn main() {
+/// assert_eq!(Some(Compat::Lox), Compat::from_name("lox"));
+/// assert_eq!(Compat::Rlox, Compat::default());
+/// assert_eq!(None, Compat::from_name("clox"));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Compat {
+    /// Only `nil` and `false` are falsey, `+` requires two numbers or two
+    /// strings, and REPL lines are not automatically terminated with a
+    /// semicolon.
+    Lox,
+    /// `0` and `""` are also falsey, `+` concatenates when either operand is
+    /// a string, and the REPL terminates lines with a semicolon when missing.
+    #[default]
+    Rlox,
+}
+
+impl Compat {
+    /// Returns the Compat matching its command line name, if any.
+    pub fn from_name(name: &str) -> Option<Compat> {
+        match name {
+            "lox" => Some(Compat::Lox),
+            "rlox" => Some(Compat::Rlox),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! A module describing the detection, pruning, and folding of constant
+//! expressions.
+//!
+//! Expressions built solely from literals have no side effects, so they are
+//! evaluated ahead of time with the interpreter that will later run them.
+
+use ast::expr::Expr;
+use ast::fold::{self, Folder};
+use ast::stmt::Stmt;
+use ast::token::{Literal, Token, Type};
+use interpreter::Interpreter;
+use object::Object;
+
+/// Returns true if the expression consists only of literals and operators.
+pub fn is_constant(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Literal(_) => true,
+        Expr::Grouping(ref inside) | Expr::Unary(_, ref inside) => is_constant(inside),
+        Expr::Binary(ref lhs, _, ref rhs) => is_constant(lhs) && is_constant(rhs),
+        _ => false,
+    }
+}
+
+/// Returns the truthiness of a constant condition, or None if the condition
+/// is not constant or would fail to evaluate.
+pub fn condition(i: &mut Interpreter, cond: &Expr) -> Option<bool> {
+    if !is_constant(cond) {
+        return None;
+    }
+
+    cond.accept(i).ok().map(|obj| i.is_truthy(&obj))
+}
+
+/// Removes branches and loops that constant conditions prevent from ever
+/// running.
+pub fn prune(i: &mut Interpreter, stmt: Stmt) -> Stmt {
+    Pruner(i).fold_stmt(stmt)
+}
+
+/// Replaces constant expressions with the literals they evaluate to.
+/// Expressions that would fail to evaluate, or that produce numbers without
+/// a literal form, are left to fail or evaluate at runtime.
+pub fn fold(i: &mut Interpreter, stmt: Stmt) -> Stmt {
+    ConstantFolder(i).fold_stmt(stmt)
+}
+
+struct Pruner<'a>(&'a mut Interpreter);
+
+impl<'a> Folder for Pruner<'a> {
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::If(cond, then, els) => match condition(self.0, &cond) {
+                Some(true) => self.fold_stmt(*then),
+                Some(false) => els.map_or(Stmt::Empty, |s| self.fold_stmt(*s)),
+                None => fold::fold_stmt(self, Stmt::If(cond, then, els)),
+            },
+            Stmt::While(cond, body) => match condition(self.0, &cond) {
+                Some(false) => Stmt::Empty,
+                _ => fold::fold_stmt(self, Stmt::While(cond, body)),
+            },
+            stmt => fold::fold_stmt(self, stmt),
+        }
+    }
+}
+
+struct ConstantFolder<'a>(&'a mut Interpreter);
+
+impl<'a> Folder for ConstantFolder<'a> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        if let Expr::Literal(_) = expr {
+            return expr;
+        }
+
+        if !is_constant(&expr) {
+            return fold::fold_expr(self, expr);
+        }
+
+        let lit = match expr.accept(self.0) {
+            Ok(Object::Literal(Literal::Number(n))) if !n.is_finite() => return expr,
+            Ok(Object::Literal(ref lit)) => lit.clone(),
+            _ => return expr,
+        };
+
+        let typ = match lit {
+            Literal::Nil => Type::Nil,
+            Literal::Boolean(true) => Type::True,
+            Literal::Boolean(false) => Type::False,
+            Literal::Number(_) => Type::Number,
+            Literal::String(_) => Type::String,
+        };
+
+        let lexeme = match lit {
+            Literal::String(ref s) => format!("\"{}\"", s),
+            ref lit => format!("{}", lit),
+        };
+
+        Expr::Literal(Token {
+            typ,
+            lexeme,
+            literal: Some(lit),
+            ..expr.token().clone()
+        })
+    }
+}
@@ -0,0 +1,88 @@
+//! A module describing UTC calendar dates for the time natives.
+
+use std::fmt::Write;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// A UTC date and time broken into its calendar components.
+#[derive(Debug, PartialEq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millisecond: u32,
+    /// Day of the week, where Sunday is 0
+    pub weekday: u32,
+}
+
+impl DateTime {
+    /// Converts milliseconds since the Unix epoch into a UTC DateTime.
+    pub fn from_millis(ms: f64) -> DateTime {
+        let ms = ms.floor() as i64;
+        let days = ms.div_euclid(MS_PER_DAY);
+        let rem = ms.rem_euclid(MS_PER_DAY) as u32;
+
+        let (year, month, day) = civil_from_days(days);
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: rem / 3_600_000,
+            minute: rem / 60_000 % 60,
+            second: rem / 1000 % 60,
+            millisecond: rem % 1000,
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+
+    /// Formats the DateTime with a strftime-like format string, supporting
+    /// `%Y`, `%m`, `%d`, `%H`, `%M`, `%S`, `%L` (milliseconds), and `%%`.
+    ///
+    /// Returns the offending character if an unknown specifier is found.
+    pub fn format(&self, fmt: &str) -> ::std::result::Result<String, char> {
+        let mut out = String::with_capacity(fmt.len());
+        let mut chars = fmt.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+
+            let _ = match chars.next() {
+                Some('Y') => write!(out, "{:04}", self.year),
+                Some('m') => write!(out, "{:02}", self.month),
+                Some('d') => write!(out, "{:02}", self.day),
+                Some('H') => write!(out, "{:02}", self.hour),
+                Some('M') => write!(out, "{:02}", self.minute),
+                Some('S') => write!(out, "{:02}", self.second),
+                Some('L') => write!(out, "{:03}", self.millisecond),
+                Some('%') => write!(out, "%"),
+                Some(c) => return Err(c),
+                None => return Err('%'),
+            };
+        }
+
+        Ok(out)
+    }
+}
+
+// Converts days since the Unix epoch into a (year, month, day) triple.
+// See: http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
@@ -1,6 +1,3 @@
-use std::time::SystemTime;
-
-#[macro_export]
 macro_rules! debug_drop {
     ( $x:expr $(, $y:expr)* ) => {
         if cfg!(feature = "debug-destructors") {
@@ -9,7 +6,6 @@ macro_rules! debug_drop {
     }
 }
 
-#[macro_export]
 macro_rules! debug_create {
     ($x:expr $(, $y:expr)*) => {
         if cfg!(feature = "debug-constructors") {
@@ -18,7 +14,6 @@ macro_rules! debug_create {
     }
 }
 
-#[macro_export]
 macro_rules! debug_define {
     ($x:expr $(, $y:expr)*) => {
         if cfg!(feature = "debug-define") {
@@ -27,7 +22,6 @@ macro_rules! debug_define {
     }
 }
 
-#[macro_export]
 macro_rules! debug_assign {
     ($x:expr $(, $y:expr)*) => {
         if cfg!(feature = "debug-assign") {
@@ -35,27 +29,3 @@ macro_rules! debug_assign {
         }
     }
 }
-
-pub fn time<F, T>(id: &str, func: F) -> T
-    where F: FnOnce() -> T
-{
-    if !cfg!(feature = "debug-timings") {
-        return func();
-    }
-
-    let start = SystemTime::now();
-    let out = func();
-
-    let elapsed = start.elapsed().expect("could not get elapsed time");
-    let dur = match (elapsed.as_secs(), elapsed.subsec_nanos()) {
-        (s, ns) if s > 0 => format!("{}s{}ms", s, ns/1_000_000),
-        (_, ns) if ns < 1000 => format!("{}ns", ns),
-        (_, ns) if ns < 1_000_000 => format!("{}µs{}ns", ns/1000, ns % 1000),
-        (_, ns) => format!("{}ms{}µs", ns/1_000_000, (ns % 1_000_000) / 1000),
-    };
-
-    eprintln!("\x1B[1;31m[TIME] {}: {}\x1B[0m", id, dur);
-
-    out
-}
-
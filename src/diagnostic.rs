@@ -0,0 +1,161 @@
+//! A module describing the rendering of errors and warnings for display.
+
+use std::path::Path;
+
+use codes;
+use json::write_string;
+use lint::{Severity, Warning};
+use result::{Error, RuntimeKind};
+
+/// The format diagnostics are written to stderr in
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    /// One line per diagnostic, as in `Parse Error [line 3] ...`
+    #[default]
+    Human,
+    /// One JSON object per line, with `file`, `line`, `severity`, `code`,
+    /// `kind`, `message`, `near`, and `help` keys. Missing values are null.
+    Json,
+}
+
+impl Format {
+    /// Returns the Format matching its command line name, if any.
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "human" => Some(Format::Human),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Diagnostic is an error or warning, along with the file it arose in.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use std::path::Path;
+/// # use rlox::{Error, RuntimeKind};
+/// # use rlox::diagnostic::{Diagnostic, Format};
+/// # fn main() {
+/// let e = Error::Runtime(2, RuntimeKind::UndefinedVariable {
+///     name: "countr".to_owned(),
+///     suggestion: Some("counter".to_owned()),
+/// }, "countr".to_owned());
+/// let d = Diagnostic::from_error(&e, Some(Path::new("main.lox")));
+///
+/// assert_eq!(format!("{}", e), d.render(Format::Human));
+/// assert_eq!(
+///     r#"{"file":"main.lox","line":2,"severity":"error","code":"E0001","kind":"runtime","message":"variable `countr` is undefined","near":"countr","help":"did you mean `counter`?"}"#,
+///     d.render(Format::Json));
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub severity: Severity,
+    /// The stable code identifying the diagnostic, as explained by
+    /// `rlox explain`, if it has one
+    pub code: Option<&'static str>,
+    /// The phase that raised the diagnostic: `lexical`, `parse`, `runtime`,
+    /// or `lint`, or `io` and `usage` for those outside the source
+    pub kind: &'static str,
+    pub message: String,
+    pub near: Option<String>,
+    /// A suggested fix, if any
+    pub help: Option<String>,
+    /// The diagnostic as formatted for people
+    human: String,
+}
+
+impl Diagnostic {
+    pub fn from_error(e: &Error, file: Option<&Path>) -> Diagnostic {
+        let (kind, message, near, help) = match *e {
+            Error::Usage => ("usage", format!("{}", e), None, None),
+            Error::IO(ref e) => ("io", format!("{}", e), None, None),
+            Error::Lexical(_, ref kind, ref whence) => ("lexical", format!("{}", kind), Some(whence.to_owned()), None),
+            Error::Parse(_, ref kind, ref near) => ("parse", format!("{}", kind), Some(near.to_owned()), None),
+            Error::Runtime(_, ref kind, ref near) => {
+                let (kind, help) = suggestion(kind);
+                ("runtime", format!("{}", kind), Some(near.to_owned()), help)
+            }
+        };
+
+        Diagnostic {
+            file: file.map(|f| f.display().to_string()),
+            line: e.line(),
+            severity: Severity::Error,
+            code: codes::of_error(e).map(|c| c.code),
+            kind,
+            message,
+            near,
+            help,
+            human: format!("{}", e),
+        }
+    }
+
+    pub fn from_warning(w: &Warning, file: Option<&Path>) -> Diagnostic {
+        Diagnostic {
+            file: file.map(|f| f.display().to_string()),
+            line: Some(w.line),
+            severity: w.severity,
+            code: Some(codes::of_warning(w).code),
+            kind: "lint",
+            message: w.msg.to_owned(),
+            near: Some(w.near.to_owned()),
+            help: None,
+            human: format!("{}", w),
+        }
+    }
+
+    /// Renders the diagnostic as a single line in the format.
+    pub fn render(&self, fmt: Format) -> String {
+        if fmt == Format::Human {
+            return self.human.to_owned();
+        }
+
+        let mut out = String::from("{\"file\":");
+        optional(&mut out, self.file.as_deref());
+        out.push_str(",\"line\":");
+        match self.line {
+            Some(line) => out.push_str(&line.to_string()),
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"severity\":");
+        write_string(&mut out, &format!("{}", self.severity).to_lowercase());
+        out.push_str(",\"code\":");
+        optional(&mut out, self.code);
+        out.push_str(",\"kind\":");
+        write_string(&mut out, self.kind);
+        out.push_str(",\"message\":");
+        write_string(&mut out, &self.message);
+        out.push_str(",\"near\":");
+        optional(&mut out, self.near.as_deref());
+        out.push_str(",\"help\":");
+        optional(&mut out, self.help.as_deref());
+        out.push('}');
+        out
+    }
+}
+
+/// Splits any suggested name from the kind, returning it as help.
+fn suggestion(kind: &RuntimeKind) -> (RuntimeKind, Option<String>) {
+    match *kind {
+        RuntimeKind::UndefinedVariable { ref name, suggestion: Some(ref s) } => (
+            RuntimeKind::UndefinedVariable { name: name.to_owned(), suggestion: None },
+            Some(format!("did you mean `{}`?", s))),
+        RuntimeKind::PropertyNotFound { ref name, suggestion: Some(ref s) } => (
+            RuntimeKind::PropertyNotFound { name: name.to_owned(), suggestion: None },
+            Some(format!("did you mean `{}`?", s))),
+        ref kind => (kind.clone(), None),
+    }
+}
+
+fn optional(out: &mut String, s: Option<&str>) {
+    match s {
+        Some(s) => write_string(out, s),
+        None => out.push_str("null"),
+    }
+}
@@ -0,0 +1,257 @@
+//! A module describing the pretty-printer for runtime values.
+
+use ast::token::Literal;
+use class::LoxInstance;
+use functions::Callable;
+use object::Object;
+
+/// The maximum line length before an instance is broken over multiple lines
+const INLINE_WIDTH: usize = 60;
+
+/// Printer renders Objects for display, expanding instances into their fields.
+///
+/// Nested instances are indented, instances already being printed are marked
+/// as cycles, and output is truncated past the configured depth and number
+/// of fields.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::display::Printer;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::object::Object;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ast::token::Literal;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let p = Printer::default();
+/// assert_eq!("hello", p.print(&Object::Literal(Literal::String("hello".into()))));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Printer {
+    /// The number of nested instances to expand before eliding their fields
+    pub max_depth: usize,
+    /// The number of fields to display per instance before truncating
+    pub max_width: usize,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Printer {
+            max_depth: 4,
+            max_width: 16,
+        }
+    }
+}
+
+impl Printer {
+    /// Renders the Object as it should appear when printed. Top-level strings
+    /// are not quoted, while nested values are.
+    pub fn print(&self, obj: &Object) -> String {
+        match *obj {
+            Object::Literal(ref lit) => format!("{}", lit),
+            _ => self.render(obj, 0, &mut Vec::new()),
+        }
+    }
+
+    /// Renders a detailed view of the Object: an instance's class and fields,
+    /// a class's superclasses and methods (including those inherited), or a
+    /// function's parameters and captured variables. Used by the REPL's
+    /// `:inspect` command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate rlox;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::display::Printer;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::object::Object;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::ast::token::Literal;
+    /// # #[cfg(not(feature = "internals"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "internals")]
+    /// # fn main() {
+    /// let p = Printer::default();
+    /// assert_eq!("string \"hi\"", p.inspect(&Object::Literal(Literal::String("hi".into()))));
+    /// # }
+    /// ```
+    pub fn inspect(&self, obj: &Object) -> String {
+        match *obj {
+            Object::Literal(_) => format!("{} {}", obj.type_name(), self.render(obj, 0, &mut Vec::new())),
+            Object::Instance(ref inst) => {
+                let mut out = format!("instance of {}", inst.class());
+                out.push_str(&self.section("fields", &inst.snapshot_fields()));
+                out
+            }
+            Object::Class(ref cls) => {
+                let mut out = format!("class {}", cls.name());
+                let mut methods: Vec<String> = Vec::new();
+                let mut seen: Vec<String> = Vec::new();
+                let mut ancestors: Vec<&str> = Vec::new();
+
+                let mut next = Some(cls);
+                while let Some(c) = next {
+                    for name in c.method_names() {
+                        if seen.contains(&name) {
+                            continue;
+                        }
+
+                        if let Some(m) = c.find_method(&name) {
+                            methods.push(signature(&m));
+                        }
+                        seen.push(name);
+                    }
+
+                    next = c.parent();
+                    if let Some(p) = next {
+                        ancestors.push(p.name());
+                    }
+                }
+
+                if !ancestors.is_empty() {
+                    out.push_str(&format!("\n  superclasses: {}", ancestors.join(" < ")));
+                }
+
+                out.push_str("\n  methods:");
+                if methods.is_empty() {
+                    out.push_str(" none");
+                }
+                for m in methods {
+                    out.push_str(&format!("\n    {}", m));
+                }
+
+                out
+            }
+            Object::Func(ref f) if f.params().is_none() =>
+                format!("native function {} with arity {}", f.name(), f.arity()),
+            Object::Func(ref f) => {
+                let mut out = format!("function {}", signature(f));
+                out.push_str(&self.section("captured", &f.captured()));
+                out
+            }
+        }
+    }
+
+    /// Renders the documentation of a function or class: its signature,
+    /// followed by the text of its doc comment, indented. Instances are
+    /// documented by their class, and natives by the interpreter. Used by the
+    /// REPL's `:help` command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate rlox;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::display::Printer;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::object::Object;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::ast::token::Literal;
+    /// # #[cfg(not(feature = "internals"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "internals")]
+    /// # fn main() {
+    /// let p = Printer::default();
+    /// assert_eq!("number 1\n  no documentation", p.help(&Object::Literal(Literal::Number(1.0))));
+    /// # }
+    /// ```
+    pub fn help(&self, obj: &Object) -> String {
+        let (heading, doc) = match *obj {
+            Object::Class(ref cls) => (format!("class {}", cls.name()), cls.doc()),
+            Object::Instance(ref inst) => (format!("instance of {}", inst.class_name()), inst.class().doc()),
+            Object::Func(ref f) if f.params().is_none() =>
+                (format!("native function {} with arity {}", f.name(), f.arity()), f.doc()),
+            Object::Func(ref f) => (format!("function {}", signature(f)), f.doc()),
+            Object::Literal(_) => (format!("{} {}", obj.type_name(), self.render(obj, 0, &mut Vec::new())), None),
+        };
+
+        let doc: Vec<String> = doc.unwrap_or("no documentation").lines()
+            .map(|l| if l.is_empty() { String::new() } else { format!("  {}", l) })
+            .collect();
+
+        format!("{}\n{}", heading, doc.join("\n"))
+    }
+
+    /// Renders the named values on their own indented lines
+    fn section(&self, title: &str, vals: &[(String, Object)]) -> String {
+        if vals.is_empty() {
+            return format!("\n  {}: none", title);
+        }
+
+        let mut out = format!("\n  {}:", title);
+        for (k, v) in vals {
+            let val = self.render(v, 0, &mut Vec::new()).replace('\n', "\n    ");
+            out.push_str(&format!("\n    {} = {}", k, val));
+        }
+
+        out
+    }
+
+    fn render(&self, obj: &Object, depth: usize, seen: &mut Vec<LoxInstance>) -> String {
+        match *obj {
+            Object::Literal(Literal::String(ref s)) => format!("{:?}", s),
+            Object::Instance(ref inst) => self.render_instance(inst, depth, seen),
+            ref x => format!("{}", x),
+        }
+    }
+
+    fn render_instance(&self, inst: &LoxInstance, depth: usize, seen: &mut Vec<LoxInstance>) -> String {
+        if seen.iter().any(|i| i.is(inst)) {
+            return format!("<cycle {}>", inst);
+        }
+
+        let fields = inst.snapshot_fields();
+
+        if fields.is_empty() {
+            return format!("{}", inst);
+        }
+
+        if depth >= self.max_depth {
+            return format!("{} {{ ... }}", inst);
+        }
+
+        seen.push(inst.clone());
+
+        let mut parts: Vec<String> = fields.iter()
+            .take(self.max_width)
+            .map(|(k, v)| format!("{}: {}", k, self.render(v, depth + 1, seen)))
+            .collect();
+
+        seen.pop();
+
+        if fields.len() > self.max_width {
+            parts.push(format!("... ({} more)", fields.len() - self.max_width));
+        }
+
+        let inline = format!("{} {{ {} }}", inst, parts.join(", "));
+        if inline.len() <= INLINE_WIDTH && !inline.contains('\n') {
+            return inline;
+        }
+
+        let mut out = format!("{} {{\n", inst);
+        for part in parts {
+            for line in part.lines() {
+                out.push_str("  ");
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.pop();
+            out.push_str(",\n");
+        }
+        out.push('}');
+
+        out
+    }
+}
+
+/// Renders the function's name and parameters, as in `Point.init(x, y)`
+fn signature(f: &Callable) -> String {
+    format!("{}({})", f.name(), f.params().unwrap_or_default().join(", "))
+}
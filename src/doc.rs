@@ -0,0 +1,77 @@
+//! A module describing the rendering of doc comments as Markdown, as printed
+//! by `rlox doc`.
+
+use ast::printer::AstPrinter;
+use ast::stmt::Stmt;
+use ast::token::Token;
+
+/// Renders the top-level functions and classes of a script, along with their
+/// methods, as a Markdown reference. Declarations are listed in source order,
+/// whether documented or not.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::doc::markdown;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::parser::StmtIterator;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::scanner::TokenIterator;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let src = "/// Adds the numbers.\nfun add(a, b) { return a + b; }";
+/// let stmts: Vec<_> = src.chars().tokens().statements()
+///     .collect::<Result<_, _>>().unwrap();
+///
+/// assert_eq!("# math\n\n## fun add(a, b)\n\nAdds the numbers.\n", markdown("math", &stmts));
+/// # }
+/// ```
+pub fn markdown(title: &str, stmts: &[Stmt]) -> String {
+    let mut out = format!("# {}\n", title);
+
+    for stmt in stmts {
+        let decl = match *stmt {
+            Stmt::Export(_, ref decl) => decl.as_ref(),
+            ref decl => decl,
+        };
+
+        match *decl {
+            Stmt::Function(ref id, ref params, _, _) =>
+                section(&mut out, "##", &format!("fun {}", signature(&id.lexeme, params)), decl),
+            Stmt::Class(ref id, ref parent, ref methods, _) => {
+                let heading = match *parent {
+                    Some(ref p) => format!("class {} < {}", id.lexeme, AstPrinter::default().print_expr(p)),
+                    None => format!("class {}", id.lexeme),
+                };
+                section(&mut out, "##", &heading, decl);
+
+                for m in methods {
+                    if let Stmt::Function(ref name, ref params, _, _) = *m {
+                        let name = format!("{}.{}", id.lexeme, name.lexeme);
+                        section(&mut out, "###", &signature(&name, params), m);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    out
+}
+
+fn section(out: &mut String, level: &str, heading: &str, decl: &Stmt) {
+    out.push_str(&format!("\n{} {}\n", level, heading));
+
+    if let Some(doc) = decl.doc() {
+        out.push_str(&format!("\n{}\n", doc));
+    }
+}
+
+fn signature(name: &str, params: &[Token]) -> String {
+    let params: Vec<&str> = params.iter().map(|p| p.lexeme.as_str()).collect();
+    format!("{}({})", name, params.join(", "))
+}
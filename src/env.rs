@@ -5,17 +5,21 @@ use std::cell::RefCell;
 use object::Object;
 use functions::*;
 use ast::token::Token;
+use suggest;
+use stats::Live;
 
 #[derive(Default, Debug)]
 pub struct Env {
     parent: Option<Parent>,
     vals: RefCell<HashMap<String, Object>>,
+    /// Counts this env as alive until dropped, for `rlox --report`
+    _live: Live,
 }
 
 impl Env {
-    pub fn new() -> Rc<Env> {
+    pub fn new(sandbox: bool) -> Rc<Env> {
         let e = Env::init(None);
-        Callable::define_globals(e.as_ref());
+        Callable::define_globals(e.as_ref(), sandbox);
 
         debug_create!("Env::Root");
 
@@ -46,13 +50,15 @@ impl Env {
         e
     }
 
+    /// Defines the variable in this env. Globals may be redefined, matching
+    /// the reference implementation, while locals may not.
     pub fn define(&self, id: &Token, val: Object) -> Result<()> {
         let name = &id.lexeme;
         let mut vals = self.vals.borrow_mut();
 
-        if vals.contains_key(name) {
+        if self.parent.is_some() && vals.contains_key(name) {
             return Err(Error::Runtime(id.line,
-                                      format!("variable `{}` already defined", name),
+                                      RuntimeKind::AlreadyDefined(name.to_owned()),
                                       name.to_owned()));
         }
 
@@ -63,25 +69,37 @@ impl Env {
         Ok(())
     }
 
+    /// Defines the variable in the root env.
+    pub fn define_global(&self, id: &Token, val: Object) -> Result<()> {
+        match self.parent {
+            None => self.define(id, val),
+            Some(ref parent) => parent.define_global(id, val),
+        }
+    }
+
     pub fn assign_at(&self, id: &Token, val: Object, dist: Option<&usize>) -> Result<Object> {
-        if dist.map_or(0, |d| *d) == 0 {
-            return self.assign(id, val);
+        if dist.is_none() {
+            return self.assign_global(id, val).map_err(|e| self.suggest(e));
         }
 
         let d: usize = *dist.unwrap();
 
+        if d == 0 {
+            return self.assign(id, val);
+        }
+
         if let Some(ancestor) = self.ancestor(d) {
             return ancestor.assign(id, val);
         }
 
         Err(Error::Runtime(id.line,
-                           format!("ancestor is undefined at depth {}", d),
+                           RuntimeKind::Other(format!("ancestor is undefined at depth {}", d)),
                            id.lexeme.to_string()))
     }
 
     pub fn get_at(&self, id: &Token, dist: Option<&usize>) -> Result<Object> {
         if dist.is_none() {
-            return self.get_global(id);
+            return self.get_global(id).map_err(|e| self.suggest(e));
         }
 
         let d = *dist.unwrap();
@@ -95,16 +113,66 @@ impl Env {
         }
 
         Err(Error::Runtime(id.line,
-                           format!("ancestor is undefined at depth {}", d),
+                           RuntimeKind::Other(format!("ancestor is undefined at depth {}", d)),
                            id.lexeme.to_string()))
     }
 
+    /// Returns the number of ancestors of this env; the root env has none.
+    pub fn depth(&self) -> usize {
+        match self.parent {
+            Some(ref p) => 1 + p.depth(),
+            None => 0,
+        }
+    }
+
+    /// Returns every variable visible from this env, including globals,
+    /// sorted by name. Shadowed variables are omitted.
+    pub fn flatten(&self) -> Vec<(String, Object)> {
+        let mut vals: Vec<(String, Object)> = self.vals.borrow().iter()
+            .map(|(k, v)| (k.to_owned(), v.clone()))
+            .collect();
+
+        if let Some(ref parent) = self.parent {
+            for (k, v) in parent.flatten() {
+                if !vals.iter().any(|(name, _)| *name == k) {
+                    vals.push((k, v));
+                }
+            }
+        }
+
+        vals.sort_by(|a, b| a.0.cmp(&b.0));
+        vals
+    }
+
     pub fn has_weak(&self) -> bool {
         match self.parent {
             Some(ref p) => p.has_weak(),
             None => false,
         }
     }
+
+    /// Returns the variables visible from this env, excluding globals,
+    /// nearest first. Each env's variables are sorted by name, and shadowed
+    /// variables are omitted.
+    pub fn locals(&self) -> Vec<(String, Object)> {
+        let parent = match self.parent {
+            Some(ref p) => p,
+            None => return Vec::new(),
+        };
+
+        let mut vals: Vec<(String, Object)> = self.vals.borrow().iter()
+            .map(|(k, v)| (k.to_owned(), v.clone()))
+            .collect();
+        vals.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (k, v) in parent.locals() {
+            if !vals.iter().any(|(name, _)| *name == k) {
+                vals.push((k, v));
+            }
+        }
+
+        vals
+    }
 }
 
 impl Env {
@@ -112,9 +180,32 @@ impl Env {
         Rc::new(Env {
             parent,
             vals: RefCell::new(HashMap::new()),
+            _live: Live::env(),
         })
     }
 
+    /// Adds the closest name visible from this env to an undefined variable
+    /// error.
+    fn suggest(&self, e: Error) -> Error {
+        match e {
+            Error::Runtime(line, RuntimeKind::UndefinedVariable { name, suggestion: None }, near) => {
+                let names = self.names();
+                let suggestion = suggest::closest(&name, names.iter().map(String::as_str));
+                Error::Runtime(line, RuntimeKind::UndefinedVariable { name, suggestion }, near)
+            }
+            e => e,
+        }
+    }
+
+    /// Returns the names of the variables in this env and its ancestors.
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.vals.borrow().keys().cloned().collect();
+        if let Some(ref parent) = self.parent {
+            names.extend(parent.names());
+        }
+        names
+    }
+
     fn ancestor(&self, dist: usize) -> Option<Parent> {
         let mut env = self.parent.clone();
 
@@ -129,36 +220,38 @@ impl Env {
         let name = &id.lexeme;
         let mut vals = self.vals.borrow_mut();
 
-        if !vals.contains_key(name) {
-            if let Some(ref parent) = self.parent {
-                return parent.assign(id, val);
-            }
+        // existing slots are overwritten in place to avoid reallocating keys
+        if let Some(slot) = vals.get_mut(name) {
+            debug_assign!("{} => {:?}", name, val);
+            *slot = val.clone();
+            return Ok(val);
+        }
 
-            return Err(Error::Runtime(id.line,
-                                      format!("variable `{}` is undefined", name),
-                                      name.to_owned()));
+        if let Some(ref parent) = self.parent {
+            return parent.assign(id, val);
         }
 
-        debug_assign!("{} => {:?}", name, val);
-        let _ = vals.insert(name.to_owned(), val.clone());
-        Ok(val)
+        Err(Error::Runtime(id.line,
+                           RuntimeKind::UndefinedVariable { name: name.to_owned(), suggestion: None },
+                           name.to_owned()))
     }
 
+    /// Reads the variable. Objects are reference counted handles, so this
+    /// does not allocate.
     fn get(&self, id: &Token) -> Result<Object> {
         let name = &id.lexeme;
-        let vals = self.vals.borrow();
 
-        if !vals.contains_key(name) {
-            if let Some(ref parent) = self.parent {
-                return parent.get(id);
-            }
+        if let Some(val) = self.vals.borrow().get(name) {
+            return Ok(val.clone());
+        }
 
-            return Err(Error::Runtime(id.line,
-                                      format!("variable `{}` is undefined", name),
-                                      name.to_string()));
+        if let Some(ref parent) = self.parent {
+            return parent.get(id);
         }
 
-        Ok(vals.get(name).cloned().unwrap())
+        Err(Error::Runtime(id.line,
+                           RuntimeKind::UndefinedVariable { name: name.to_owned(), suggestion: None },
+                           name.to_string()))
     }
 
     fn get_global(&self, id: &Token) -> Result<Object> {
@@ -167,6 +260,13 @@ impl Env {
             Some(ref parent) => parent.get_global(id),
         }
     }
+
+    fn assign_global(&self, id: &Token, val: Object) -> Result<Object> {
+        match self.parent {
+            None => self.assign(id, val),
+            Some(ref parent) => parent.assign_global(id, val),
+        }
+    }
 }
 
 #[cfg(feature = "debug-destructors")]
@@ -197,24 +297,40 @@ enum Parent {
     Weak(Weak<Env>),
 }
 
+/// Calls the method on the parent env, evaluating to the fallback if the
+/// parent was weakly referenced and has since been dropped.
 macro_rules! parent_call {
-    ($self:ident$(.$member:ident)+ $(, $arg:expr)* ) => {
+    ($self:ident$(.$member:ident)+ $(, $arg:expr)* ; $dropped:expr) => {
         match *$self {
             Parent::Strong(ref e) => e$(.$member)+($($arg,)*),
             Parent::Weak(ref w) => match w.upgrade() {
                 Some(ref e) => e$(.$member)+($($arg,)*),
-                None => panic!("parent env went out of scope"),
+                None => $dropped,
             }
         }
     };
 }
 
+/// The error for a variable looked up through a dropped parent env. This
+/// should not occur, as the root env outlives the closures it holds, but an
+/// embedder holding a closure's env past its interpreter could trigger it.
+fn dropped(id: &Token) -> Error {
+    Error::Runtime(id.line,
+                   RuntimeKind::Other(format!("cannot access `{}`: its enclosing env was dropped", id.lexeme)),
+                   id.lexeme.to_owned())
+}
 
 impl Parent {
-    fn parent(&self) -> Option<Parent> { parent_call!(self.parent.clone) }
-    fn assign(&self, id: &Token, val: Object) -> Result<Object> { parent_call!(self.assign, id, val) }
-    fn get(&self, id: &Token) -> Result<Object> { parent_call!(self.get, id) }
-    fn get_global(&self, id: &Token) -> Result<Object> { parent_call!(self.get_global, id) }
+    fn parent(&self) -> Option<Parent> { parent_call!(self.parent.clone; None) }
+    fn assign(&self, id: &Token, val: Object) -> Result<Object> { parent_call!(self.assign, id, val; Err(dropped(id))) }
+    fn get(&self, id: &Token) -> Result<Object> { parent_call!(self.get, id; Err(dropped(id))) }
+    fn get_global(&self, id: &Token) -> Result<Object> { parent_call!(self.get_global, id; Err(dropped(id))) }
+    fn define_global(&self, id: &Token, val: Object) -> Result<()> { parent_call!(self.define_global, id, val; Err(dropped(id))) }
+    fn assign_global(&self, id: &Token, val: Object) -> Result<Object> { parent_call!(self.assign_global, id, val; Err(dropped(id))) }
+    fn locals(&self) -> Vec<(String, Object)> { parent_call!(self.locals; Vec::new()) }
+    fn names(&self) -> Vec<String> { parent_call!(self.names; Vec::new()) }
+    fn depth(&self) -> usize { parent_call!(self.depth; 0) }
+    fn flatten(&self) -> Vec<(String, Object)> { parent_call!(self.flatten; Vec::new()) }
 
     fn refs(&self) -> usize {
         match *self {
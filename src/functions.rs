@@ -1,18 +1,24 @@
-use interpreter::Interpreter;
+use interpreter::{ControlFlow, Interpreter};
 use object::Object;
 use result::Result;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use ast::token::Token;
-use ast::token::Type as TokenType;
-use ast::token::Literal::{Number, Nil};
+use ast::token::Literal::Number;
+use ast::token::Literal;
 use ast::stmt::Stmt;
 use env::Env;
 use std::rc::Rc;
-use result::Error;
+use result::{Error, RuntimeKind};
 use class::{LoxInstance, THIS_ID, LoxClass};
+use datetime::DateTime;
+use json;
+use output::Writer;
+use stats::{self, Counter};
 use std::fmt;
+use compat::Compat;
 
 pub const INITIALIZER_FUNC: &str = "init";
+pub const VALIDATE_FUNC: &str = "validate";
+pub const FINALIZE_FUNC: &str = "finalize";
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Type {
@@ -30,9 +36,12 @@ pub enum Callable {
 }
 
 impl Callable {
-    pub fn new(env: Rc<Env>, params: &[Token], body: &Rc<Stmt>, init: bool) -> Callable {
-        debug_create!("LoxFunction with arity {}", params.len());
-        Callable::Runtime(LoxFunction::new(env, params, body, init))
+    /// Creates a function declared in the env. Methods are named after
+    /// their class, as in `Point.init`. The function follows the semantics
+    /// it was declared with, whatever those of its callers.
+    pub fn new(env: Rc<Env>, name: &str, params: &[Token], body: &Rc<Stmt>, init: bool, doc: Option<&Rc<str>>, compat: Compat) -> Callable {
+        debug_create!("LoxFunction {} with arity {}", name, params.len());
+        Callable::Runtime(LoxFunction::new(env, name.into(), params.into(), body, init, doc.cloned(), compat))
     }
 
     pub fn init(cls: &Rc<LoxClass>) -> Callable {
@@ -40,15 +49,45 @@ impl Callable {
         Callable::Initializer(InitFunction(Rc::clone(cls)))
     }
 
-    pub fn define_globals(env: &Env) {
-        let clock = Object::Func(Callable::Static(StaticFunction::clock()));
-        env.define(&CLOCK_ID, clock).expect("unable to attach clock()");
+    /// Defines the native functions in the provided env. If sandboxed, natives
+    /// that block or observe the wall clock beyond `clock()` are omitted.
+    pub fn define_globals(env: &Env, sandbox: bool) {
+        let mut natives = vec![
+            StaticFunction::clock(),
+            StaticFunction::random(),
+            StaticFunction::eprint(),
+            StaticFunction::json_parse(),
+            StaticFunction::json_stringify(),
+            StaticFunction::hash(),
+        ];
+
+        if !sandbox {
+            natives.push(StaticFunction::sleep());
+            natives.push(StaticFunction::now());
+            natives.push(StaticFunction::format_time());
+        }
+
+        for f in natives {
+            let id = Token::identifier(f.name);
+
+            env.define(&id, Object::Func(Callable::Static(f)))
+                .expect("unable to attach native function");
+        }
+    }
+
+    /// Defines the natives that modify classes at runtime in the provided
+    /// env. These are only defined if monkey patching is enabled.
+    pub fn define_patching(env: &Env) {
+        let f = StaticFunction::define_method();
+
+        env.define(&Token::identifier(f.name), Object::Func(Callable::Static(f)))
+            .expect("unable to attach native function");
     }
 
     pub fn call(&self, int: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
         match *self {
             Callable::Runtime(ref f) => f.call(int, args),
-            Callable::Static(ref f) => f.call(int, args),
+            Callable::Static(ref f) => f.call(int, args, paren),
             Callable::Initializer(ref cls) => cls.call(int, args, paren),
         }
     }
@@ -61,6 +100,48 @@ impl Callable {
         }
     }
 
+    /// The name of the function as reported in errors. Calling a class
+    /// calls its initializer, so it is named after the `init` method if one
+    /// is declared, or the class otherwise.
+    pub fn name(&self) -> String {
+        match *self {
+            Callable::Runtime(ref f) => f.name.to_string(),
+            Callable::Static(ref f) => f.name.to_owned(),
+            Callable::Initializer(ref cls) => cls.name(),
+        }
+    }
+
+    /// The names of the function's parameters, or None for natives, whose
+    /// parameters are unnamed.
+    pub fn params(&self) -> Option<Vec<String>> {
+        match *self {
+            Callable::Runtime(ref f) => Some(f.params.iter().map(|p| p.lexeme.to_owned()).collect()),
+            Callable::Static(_) => None,
+            Callable::Initializer(ref cls) => Some(cls.0.find_method(INITIALIZER_FUNC)
+                .and_then(|m| m.params())
+                .unwrap_or_default()),
+        }
+    }
+
+    /// The text of the function's doc comment, if any. Calling a class is
+    /// documented by the class.
+    pub fn doc(&self) -> Option<&str> {
+        match *self {
+            Callable::Runtime(ref f) => f.doc.as_deref(),
+            Callable::Static(ref f) => Some(f.doc),
+            Callable::Initializer(ref cls) => cls.0.doc(),
+        }
+    }
+
+    /// The variables captured by the function, excluding globals. See
+    /// `Env::locals`.
+    pub fn captured(&self) -> Vec<(String, Object)> {
+        match *self {
+            Callable::Runtime(ref f) => f.scope.locals(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn bind(&self, inst: &LoxInstance) -> Callable {
         match *self {
             Callable::Runtime(ref f) => Callable::Runtime(f.bind(inst)),
@@ -69,49 +150,94 @@ impl Callable {
     }
 }
 
+/// Functions are equal only to themselves: the same declaration closed over
+/// the same scope and bound to the same instance, if any, the same native, or
+/// the same class's initializer.
+impl PartialEq for Callable {
+    fn eq(&self, other: &Callable) -> bool {
+        match (self, other) {
+            (Callable::Runtime(l), Callable::Runtime(r)) =>
+                Rc::ptr_eq(&l.body, &r.body) && Rc::ptr_eq(&l.scope, &r.scope) && match (&l.this, &r.this) {
+                    (Some(l), Some(r)) => l.is(r),
+                    (None, None) => true,
+                    _ => false,
+                },
+            (Callable::Static(l), Callable::Static(r)) => l.name == r.name,
+            (Callable::Initializer(l), Callable::Initializer(r)) => Rc::ptr_eq(&l.0, &r.0),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Callable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Callable::Static(ref s) => write!(f, "<native fn {}>", s.name),
+            _ => write!(f, "<fn {}>", self.name()),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct LoxFunction {
     scope: Rc<Env>,
-    params: Vec<Token>,
+    name: Rc<str>,
+    params: Rc<[Token]>,
     body: Rc<Stmt>,
     initializer: bool,
+    doc: Option<Rc<str>>,
+    compat: Compat,
+    /// The instance a method is bound to, defined as `this` alongside the
+    /// parameters of each call. Binding allocates nothing, so accessing a
+    /// method is cheap whether or not it is called.
+    this: Option<LoxInstance>,
 }
 
 impl LoxFunction {
-    fn new(scope: Rc<Env>, params: &[Token], body: &Rc<Stmt>, init: bool) -> LoxFunction {
+    fn new(scope: Rc<Env>, name: Rc<str>, params: Rc<[Token]>, body: &Rc<Stmt>, init: bool, doc: Option<Rc<str>>, compat: Compat) -> LoxFunction {
+        stats::record(Counter::Function);
         LoxFunction {
             scope,
-            params: params.to_owned(),
+            name,
+            params,
             body: Rc::clone(body),
             initializer: init,
+            doc,
+            compat,
+            this: None,
         }
     }
 
     fn bind(&self, inst: &LoxInstance) -> LoxFunction {
-        let scope = Env::from(&self.scope);
-
-        scope.define(&THIS_ID, Object::Instance(inst.clone()))
-            .expect("failed to define `this`");
-
-        LoxFunction::new(scope, &self.params, &self.body, self.initializer)
+        let mut f = self.clone();
+        f.this = Some(inst.clone());
+        f
     }
 
     fn arity(&self) -> usize { self.params.len() }
 
     fn call(&self, int: &Interpreter, args: &[Object]) -> Result<Object> {
         let env = Env::from(&self.scope);
-        let zip = (&self.params).into_iter().zip(args.into_iter());
+
+        if let Some(ref inst) = self.this {
+            env.define(&THIS_ID, Object::Instance(inst.clone()))?;
+        }
+
+        let zip = self.params.iter().zip(args.iter());
 
         for (param, arg) in zip {
             env.define(param, arg.clone())?;
         }
 
-        match self.body.accept(&mut int.with_env(env)) {
-            Ok(()) | Err(Error::Return(_, _)) if self.initializer =>
-                self.scope.get_at(&THIS_ID, Some(&0)),
-            Ok(()) => Ok(Object::Literal(Nil)),
-            Err(Error::Return(_, res)) => Ok(res),
-            Err(e) => Err(e),
+        let mut int = int.with_env(env);
+        int.set_compat(self.compat);
+
+        match self.body.accept(&mut int) {
+            Ok(()) | Err(ControlFlow::Return(_, _)) if self.initializer =>
+                Ok(self.this.clone().map_or(Object::NIL, Object::Instance)),
+            Ok(()) => Ok(Object::NIL),
+            Err(ControlFlow::Return(_, res)) => Ok(res),
+            Err(flow) => Err(flow.into_error()),
         }
     }
 }
@@ -142,6 +268,15 @@ impl InitFunction {
             method.bind(&inst).call(int, args, paren)?;
         }
 
+        // runs once init completes, so an error abandons the instance
+        if let Some(validate) = inst.method(VALIDATE_FUNC) {
+            int.call(&Object::Func(validate), &[], paren)?;
+        }
+
+        if self.0.find_method(FINALIZE_FUNC).is_some() {
+            int.track_finalizer(&inst, paren);
+        }
+
         Ok(Object::Instance(inst))
     }
 
@@ -149,6 +284,11 @@ impl InitFunction {
         self.0.find_method(INITIALIZER_FUNC)
             .map_or(0, |m| m.arity())
     }
+
+    fn name(&self) -> String {
+        self.0.find_method(INITIALIZER_FUNC)
+            .map_or_else(|| self.0.name().to_owned(), |m| m.name())
+    }
 }
 
 #[cfg(feature = "debug-destructors")]
@@ -160,25 +300,83 @@ impl Drop for InitFunction {
 
 #[derive(Clone)]
 pub struct StaticFunction {
-    name: String,
+    name: &'static str,
     _arity: usize,
-    func: fn(&Interpreter, &[Object]) -> Result<Object>,
+    func: NativeFn,
+    doc: &'static str,
 }
 
+/// The signature of a native. Natives may call back into Lox through
+/// `Interpreter::call`, but must not hold a borrow of an instance's fields or
+/// a writer across the call, as the callback may need it.
+type NativeFn = fn(&Interpreter, &[Object], &Token) -> Result<Object>;
+
 impl StaticFunction {
-    fn new(name: &str, arity: usize, func: fn(&Interpreter, &[Object]) -> Result<Object>) -> StaticFunction {
+    fn new(name: &'static str, arity: usize, func: NativeFn, doc: &'static str) -> StaticFunction {
         debug_create!("StaticFunction {}", name);
         StaticFunction {
-            name: name.to_owned(),
+            name,
             _arity: arity,
             func,
+            doc,
         }
     }
 
-    fn clock() -> StaticFunction { StaticFunction::new("clock", 0, clock) }
+    fn clock() -> StaticFunction {
+        StaticFunction::new("clock", 0, clock,
+                            "Returns the milliseconds elapsed since the Unix epoch.")
+    }
 
-    fn call(&self, int: &Interpreter, args: &[Object]) -> Result<Object> {
-        (self.func)(int, args)
+    fn random() -> StaticFunction {
+        StaticFunction::new("random", 0, random,
+                            "Returns a random number between 0, inclusive, and 1, exclusive.")
+    }
+
+    fn eprint() -> StaticFunction {
+        StaticFunction::new("eprint", 1, eprint, "Prints the value to stderr.")
+    }
+
+    fn sleep() -> StaticFunction {
+        StaticFunction::new("sleep", 1, sleep, "Blocks for the number of milliseconds.")
+    }
+
+    fn now() -> StaticFunction {
+        StaticFunction::new("now", 0, now,
+                            "Returns a DateTime of the current UTC time, with year, month, day, hour,\n\
+                             minute, second, millisecond, weekday, and epoch fields.")
+    }
+
+    fn format_time() -> StaticFunction {
+        StaticFunction::new("formatTime", 2, format_time,
+                            "Formats the milliseconds since the Unix epoch using strftime-style\n\
+                             specifiers, such as `%Y-%m-%d`.")
+    }
+
+    fn json_parse() -> StaticFunction {
+        StaticFunction::new("jsonParse", 1, json_parse,
+                            "Parses a JSON string into Lox values. Objects become instances, and\n\
+                             arrays become Lists.")
+    }
+
+    fn json_stringify() -> StaticFunction {
+        StaticFunction::new("jsonStringify", 1, json_stringify,
+                            "Renders the value as a JSON string.")
+    }
+
+    fn hash() -> StaticFunction {
+        StaticFunction::new("hash", 1, hash,
+                            "Returns a hash of the value, equal for equal values. Functions and\n\
+                             classes cannot be hashed.")
+    }
+
+    fn define_method() -> StaticFunction {
+        StaticFunction::new("defineMethod", 3, define_method,
+                            "Defines the function as the named method of the class, replacing any\n\
+                             method of the same name. Existing instances see the change.")
+    }
+
+    fn call(&self, int: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+        (self.func)(int, args, paren)
     }
 
     fn arity(&self) -> usize { self._arity }
@@ -197,21 +395,151 @@ impl Drop for StaticFunction {
     }
 }
 
-lazy_static! {
-    pub static ref CLOCK_ID : Token = Token {
-        typ: TokenType::Identifier,
-        lexeme: "clock".to_owned(),
-        ..Token::default()
+fn clock(int: &Interpreter, _: &[Object], _: &Token) -> Result<Object> {
+    Ok(Object::Literal(Number(int.host().now())))
+}
+
+fn random(int: &Interpreter, _: &[Object], _: &Token) -> Result<Object> {
+    Ok(Object::Literal(Number(int.host().random())))
+}
+
+fn eprint(int: &Interpreter, args: &[Object], _: &Token) -> Result<Object> {
+    Writer::writeln(int.stderr(), &format!("{}", args[0]))?;
+    Ok(Object::NIL)
+}
+
+fn sleep(int: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+    match args[0] {
+        Object::Literal(Number(ms)) if ms >= 0.0 => {
+            int.host().sleep(ms);
+            Ok(Object::NIL)
+        }
+        ref x => Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::InvalidArgument("sleep expects a non-negative number of milliseconds".to_owned()),
+            format!("{}", x))),
+    }
+}
+
+fn now(int: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+    let ms = match clock(int, args, paren)? {
+        Object::Literal(Number(ms)) => ms,
+        _ => unreachable!(),
     };
+
+    let dt = DateTime::from_millis(ms);
+    let num = |n: u32| Object::Literal(Number(f64::from(n)));
+
+    Ok(Object::Instance(LoxInstance::record("DateTime", vec![
+        ("epoch", Object::Literal(Number(ms))),
+        ("year", Object::Literal(Number(dt.year as f64))),
+        ("month", num(dt.month)),
+        ("day", num(dt.day)),
+        ("hour", num(dt.hour)),
+        ("minute", num(dt.minute)),
+        ("second", num(dt.second)),
+        ("millisecond", num(dt.millisecond)),
+        ("weekday", num(dt.weekday)),
+    ])))
 }
 
-#[cfg_attr(feature = "cargo-clippy", allow(cast_lossless))]
-fn clock(_: &Interpreter, _: &[Object]) -> Result<Object> {
-    let dur: Duration = SystemTime::now().
-        duration_since(UNIX_EPOCH).expect("time went backwards");
+fn format_time(_: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+    let (ms, fmt) = match (&args[0], &args[1]) {
+        (&Object::Literal(Number(ms)), &Object::Literal(Literal::String(ref fmt))) => (ms, fmt),
+        (x, y) => return Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::InvalidArgument("formatTime expects a number of milliseconds and a format string".to_owned()),
+            format!("{}, {}", x, y))),
+    };
+
+    match DateTime::from_millis(ms).format(fmt) {
+        Ok(s) => Ok(Object::Literal(Literal::String(s.into()))),
+        Err(c) => Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::InvalidArgument(format!("unknown format specifier '%{}'", c)),
+            fmt.to_string())),
+    }
+}
+
+fn json_parse(int: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+    let src = match args[0] {
+        Object::Literal(Literal::String(ref s)) => s,
+        ref x => return Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::InvalidArgument("jsonParse expects a string".to_owned()),
+            format!("{}", x))),
+    };
 
-    let ms: f64 = dur.as_secs() as f64 * 1e3 +
-        dur.subsec_nanos() as f64 / 1e6;
+    // errors raised by List are returned as is, rather than as invalid JSON
+    let mut failed = None;
+    let res = json::parse(src, &mut |elems| {
+        let cls = int.global(json::LIST_CLASS)
+            .ok_or_else(|| format!("arrays require the standard library's {}", json::LIST_CLASS))?;
 
-    Ok(Object::Literal(Number(ms)))
+        list_of(int, &cls, elems, paren).map_err(|e| {
+            failed = Some(e);
+            String::new()
+        })
+    });
+
+    if let Some(e) = failed {
+        return Err(e);
+    }
+
+    res.map_err(|msg| Error::Runtime(
+        paren.line,
+        RuntimeKind::InvalidArgument(format!("invalid JSON: {}", msg)),
+        paren.lexeme.to_owned()))
+}
+
+/// Creates a list by calling the List class, then pushing each element.
+fn list_of(int: &Interpreter, cls: &Object, elems: Vec<Object>, paren: &Token) -> Result<Object> {
+    let list = int.call(cls, &[], paren)?;
+    let push = match list {
+        Object::Instance(ref inst) => inst.get(&Token::identifier("push"))?,
+        ref x => return Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::InvalidArgument(format!("{} is not a class of lists", json::LIST_CLASS)),
+            format!("{}", x))),
+    };
+
+    for e in elems {
+        int.call(&push, &[e], paren)?;
+    }
+
+    Ok(list)
+}
+
+fn json_stringify(_: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+    json::stringify(&args[0])
+        .map(|s| Object::Literal(Literal::String(s.into())))
+        .map_err(|msg| Error::Runtime(paren.line, RuntimeKind::InvalidArgument(msg), paren.lexeme.to_owned()))
+}
+
+fn hash(_: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+    match args[0].hash_code() {
+        // keep the top 53 bits, so the hash is exactly representable
+        Some(h) => Ok(Object::Literal(Number((h >> 11) as f64))),
+        None => Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::InvalidArgument(format!("cannot hash a {}", args[0].type_name())),
+            format!("{}", args[0]))),
+    }
+}
+
+fn define_method(_: &Interpreter, args: &[Object], paren: &Token) -> Result<Object> {
+    match (&args[0], &args[1], &args[2]) {
+        (Object::Class(cls), Object::Literal(Literal::String(name)), Object::Func(Callable::Runtime(f))) => {
+            // a method taken from another instance is rebound on each access
+            let mut method = f.clone();
+            method.initializer = name.as_ref() == INITIALIZER_FUNC;
+            method.this = None;
+            cls.define_method(name, Callable::Runtime(method));
+            Ok(Object::NIL)
+        }
+        (x, y, z) => Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::InvalidArgument("defineMethod expects a class, a method name, and a Lox function".to_owned()),
+            format!("{}, {}, {}", x, y, z))),
+    }
 }
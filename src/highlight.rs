@@ -0,0 +1,180 @@
+//! A module describing syntax highlighting of Lox source.
+//!
+//! Source is scanned losslessly, so highlighted output preserves the original
+//! whitespace and comments exactly.
+
+use std::fmt::Write;
+
+use ast::token::{Lexeme, Type};
+use json;
+use result::Result;
+use scanner::Scanner;
+
+/// The output formats supported by the highlighter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Source wrapped in a `<pre>` with a classed `<span>` per token
+    Html,
+    /// Source colored with ANSI terminal escapes
+    Ansi,
+    /// A JSON list of classified spans, for use by editors
+    Json,
+}
+
+impl Format {
+    /// Returns the Format matching its command line name, if any.
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "html" => Some(Format::Html),
+            "ansi" => Some(Format::Ansi),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The highlighting class of a token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Class {
+    Keyword,
+    String,
+    Number,
+    Identifier,
+    Comment,
+    Operator,
+}
+
+impl Class {
+    /// Classifies the token type, returning None for types without any
+    /// source text to highlight.
+    pub fn of(typ: Type) -> Option<Class> {
+        use ast::token::Type::*;
+
+        let class = match typ {
+            And | Class | Else | False | Fun | For | If | Nil | Or | Print |
+            Return | Super | This | True | Var | While | Break | Import | Export => Self::Keyword,
+            String => Self::String,
+            Number => Self::Number,
+            Identifier => Self::Identifier,
+            Comment | DocComment => Self::Comment,
+            Eof => return None,
+            _ => Self::Operator,
+        };
+
+        Some(class)
+    }
+
+    /// The lowercase name of the class
+    pub fn name(self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::String => "string",
+            Class::Number => "number",
+            Class::Identifier => "identifier",
+            Class::Comment => "comment",
+            Class::Operator => "operator",
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Class::Keyword => "\x1b[35m",
+            Class::String => "\x1b[32m",
+            Class::Number => "\x1b[33m",
+            Class::Identifier => "",
+            Class::Comment => "\x1b[90m",
+            Class::Operator => "\x1b[36m",
+        }
+    }
+}
+
+/// Highlights the source in the requested format, returning the first
+/// lexical error encountered, if any.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::highlight::*;
+/// # fn main() {
+/// let html = highlight("print 1;", Format::Html).unwrap();
+/// assert_eq!(concat!(
+///     r#"<pre class="lox"><span class="keyword">print</span> "#,
+///     r#"<span class="number">1</span><span class="operator">;</span></pre>"#,
+/// ), html);
+///
+/// let spans = highlight("x", Format::Json).unwrap();
+/// assert_eq!(
+///     r#"[{"class":"identifier","start":0,"end":1,"line":1,"text":"x"}]"#,
+///     spans);
+/// # }
+/// ```
+pub fn highlight(src: &str, fmt: Format) -> Result<String> {
+    let mut out = String::new();
+
+    if fmt == Format::Html { out.push_str(r#"<pre class="lox">"#); }
+    if fmt == Format::Json { out.push('['); }
+
+    for (idx, res) in Scanner::new(src.chars()).lossless().enumerate() {
+        let lex: Lexeme = res?;
+
+        match fmt {
+            Format::Html => html(&mut out, &lex),
+            Format::Ansi => ansi(&mut out, &lex),
+            Format::Json => {
+                if Class::of(lex.token.typ).is_none() { continue; }
+                if idx > 0 { out.push(','); }
+                span(&mut out, &lex);
+            }
+        }
+    }
+
+    if fmt == Format::Html { out.push_str("</pre>"); }
+    if fmt == Format::Json { out.push(']'); }
+
+    Ok(out)
+}
+
+fn html(out: &mut String, lex: &Lexeme) {
+    escape_html(out, lex.trivia);
+
+    if let Some(class) = Class::of(lex.token.typ) {
+        let _ = write!(out, r#"<span class="{}">"#, class.name());
+        escape_html(out, lex.text);
+        out.push_str("</span>");
+    }
+}
+
+fn escape_html(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn ansi(out: &mut String, lex: &Lexeme) {
+    out.push_str(lex.trivia);
+
+    match Class::of(lex.token.typ).map(Class::ansi) {
+        Some("") | None => out.push_str(lex.text),
+        Some(color) => {
+            out.push_str(color);
+            out.push_str(lex.text);
+            out.push_str("\x1b[0m");
+        }
+    }
+}
+
+fn span(out: &mut String, lex: &Lexeme) {
+    let class = Class::of(lex.token.typ).map_or("", Class::name);
+
+    let _ = write!(out, r#"{{"class":"{}","start":{},"end":{},"line":{},"text":"#,
+                   class, lex.span.start, lex.span.end, lex.token.line);
+    json::write_string(out, lex.text);
+    out.push('}');
+}
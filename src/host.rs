@@ -0,0 +1,115 @@
+//! A module describing the sources of time and randomness available to
+//! natives.
+//!
+//! Both are held by the interpreter rather than read from global state, so
+//! runs can be made reproducible by fixing the seed and virtualizing the
+//! clock.
+
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Mixed into seeds so small seeds still produce well-distributed output
+const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// The source of time observed by `clock()`, `now()`, and `sleep()`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Clock {
+    /// The wall clock; `sleep` blocks the thread
+    #[default]
+    System,
+    /// A clock starting at the provided milliseconds since the Unix epoch,
+    /// which only moves forward when `sleep` is called
+    Virtual(f64),
+}
+
+/// Host holds the clock and random number generator shared by an interpreter
+/// and all of its children.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::host::{Clock, Host};
+/// # fn main() {
+/// let h = Host::new(Clock::Virtual(1000.0), Some(42));
+/// h.sleep(500.0);
+/// assert_eq!(1500.0, h.now());
+///
+/// let x = h.random();
+/// assert!(x >= 0.0 && x < 1.0);
+/// assert_eq!(x, Host::new(Clock::System, Some(42)).random());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Host {
+    clock: Option<Cell<f64>>,
+    state: Cell<u64>,
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Host::new(Clock::System, None)
+    }
+}
+
+impl Host {
+    /// Creates a Host with the clock and RNG seed. If no seed is provided,
+    /// one is derived from the wall clock.
+    pub fn new(clock: Clock, seed: Option<u64>) -> Host {
+        let clock = match clock {
+            Clock::System => None,
+            Clock::Virtual(ms) => Some(Cell::new(ms)),
+        };
+
+        let seed = seed.unwrap_or_else(|| system_millis().to_bits());
+
+        // xorshift requires a non-zero state
+        let state = match seed ^ MIX {
+            0 => MIX,
+            s => s,
+        };
+
+        Host {
+            clock,
+            state: Cell::new(state),
+        }
+    }
+
+    /// Returns the current time in milliseconds since the Unix epoch
+    pub fn now(&self) -> f64 {
+        match self.clock {
+            Some(ref ms) => ms.get(),
+            None => system_millis(),
+        }
+    }
+
+    /// Blocks for the provided milliseconds, or advances a virtual clock
+    /// without blocking.
+    pub fn sleep(&self, ms: f64) {
+        match self.clock {
+            Some(ref now) => now.set(now.get() + ms),
+            None => thread::sleep(Duration::from_millis(ms as u64)),
+        }
+    }
+
+    /// Returns the next pseudo-random number in the range [0, 1)
+    pub fn random(&self) -> f64 {
+        // xorshift64*
+        let mut x = self.state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state.set(x);
+
+        let n = x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (n >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn system_millis() -> f64 {
+    let dur: Duration = SystemTime::now().
+        duration_since(UNIX_EPOCH).expect("time went backwards");
+
+    dur.as_secs() as f64 * 1e3 + dur.subsec_nanos() as f64 / 1e6
+}
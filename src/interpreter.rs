@@ -1,24 +1,142 @@
 use std::cmp::PartialOrd;
 use std::collections::HashMap;
+use std::mem;
 use std::rc::Rc;
 
 use ast::expr::{Expr, Visitor as ExprVisitor};
 use ast::stmt::{Stmt, Visitor as StmtVisitor};
 use ast::token::{Token, Literal};
 
-use class::{LoxClass, SUPER_ID, THIS_ID};
+use class::{Finalizers, LoxClass, LoxInstance, SUPER_ID, THIS_ID};
 use env::Env;
-use functions::{Callable, INITIALIZER_FUNC};
+use functions::{Callable, FINALIZE_FUNC, INITIALIZER_FUNC};
 use object::Object;
-use result::{Result, Error};
+use result::{Result, Error, ParseKind, RuntimeKind};
 use output::Writer;
-use std::cell::RefCell;
+use display::Printer;
+use host::{Clock, Host};
+use interrupt::Interrupt;
+use compat::Compat;
+use ordered::OrderedMap;
+use stats::{self, Counter};
+use std::cell::{Cell, RefCell};
+
+/// The default maximum depth of nested calls. Calls are evaluated
+/// recursively, so deeper nesting risks overflowing the native stack. The
+/// default holds on a 2MB stack, that of spawned threads, even in debug
+/// builds; embedders running on larger stacks may raise it.
+pub const MAX_CALL_DEPTH: usize = 100;
+
+/// InterpreterOptions configures a root Interpreter and all of its children.
+/// Callers override only the options they need, taking the rest from the
+/// defaults.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::Compat;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::interpreter::{InterpreterOptions, MAX_CALL_DEPTH};
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let opts = InterpreterOptions { compat: Compat::Lox, seed: Some(7), ..InterpreterOptions::default() };
+/// assert!(!opts.repl && !opts.sandbox);
+/// assert_eq!(MAX_CALL_DEPTH, opts.max_call_depth);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterpreterOptions {
+    /// Prints the value of each expression statement, as at the REPL
+    pub repl: bool,
+    /// Omits natives that block or read the wall clock (beyond `clock()`)
+    pub sandbox: bool,
+    /// The clock observed by natives
+    pub clock: Clock,
+    /// The seed of `random()`. Without one, it is derived from the wall clock.
+    pub seed: Option<u64>,
+    /// The maximum depth of nested calls, past which a call fails with a
+    /// stack overflow rather than exhausting the native stack
+    pub max_call_depth: usize,
+    /// Makes dividing by zero a runtime error, rather than producing an
+    /// infinity or NaN per IEEE semantics
+    pub strict_division: bool,
+    /// The language semantics followed
+    pub compat: Compat,
+    /// Defines the `defineMethod` native, which adds or replaces methods on
+    /// existing classes. Meant for tests and exploring at the REPL.
+    pub monkey_patching: bool,
+}
+
+impl Default for InterpreterOptions {
+    fn default() -> Self {
+        InterpreterOptions {
+            repl: false,
+            sandbox: false,
+            clock: Clock::default(),
+            seed: None,
+            max_call_depth: MAX_CALL_DEPTH,
+            strict_division: false,
+            compat: Compat::default(),
+            monkey_patching: false,
+        }
+    }
+}
+
+/// ControlFlow describes why execution of a statement ended early. Only
+/// `Error` represents a failure; `Break` and `Return` unwind to the enclosing
+/// loop or function call.
+#[derive(Debug)]
+pub(crate) enum ControlFlow {
+    /// A runtime failure
+    Error(Error),
+    /// A `break` statement on the provided line
+    Break(u64),
+    /// A `return` statement on the provided line, with its value
+    Return(u64, Object),
+}
+
+impl From<Error> for ControlFlow {
+    fn from(err: Error) -> ControlFlow {
+        ControlFlow::Error(err)
+    }
+}
+
+impl ControlFlow {
+    /// Converts the control flow into an Error, for when it escapes the
+    /// construct it is meant to unwind to.
+    pub fn into_error(self) -> Error {
+        match self {
+            ControlFlow::Error(e) => e,
+            ControlFlow::Break(line) => Error::Runtime(
+                line,
+                RuntimeKind::Other("unexpected break statement".to_owned()),
+                "break".to_owned()),
+            ControlFlow::Return(line, _) => Error::Runtime(
+                line,
+                RuntimeKind::Other("unexpected return statement".to_owned()),
+                "return".to_owned()),
+        }
+    }
+}
+
+/// The outcome of executing a statement
+pub(crate) type Exec = ::std::result::Result<(), ControlFlow>;
 
 pub struct Interpreter {
     env: Rc<Env>,
     locals: Rc<HashMap<Expr, usize>>,
-    repl: bool,
+    options: InterpreterOptions,
     stdout: Rc<RefCell<Writer>>,
+    stderr: Rc<RefCell<Writer>>,
+    printer: Printer,
+    host: Rc<Host>,
+    interrupt: Interrupt,
+    finalizers: Finalizers,
+    calls: Rc<Cell<usize>>,
 }
 
 #[cfg(feature = "debug-destructors")]
@@ -33,15 +151,22 @@ impl Drop for Interpreter {
 
 
 impl Interpreter {
-    pub fn new(repl: bool, stdout: Rc<RefCell<Writer>>) -> Interpreter {
+    /// Creates a root Interpreter configured by the options.
+    pub fn new(options: InterpreterOptions, stdout: Rc<RefCell<Writer>>, stderr: Rc<RefCell<Writer>>) -> Interpreter {
         let i = Interpreter {
-            repl,
-            env: Env::new(),
+            env: Interpreter::globals(&options),
             locals: Rc::new(HashMap::new()),
+            options,
             stdout,
+            stderr,
+            printer: Printer::default(),
+            host: Rc::new(Host::new(options.clock, options.seed)),
+            interrupt: Interrupt::default(),
+            finalizers: Rc::default(),
+            calls: Rc::default(),
         };
 
-        debug_create!("Interpreter::Root (REPL: {})", i.repl);
+        debug_create!("Interpreter::Root (REPL: {}, sandbox: {})", options.repl, options.sandbox);
 
         i
     }
@@ -51,12 +176,196 @@ impl Interpreter {
         Interpreter {
             env,
             locals: Rc::clone(&self.locals),
-            repl: self.repl,
+            options: self.options,
             stdout: Rc::clone(&self.stdout),
+            stderr: Rc::clone(&self.stderr),
+            printer: self.printer,
+            host: Rc::clone(&self.host),
+            interrupt: self.interrupt.clone(),
+            finalizers: Rc::clone(&self.finalizers),
+            calls: Rc::clone(&self.calls),
+        }
+    }
+
+    /// Creates a root env holding the natives allowed by the options.
+    pub fn globals(options: &InterpreterOptions) -> Rc<Env> {
+        let env = Env::new(options.sandbox);
+
+        if options.monkey_patching {
+            Callable::define_patching(&env);
+        }
+
+        env
+    }
+
+    /// Replaces the env statements are run in, returning the previous one.
+    /// Imported modules are run in their own root env, so their globals are
+    /// kept apart from those of the importer.
+    pub(crate) fn replace_env(&mut self, env: Rc<Env>) -> Rc<Env> {
+        mem::replace(&mut self.env, env)
+    }
+
+    /// The clock and random number generator used by natives
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+
+    /// Replaces the clock and random number generator used by natives
+    pub fn set_host(&mut self, host: Host) {
+        self.host = Rc::new(host);
+    }
+
+    /// Replaces the flag checked before each loop iteration and function
+    /// call, stopping execution with a runtime error once triggered.
+    pub fn set_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt = interrupt;
+    }
+
+    /// The options the root interpreter was created with
+    pub fn options(&self) -> &InterpreterOptions {
+        &self.options
+    }
+
+    /// Switches the semantics followed by the interpreter. Functions already
+    /// declared keep the semantics they were declared with.
+    pub fn set_compat(&mut self, compat: Compat) {
+        self.options.compat = compat;
+    }
+
+    /// Returns the truthiness of the value under the interpreter's semantics
+    pub fn is_truthy(&self, obj: &Object) -> bool {
+        match (self.options.compat, obj) {
+            (Compat::Lox, &Object::Literal(Literal::Nil)) => false,
+            (Compat::Lox, &Object::Literal(Literal::Boolean(b))) => b,
+            (Compat::Lox, _) => true,
+            (Compat::Rlox, obj) => obj.is_truthy(),
+        }
+    }
+
+    /// The writer shared with the `Runner` for diagnostics and `eprint`
+    pub fn stderr(&self) -> &Rc<RefCell<Writer>> {
+        &self.stderr
+    }
+
+    /// Configures how values are rendered by `print` and the REPL
+    pub fn set_printer(&mut self, printer: Printer) {
+        self.printer = printer;
+    }
+
+    /// The printer used to render values by `print` and the REPL
+    pub fn printer(&self) -> Printer {
+        self.printer
+    }
+
+    /// Returns the value of the named global variable, if defined.
+    pub fn global(&self, name: &str) -> Option<Object> {
+        self.env.get_at(&Token::identifier(name), None).ok()
+    }
+
+    /// Defines (or redefines) the named global variable.
+    pub fn define_global(&mut self, name: &str, val: Object) -> Result<()> {
+        let id = Token::identifier(name);
+
+        self.env.define_global(&id, val)
+    }
+
+    /// Evaluates the resolved expression in the interpreter's current env,
+    /// returning its value.
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<Object> {
+        expr.accept(self)
+    }
+
+    /// Executes the resolved statement in the interpreter's current env.
+    /// A `break` or `return` outside of a loop or function is an error.
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<()> {
+        self.exec(stmt).map_err(ControlFlow::into_error)?;
+        self.finalize()
+    }
+
+    /// Registers an instance whose class declares `finalize`, to be called
+    /// once the instance is otherwise unreferenced. Errors raised by
+    /// `finalize` are reported at the token of the call creating it.
+    pub fn track_finalizer(&self, inst: &LoxInstance, tkn: &Token) {
+        inst.finalize_into(&self.finalizers, tkn);
+    }
+
+    /// Calls `finalize` on each instance released since the last statement
+    /// completed. Finalizers run between statements rather than when the
+    /// last reference drops, so they never interrupt other code. This is best
+    /// effort: instances kept alive by a cycle are never finalized.
+    fn finalize(&self) -> Result<()> {
+        // finalizing may release other tracked instances
+        loop {
+            let (inst, tkn) = match self.finalizers.borrow_mut().pop_front() {
+                Some(queued) => queued,
+                None => return Ok(()),
+            };
+
+            if let Some(finalize) = inst.method(FINALIZE_FUNC) {
+                self.call(&Object::Func(finalize), &[], &tkn)?;
+            }
         }
     }
 
-    pub fn resolve(&mut self, b: &Expr, idx: usize) {
+    /// Calls the function or class with already evaluated arguments, as a
+    /// call expression would, reporting errors at the paren token.
+    ///
+    /// Natives and embedders use this to call back into Lox. Only a shared
+    /// reference is needed: each call runs in its own child interpreter that
+    /// shares this one's globals, resolved locals, and output. Re-entrant
+    /// calls also share its call depth, so are limited by its
+    /// `max_call_depth` like any other call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate rlox;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::Object;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::ast::token::{Literal, Token};
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::interpreter::{Interpreter, InterpreterOptions};
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::Writer;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::run::Runner;
+    /// # #[cfg(not(feature = "internals"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "internals")]
+    /// # fn main() {
+    /// # use std::cell::RefCell;
+    /// # use std::io::Cursor;
+    /// # use std::rc::Rc;
+    /// let out = Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    /// let mut r = Runner::new(Rc::clone(&out), Rc::clone(&out));
+    /// let mut i = Interpreter::new(InterpreterOptions::default(), Rc::clone(&out), out);
+    ///
+    /// r.run(&mut i, "fun twice(f, x) { return f(f(x)); }").unwrap();
+    /// r.run(&mut i, "fun inc(n) { return n + 1; }").unwrap();
+    ///
+    /// let twice = r.eval_expression(&mut i, "twice").unwrap();
+    /// let inc = r.eval_expression(&mut i, "inc").unwrap();
+    /// let one = Object::Literal(Literal::Number(1.0));
+    ///
+    /// let res = i.call(&twice, &[inc, one.clone()], &Token::default()).unwrap();
+    /// assert_eq!("3", format!("{}", res));
+    ///
+    /// assert!(i.call(&twice, &[one], &Token::default()).is_err());
+    /// # }
+    /// ```
+    pub fn call(&self, callee: &Object, args: &[Object], paren: &Token) -> Result<Object> {
+        let func = match *callee {
+            Object::Func(ref func) => func.clone(),
+            Object::Class(ref cls) => Callable::init(cls),
+            ref x => return self.err_type("function or class", x.type_name(), paren),
+        };
+
+        self.check_arity(&func, args.len(), paren)?;
+        self.invoke(&func, args, paren)
+    }
+
+    pub(crate) fn resolve(&mut self, b: &Expr, idx: usize) {
         Rc::get_mut(&mut self.locals)
             .expect("should be the only ref given the &mut")
             .insert(b.clone(), idx);
@@ -85,9 +394,9 @@ impl ExprVisitor<Result<Object>> for Interpreter {
         match op.typ {
             Minus => match r {
                 Object::Literal(Number(n)) => Ok(Object::Literal(Number(-n))),
-                _ => self.err_near("cannot negate non-numeric", op, format!("{:?}", r)),
+                _ => self.err_type("number", r.type_name(), op),
             },
-            Bang => Ok(Object::Literal(Boolean(!r.is_truthy()))),
+            Bang => Ok(Object::Literal(Boolean(!self.is_truthy(&r)))),
             _ => self.err_op("erroneous unary operator", op),
         }
     }
@@ -109,83 +418,72 @@ impl ExprVisitor<Result<Object>> for Interpreter {
         let res: Literal = match op.typ {
             Plus => match (l, r) {
                 (ObjLit(Number(ref ln)), ObjLit(Number(ref rn))) => Number(ln + rn),
-                (ObjLit(String(ref ln)), ObjLit(ref r)) => String(format!("{}{}", ln, r)),
-                (ObjLit(ref l), ObjLit(String(ref rn))) => String(format!("{}{}", l, rn)),
-                (ref l, ref r) => return self.err_near(
-                    "cannot add mixed types",
-                    op, format!("{:?} + {:?}", l, r)),
+                (ObjLit(String(ref ln)), ObjLit(String(ref rn))) => String(format!("{}{}", ln, rn).into()),
+                (ref l, ref r) if self.options.compat == Compat::Lox =>
+                    return self.err_types("two numbers or two strings", l, r, op),
+                // either side being a string concatenates the other's display
+                (ObjLit(String(ref ln)), ref r) => String(format!("{}{}", ln, r).into()),
+                (ref l, ObjLit(String(ref rn))) => String(format!("{}{}", l, rn).into()),
+                (ref l, ref r) => return self.err_types("numbers or strings", l, r, op),
             },
             Minus => match (l, r) {
                 (ObjLit(Number(ln)), ObjLit(Number(rn))) => Number(ln - rn),
-                (l, r) => return self.err_near(
-                    "cannot subtract non-numerics",
-                    op, format!("{:?} - {:?}", l, r)),
+                (ref l, ref r) => return self.err_types("numbers", l, r, op),
             },
             Star => match (l, r) {
                 (ObjLit(Number(ln)), ObjLit(Number(rn))) => Number(ln * rn),
-                (l, r) => return self.err_near(
-                    "cannot multiply non-numerics",
-                    op, format!("{:?} * {:?}", l, r)),
+                (ref l, ref r) => return self.err_types("numbers", l, r, op),
             },
             Slash => match (l, r) {
-                (ObjLit(Number(ln)), ObjLit(Number(rn))) if rn == 0.0 => return self.err_near(
-                    "divide by zero",
-                    op, format!("{:?} / {:?}", ln, rn)),
+                (ObjLit(Number(_)), ObjLit(Number(0.0))) if self.options.strict_division => return Err(Error::Runtime(
+                    op.line,
+                    RuntimeKind::DivisionByZero,
+                    op.lexeme.to_owned())),
                 (ObjLit(Number(ln)), ObjLit(Number(rn))) => Number(ln / rn),
-                (l, r) => return self.err_near(
-                    "cannot multiply non-numerics",
-                    op, format!("{:?} * {:?}", l, r)),
+                (ref l, ref r) => return self.err_types("numbers", l, r, op),
             },
             Greater | GreaterEqual | Less | LessEqual => match l.partial_cmp(&r) {
                 Some(Ord::Less) => Boolean(op.in_types(&[Less, LessEqual])),
                 Some(Ord::Equal) => Boolean(op.in_types(&[LessEqual, GreaterEqual])),
                 Some(Ord::Greater) => Boolean(op.in_types(&[Greater, GreaterEqual])),
-                None => return self.err_near(
-                    "cannot compare types",
-                    op, format!("{:?} ? {:?}", l, r)),
+                None => return self.err_types("comparable types", &l, &r, op),
             },
             EqualEqual => Boolean(l.eq(&r)),
             BangEqual => Boolean(l.ne(&r)),
             _ => return self.err_op("erroneous binary operator", op),
         };
 
+        if let String(_) = res {
+            stats::record(Counter::String);
+        }
+
         Ok(ObjLit(res))
     }
 
-    fn visit_assignment(&mut self, _expr: &Expr, id: &Token, val: &Expr) -> Result<Object> {
+    fn visit_assignment(&mut self, expr: &Expr, id: &Token, val: &Expr) -> Result<Object> {
         let v = val.accept(self)?;
-        self.env.assign_at(id, v, self.locals.get(val))
+        self.env.assign_at(id, v, self.locals.get(expr))
     }
 
     fn visit_call(&mut self, _expr: &Expr, callee: &Expr, paren: &Token, args: &[Expr]) -> Result<Object> {
         match callee.accept(self)? {
             Object::Func(ref func) => self.dispatch_call(func, paren, args),
             Object::Class(ref cls) => self.dispatch_call(&Callable::init(cls), paren, args),
-            x => self.err_near(
-                "can only call functions and classes",
-                paren, format!("{}", x)),
+            ref x => self.err_type("function or class", x.type_name(), paren),
         }
     }
 
     fn visit_get(&mut self, _expr: &Expr, callee: &Expr, prop: &Token) -> Result<Object> {
         match callee.accept(self)? {
             Object::Instance(ref inst) => inst.get(prop),
-            _ => Err(Error::Runtime(
-                prop.line,
-                "only instances have properties".to_owned(),
-                prop.lexeme.to_owned(),
-            ))
+            ref x => self.err_type("instance", x.type_name(), prop),
         }
     }
 
     fn visit_set(&mut self, _expr: &Expr, settee: &Expr, prop: &Token, val: &Expr) -> Result<Object> {
-        if let Object::Instance(ref inst) = settee.accept(self)? {
-            inst.set(prop, val.accept(self)?)
-        } else {
-            Err(Error::Runtime(
-                prop.line,
-                "only instances have fields".to_owned(),
-                prop.lexeme.to_owned()))
+        match settee.accept(self)? {
+            Object::Instance(ref inst) => inst.set(prop, val.accept(self)?),
+            ref x => self.err_type("instance", x.type_name(), prop),
         }
     }
 
@@ -200,14 +498,14 @@ impl ExprVisitor<Result<Object>> for Interpreter {
         let parent = match self.env.get_at(tkn, Some(&dist))? {
             Object::Class(ref c) => Rc::clone(c),
             _ => return Err(Error::Runtime(tkn.line,
-                                           "unexpected super".to_owned(),
+                                           RuntimeKind::Other("unexpected super".to_owned()),
                                            tkn.lexeme.to_owned())),
         };
 
         let inst = match self.env.get_at(&THIS_ID, Some(&(dist - 1)))? {
             Object::Instance(ref i) => i.clone(),
             _ => return Err(Error::Runtime(tkn.line,
-                                           "unexpected this".to_owned(),
+                                           RuntimeKind::Other("unexpected this".to_owned()),
                                            tkn.lexeme.to_owned())),
         };
 
@@ -215,62 +513,87 @@ impl ExprVisitor<Result<Object>> for Interpreter {
             Some(m) => Ok(Object::Func(m.bind(&inst))),
             None => Err(Error::Runtime(
                 method.line,
-                "undefined property".to_owned(),
+                RuntimeKind::PropertyNotFound {
+                    name: method.lexeme.to_owned(),
+                    suggestion: parent.suggest_method(&method.lexeme),
+                },
                 method.lexeme.to_owned())),
         }
     }
 }
 
-impl StmtVisitor<Result<()>> for Interpreter {
-    fn visit_empty(&mut self, _stmt: &Stmt) -> Result<()> { Ok(()) }
+impl StmtVisitor<Exec> for Interpreter {
+    fn visit_empty(&mut self, _stmt: &Stmt) -> Exec { Ok(()) }
+
+    fn visit_break(&mut self, _stmt: &Stmt, tkn: &Token) -> Exec {
+        Err(ControlFlow::Break(tkn.line))
+    }
 
-    fn visit_break(&mut self, _stmt: &Stmt, tkn: &Token) -> Result<()> {
-        Err(Error::Break(tkn.line))
+    fn visit_export(&mut self, _stmt: &Stmt, _tkn: &Token, decl: &Stmt) -> Exec {
+        self.exec(decl)
     }
 
-    fn visit_expr_stmt(&mut self, stmt: &Stmt, expr: &Expr) -> Result<()> {
-        if self.repl {
+    /// Modules are loaded by the `Runner` executing the program, so an
+    /// import reaching the interpreter cannot be run.
+    fn visit_import(&mut self, _stmt: &Stmt, tkn: &Token, _path: &Token) -> Exec {
+        Err(ControlFlow::Error(Error::Runtime(
+            tkn.line,
+            RuntimeKind::Other("imports are only supported when run by a Runner".to_owned()),
+            tkn.lexeme.to_owned())))
+    }
+
+    fn visit_expr_stmt(&mut self, stmt: &Stmt, expr: &Expr) -> Exec {
+        if self.options.repl {
             self.visit_print(stmt, expr)
         } else {
-            expr.accept(self).map(|_| ())
+            expr.accept(self)?;
+            Ok(())
         }
     }
 
-    fn visit_print(&mut self, _stmt: &Stmt, expr: &Expr) -> Result<()> {
+    fn visit_print(&mut self, _stmt: &Stmt, expr: &Expr) -> Exec {
         let obj = expr.accept(self)?;
-        Writer::writeln(&self.stdout, &format!("{}", obj))
+        Writer::writeln(&self.stdout, &self.printer.print(&obj))?;
+        Ok(())
     }
 
-    fn visit_decl(&mut self, _stmt: &Stmt, id: &Token, init: Option<&Expr>) -> Result<()> {
+    fn visit_decl(&mut self, _stmt: &Stmt, id: &Token, init: Option<&Expr>) -> Exec {
         let val: Object = init.map_or_else(
-            || Ok(Object::Literal(Literal::Nil)),
+            || Ok(Object::NIL),
             |e| e.accept(self))?;
 
-        self.env.define(id, val)
+        self.env.define(id, val)?;
+        Ok(())
     }
 
-    fn visit_block(&mut self, _stmt: &Stmt, body: &[Stmt]) -> Result<()> {
+    fn visit_block(&mut self, _stmt: &Stmt, body: &[Stmt]) -> Exec {
         let mut scope = self.scoped();
-        for stmt in body { stmt.accept(&mut scope)?; }
+        for stmt in body { scope.exec(stmt)?; }
         Ok(())
     }
 
-    fn visit_if(&mut self, _stmt: &Stmt, cond: &Expr, then: &Stmt, els: Option<&Stmt>) -> Result<()> {
-        if cond.accept(self)?.is_truthy() {
-            return then.accept(self);
+    fn visit_if(&mut self, _stmt: &Stmt, cond: &Expr, then: &Stmt, els: Option<&Stmt>) -> Exec {
+        let cond = cond.accept(self)?;
+        if self.is_truthy(&cond) {
+            return self.exec(then);
         }
 
         if let Some(stmt) = els {
-            return stmt.accept(self);
+            return self.exec(stmt);
         }
 
         Ok(())
     }
 
-    fn visit_while(&mut self, _stmt: &Stmt, cond: &Expr, body: &Stmt) -> Result<()> {
-        while cond.accept(self)?.is_truthy() {
-            match body.accept(self) {
-                Err(Error::Break(_)) => return Ok(()),
+    fn visit_while(&mut self, _stmt: &Stmt, cond: &Expr, body: &Stmt) -> Exec {
+        while {
+            let c = cond.accept(self)?;
+            self.is_truthy(&c)
+        } {
+            self.check_interrupt(cond.token())?;
+
+            match self.exec(body) {
+                Err(ControlFlow::Break(_)) => return Ok(()),
                 Err(e) => return Err(e),
                 _ => (),
             };
@@ -278,29 +601,30 @@ impl StmtVisitor<Result<()>> for Interpreter {
         Ok(())
     }
 
-    fn visit_func(&mut self, _stmt: &Stmt, id: &Token, params: &[Token], body: Rc<Stmt>) -> Result<()> {
-        let f = Callable::new(Env::from_weak(&self.env), params, &body, false);
-        self.env.define(id, Object::Func(f))
+    fn visit_func(&mut self, stmt: &Stmt, id: &Token, params: &[Token], body: Rc<Stmt>) -> Exec {
+        let f = Callable::new(Env::from_weak(&self.env), &id.lexeme, params, &body, false, stmt.doc(), self.options.compat);
+        self.env.define(id, Object::Func(f))?;
+        Ok(())
     }
 
-    fn visit_return(&mut self, _stmt: &Stmt, tkn: &Token, val: Option<&Expr>) -> Result<()> {
+    fn visit_return(&mut self, _stmt: &Stmt, tkn: &Token, val: Option<&Expr>) -> Exec {
         let res = match val {
             Some(expr) => expr.accept(self)?,
-            None => Object::Literal(Literal::Nil),
+            None => Object::NIL,
         };
 
-        Err(Error::Return(tkn.line, res))
+        Err(ControlFlow::Return(tkn.line, res))
     }
 
-    fn visit_class(&mut self, _stmt: &Stmt, id: &Token, parent: Option<&Expr>, methods: &[Stmt]) -> Result<()> {
+    fn visit_class(&mut self, stmt: &Stmt, id: &Token, parent: Option<&Expr>, methods: &[Stmt]) -> Exec {
         let env = Env::from_weak(&self.env);
 
         let superclass = if let Some(p) = parent {
             let c = match p.accept(self)? {
                 Object::Class(ref c) => Rc::clone(c),
                 _ => return Err(Error::Parse(id.line,
-                                             "superclass must be a class".to_owned(),
-                                             id.lexeme.to_owned())),
+                                             ParseKind::InvalidSuperclass,
+                                             id.lexeme.to_owned()).into()),
             };
 
             env.define(&SUPER_ID, Object::Class(Rc::clone(&c)))?;
@@ -308,35 +632,53 @@ impl StmtVisitor<Result<()>> for Interpreter {
             Some(c)
         } else { None };
 
-        let mut ms = HashMap::with_capacity(methods.len());
+        let mut ms = OrderedMap::with_capacity(methods.len());
         for method in methods {
             match *method {
-                Stmt::Function(ref id, ref params, ref body) => {
+                Stmt::Function(ref method_id, ref params, ref body, ref doc) => {
                     let f = Callable::new(
                         Rc::clone(&env),
+                        &format!("{}.{}", id.lexeme, method_id.lexeme),
                         params,
                         body,
-                        id.lexeme.eq(INITIALIZER_FUNC));
+                        method_id.lexeme.eq(INITIALIZER_FUNC),
+                        doc.as_ref(),
+                        self.options.compat);
 
-                    ms.insert(id.lexeme.clone(), f);
+                    ms.insert(method_id.lexeme.clone(), f);
                 }
                 _ => unreachable!(),
             }
         };
 
 
-        let cls = Rc::new(LoxClass::new(&id.lexeme, superclass, ms));
-        self.env.define(id, Object::Class(cls))
+        let cls = Rc::new(LoxClass::new(&id.lexeme, superclass, ms, stmt.doc()));
+        self.env.define(id, Object::Class(cls))?;
+        Ok(())
     }
 }
 
 impl Interpreter {
+    /// Executes the statement, counting it, then finalizes any instances
+    /// it released
+    fn exec(&mut self, stmt: &Stmt) -> Exec {
+        stats::record(Counter::Statement);
+        stmt.accept(self)?;
+        Ok(self.finalize()?)
+    }
+
     fn scoped(&self) -> Interpreter {
        let i = Interpreter {
             env: Env::from(&self.env),
             locals: Rc::clone(&self.locals),
-            repl: false,
+            options: InterpreterOptions { repl: false, ..self.options },
             stdout: Rc::clone(&self.stdout),
+            stderr: Rc::clone(&self.stderr),
+            printer: self.printer,
+            host: Rc::clone(&self.host),
+            interrupt: self.interrupt.clone(),
+            finalizers: Rc::clone(&self.finalizers),
+            calls: Rc::clone(&self.calls),
         };
 
         debug_create!("Interpreter::Scoped ({} parent refs now)", Rc::strong_count(&i.locals)-1);
@@ -346,18 +688,15 @@ impl Interpreter {
 
     fn visit_logical(&mut self, lhs: &Expr, op: &Token, rhs: &Expr) -> Result<Object> {
         use ast::token::Type::{Or, And};
-        use ast::token::Literal::Boolean;
 
         let l: Object = lhs.accept(self)?;
 
-        let res: Literal = match op.typ {
-            And if l.is_truthy() => Boolean(rhs.accept(self)?.is_truthy()),
-            Or if l.is_truthy() => Boolean(true),
-            Or => Boolean(rhs.accept(self)?.is_truthy()),
-            _ => Boolean(false),
-        };
-
-        Ok(Object::Literal(res))
+        // evaluates to the operand deciding the outcome, short-circuiting
+        match op.typ {
+            Or if self.is_truthy(&l) => Ok(l),
+            And if !self.is_truthy(&l) => Ok(l),
+            _ => rhs.accept(self),
+        }
     }
 
     fn lookup_var(&mut self, id: &Token, expr: &Expr) -> Result<Object> {
@@ -367,31 +706,85 @@ impl Interpreter {
     fn err_op(&self, msg: &str, op: &Token) -> Result<Object> {
         Err(Error::Runtime(
             op.line,
-            msg.to_string(),
+            RuntimeKind::Other(msg.to_string()),
             op.lexeme.clone(),
         ))
     }
 
-    fn err_near(&self, msg: &str, op: &Token, near: String) -> Result<Object> {
+    fn err_type(&self, expected: &str, found: &str, op: &Token) -> Result<Object> {
         Err(Error::Runtime(
             op.line,
-            msg.to_string(),
-            near,
+            RuntimeKind::TypeMismatch {
+                expected: expected.to_owned(),
+                found: found.to_owned(),
+            },
+            op.lexeme.clone(),
         ))
     }
 
+    fn err_types(&self, expected: &str, l: &Object, r: &Object, op: &Token) -> Result<Object> {
+        let found = format!("{} and {}", l.type_name(), r.type_name());
+        self.err_type(expected, &found, op)
+    }
+
     fn dispatch_call(&mut self, callee: &Callable, paren: &Token, args: &[Expr]) -> Result<Object> {
-        if callee.arity() != args.len() {
-            return self.err_near(
-                &format!("expected {} arguments but got {}", callee.arity(), args.len()),
-                paren, "".to_string());
-        }
+        self.check_arity(callee, args.len(), paren)?;
 
         let mut params: Vec<Object> = Vec::with_capacity(args.len());
         for arg in args {
             params.push(arg.accept(self)?);
         }
 
-        callee.call(self, &params, paren)
+        self.invoke(callee, &params, paren)
+    }
+
+    /// Calls the callee one level deeper, failing if that exceeds the
+    /// maximum depth. As a stack overflow unwinds, each call adds its callee
+    /// to the error's trace.
+    fn invoke(&self, callee: &Callable, args: &[Object], paren: &Token) -> Result<Object> {
+        self.check_interrupt(paren)?;
+
+        let depth = self.calls.get();
+        if depth >= self.options.max_call_depth {
+            return Err(Error::Runtime(
+                paren.line,
+                RuntimeKind::StackOverflow(vec![(callee.name(), 1)]),
+                paren.lexeme.to_owned()));
+        }
+
+        self.calls.set(depth + 1);
+        let res = callee.call(self, args, paren);
+        self.calls.set(depth);
+
+        res.map_err(|e| match e {
+            Error::Runtime(line, RuntimeKind::StackOverflow(mut trace), near) => {
+                let name = callee.name();
+                match trace.last_mut() {
+                    Some(&mut (ref last, ref mut n)) if *last == name => *n += 1,
+                    _ => trace.push((name, 1)),
+                }
+                Error::Runtime(line, RuntimeKind::StackOverflow(trace), near)
+            }
+            e => e,
+        })
+    }
+
+    fn check_arity(&self, callee: &Callable, found: usize, paren: &Token) -> Result<()> {
+        if callee.arity() == found {
+            return Ok(());
+        }
+
+        Err(Error::Runtime(
+            paren.line,
+            RuntimeKind::ArityMismatch { callee: callee.name(), expected: callee.arity(), found },
+            paren.lexeme.to_owned()))
+    }
+
+    fn check_interrupt(&self, near: &Token) -> Result<()> {
+        if !self.interrupt.is_triggered() {
+            return Ok(());
+        }
+
+        Err(Error::Runtime(near.line, RuntimeKind::Interrupted, near.lexeme.to_owned()))
     }
 }
@@ -0,0 +1,45 @@
+//! A module describing the interruption of running code from other threads.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Interrupt is a shared flag that stops an interpreter at its next loop
+/// iteration or function call, such as when the REPL receives a SIGINT.
+/// Clones share the same flag, so one may be moved into a signal handler
+/// while another is held by the interpreter.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::interrupt::Interrupt;
+/// # fn main() {
+/// let i = Interrupt::default();
+/// let handle = i.clone();
+///
+/// handle.trigger();
+/// assert!(i.is_triggered());
+///
+/// i.reset();
+/// assert!(!handle.is_triggered());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt(Arc<AtomicBool>);
+
+impl Interrupt {
+    /// Requests that the running code stop
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Clears a previous request, allowing code to run again
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns true if the running code should stop
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,418 @@
+//! A module describing conversions between JSON text and Lox objects.
+//!
+//! JSON objects become instances of a method-less `Object` class, whose fields
+//! hold the object's members. JSON arrays are built by the caller, which the
+//! `jsonParse` native does with the standard library's `List`. Instances of
+//! `List` are stringified as arrays in turn.
+
+use std::char;
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use ast::token::{Literal, Token};
+use class::LoxInstance;
+use object::Object;
+
+/// The class name given to instances created from JSON objects
+pub const OBJECT_CLASS: &str = "Object";
+
+/// The class name of the standard library's lists, stringified as arrays
+pub const LIST_CLASS: &str = "List";
+
+/// The deepest nesting of objects and arrays parsed or stringified. Both
+/// recurse, so deeper nesting would risk overflowing the stack.
+pub const MAX_DEPTH: usize = 128;
+
+/// Builds the Object for a JSON array from its elements, or describes why it
+/// cannot.
+pub type ListBuilder<'a> = dyn FnMut(Vec<Object>) -> ::std::result::Result<Object, String> + 'a;
+
+/// Parses JSON text into an Object, returning a description of the error,
+/// including its line and column, if the text is malformed or nests objects
+/// and arrays deeper than `MAX_DEPTH`. Arrays are built with `list`.
+pub fn parse(src: &str, list: &mut ListBuilder) -> ::std::result::Result<Object, String> {
+    let mut p = JsonParser {
+        src: src.chars().peekable(),
+        line: 1,
+        col: 0,
+        depth: 0,
+        list,
+    };
+
+    let val = p.value()?;
+    p.whitespace();
+
+    match p.src.peek() {
+        None => Ok(val),
+        Some(_) => Err(p.err("unexpected trailing characters")),
+    }
+}
+
+/// Converts an Object into JSON text, returning a description of the error if
+/// the Object (or any of its fields) cannot be represented, or nests
+/// instances deeper than `MAX_DEPTH`. Instances of `List` are written as
+/// arrays of their values.
+pub fn stringify(obj: &Object) -> ::std::result::Result<String, String> {
+    let mut out = String::new();
+    write_value(&mut out, obj, &mut Vec::new())?;
+    Ok(out)
+}
+
+struct JsonParser<'a, 'b> {
+    src: Peekable<Chars<'a>>,
+    line: u64,
+    col: u64,
+    /// The number of objects and arrays enclosing the current value
+    depth: usize,
+    list: &'b mut ListBuilder<'b>,
+}
+
+impl<'a, 'b> JsonParser<'a, 'b> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.src.next();
+
+        match c {
+            Some('\n') => {
+                self.line += 1;
+                self.col = 0;
+            }
+            Some(_) => self.col += 1,
+            None => (),
+        };
+
+        c
+    }
+
+    fn err(&self, msg: &str) -> String {
+        format!("{} at line {} column {}", msg, self.line, self.col)
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(&c) = self.src.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, c: char) -> ::std::result::Result<(), String> {
+        match self.next() {
+            Some(n) if n == c => Ok(()),
+            Some(n) => Err(self.err(&format!("expected '{}', found '{}'", c, n))),
+            None => Err(self.err(&format!("expected '{}', found end of input", c))),
+        }
+    }
+
+    fn value(&mut self) -> ::std::result::Result<Object, String> {
+        self.whitespace();
+
+        let lit = match self.src.peek().cloned() {
+            Some('{') | Some('[') if self.depth >= MAX_DEPTH => {
+                self.next();
+                return Err(self.err(&format!("values nested deeper than {}", MAX_DEPTH)));
+            }
+            Some(c @ '{') | Some(c @ '[') => {
+                self.depth += 1;
+                let obj = if c == '{' { self.object() } else { self.array() };
+                self.depth -= 1;
+                return obj;
+            }
+            Some('"') => Literal::String(self.string()?.into()),
+            Some('t') => { self.keyword("true")?; Literal::Boolean(true) }
+            Some('f') => { self.keyword("false")?; Literal::Boolean(false) }
+            Some('n') => { self.keyword("null")?; Literal::Nil }
+            Some(c) if c == '-' || c.is_ascii_digit() => Literal::Number(self.number()?),
+            Some(c) => {
+                self.next();
+                return Err(self.err(&format!("unexpected character '{}'", c)));
+            }
+            None => return Err(self.err("unexpected end of input")),
+        };
+
+        Ok(Object::Literal(lit))
+    }
+
+    fn keyword(&mut self, kw: &str) -> ::std::result::Result<(), String> {
+        for c in kw.chars() {
+            self.expect(c)?;
+        }
+        Ok(())
+    }
+
+    /// Parses a number following the JSON grammar:
+    /// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+    fn number(&mut self) -> ::std::result::Result<f64, String> {
+        let mut lex = String::new();
+
+        self.take_if(&mut lex, |c| c == '-');
+        match self.src.peek().cloned() {
+            Some('0') => {
+                self.take_if(&mut lex, |c| c == '0');
+                if self.src.peek().is_some_and(char::is_ascii_digit) {
+                    return Err(self.invalid_number(lex));
+                }
+            }
+            Some(c) if c.is_ascii_digit() => self.digits(&mut lex),
+            _ => return Err(self.invalid_number(lex)),
+        }
+
+        if self.take_if(&mut lex, |c| c == '.') {
+            if !self.src.peek().is_some_and(char::is_ascii_digit) {
+                return Err(self.invalid_number(lex));
+            }
+            self.digits(&mut lex);
+        }
+
+        if self.take_if(&mut lex, |c| c == 'e' || c == 'E') {
+            self.take_if(&mut lex, |c| c == '+' || c == '-');
+            if !self.src.peek().is_some_and(char::is_ascii_digit) {
+                return Err(self.invalid_number(lex));
+            }
+            self.digits(&mut lex);
+        }
+
+        lex.parse::<f64>()
+            .map_err(|_| self.invalid_number(lex.clone()))
+    }
+
+    /// Appends the next character to lex if it matches, returning whether
+    /// it did.
+    fn take_if<F: Fn(char) -> bool>(&mut self, lex: &mut String, f: F) -> bool {
+        match self.src.peek().cloned() {
+            Some(c) if f(c) => {
+                lex.push(c);
+                self.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn digits(&mut self, lex: &mut String) {
+        while self.take_if(lex, |c| c.is_ascii_digit()) {}
+    }
+
+    /// Describes the malformed number, including the character that ended
+    /// it, if any.
+    fn invalid_number(&mut self, mut lex: String) -> String {
+        if let Some(c) = self.next() {
+            lex.push(c);
+        }
+        self.err(&format!("invalid number '{}'", lex))
+    }
+
+    fn string(&mut self) -> ::std::result::Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+
+        loop {
+            match self.next() {
+                None => return Err(self.err("unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => s.push(self.unicode()?),
+                    _ => return Err(self.err("invalid escape sequence")),
+                },
+                Some(c) if c.is_control() => return Err(self.err("unescaped control character in string")),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn unicode(&mut self) -> ::std::result::Result<char, String> {
+        let hi = self.hex()?;
+
+        if !(0xD800..=0xDBFF).contains(&hi) {
+            return char::from_u32(hi).ok_or_else(|| self.err("invalid unicode escape"));
+        }
+
+        // surrogate pair
+        self.expect('\\')?;
+        self.expect('u')?;
+        let lo = self.hex()?;
+
+        if !(0xDC00..=0xDFFF).contains(&lo) {
+            return Err(self.err("invalid unicode surrogate pair"));
+        }
+
+        char::from_u32(0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00))
+            .ok_or_else(|| self.err("invalid unicode escape"))
+    }
+
+    fn hex(&mut self) -> ::std::result::Result<u32, String> {
+        let mut n = 0;
+
+        for _ in 0..4 {
+            let d = self.next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.err("invalid unicode escape"))?;
+            n = n * 16 + d;
+        }
+
+        Ok(n)
+    }
+
+    fn object(&mut self) -> ::std::result::Result<Object, String> {
+        self.expect('{')?;
+        let mut fields: Vec<(String, Object)> = Vec::new();
+
+        self.whitespace();
+        if self.src.peek() == Some(&'}') {
+            self.next();
+            return Ok(record(fields));
+        }
+
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.whitespace();
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(record(fields)),
+                Some(c) => return Err(self.err(&format!("expected ',' or '}}', found '{}'", c))),
+                None => return Err(self.err("unterminated object")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> ::std::result::Result<Object, String> {
+        self.expect('[')?;
+        let mut elems = Vec::new();
+
+        self.whitespace();
+        if self.src.peek() == Some(&']') {
+            self.next();
+            return (self.list)(elems).map_err(|msg| self.err(&msg));
+        }
+
+        loop {
+            elems.push(self.value()?);
+            self.whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return (self.list)(elems).map_err(|msg| self.err(&msg)),
+                Some(c) => return Err(self.err(&format!("expected ',' or ']', found '{}'", c))),
+                None => return Err(self.err("unterminated array")),
+            }
+        }
+    }
+}
+
+fn record(fields: Vec<(String, Object)>) -> Object {
+    Object::Instance(LoxInstance::record(
+        OBJECT_CLASS,
+        fields.iter().map(|(k, v)| (k.as_str(), v.clone())).collect()))
+}
+
+fn write_value(out: &mut String, obj: &Object, seen: &mut Vec<LoxInstance>) -> ::std::result::Result<(), String> {
+    match *obj {
+        Object::Literal(Literal::Nil) => out.push_str("null"),
+        Object::Literal(Literal::Boolean(b)) => out.push_str(if b { "true" } else { "false" }),
+        Object::Literal(Literal::Number(n)) if !n.is_finite() => out.push_str("null"),
+        Object::Literal(Literal::Number(n)) => { let _ = write!(out, "{}", n); }
+        Object::Literal(Literal::String(ref s)) => write_string(out, s),
+        Object::Instance(ref inst) => {
+            if seen.iter().any(|i| i.is(inst)) {
+                return Err("cannot stringify a cyclic structure".to_owned());
+            }
+
+            // seen holds the instances enclosing this one
+            if seen.len() >= MAX_DEPTH {
+                return Err(format!("cannot stringify values nested deeper than {}", MAX_DEPTH));
+            }
+
+            seen.push(inst.clone());
+
+            if let Some(values) = list_values(inst) {
+                out.push('[');
+                for (idx, v) in values.iter().enumerate() {
+                    if idx > 0 { out.push(','); }
+                    write_value(out, v, seen)?;
+                }
+                out.push(']');
+                seen.pop();
+                return Ok(());
+            }
+
+            out.push('{');
+
+            for (idx, (k, v)) in inst.snapshot_fields().iter().enumerate() {
+                if idx > 0 { out.push(','); }
+                write_string(out, k);
+                out.push(':');
+                write_value(out, v, seen)?;
+            }
+
+            out.push('}');
+            seen.pop();
+        }
+        ref x => return Err(format!("cannot stringify {}", x)),
+    };
+
+    Ok(())
+}
+
+/// Returns the values of an instance of `List`, walking its nodes, or None
+/// if the instance is not a list.
+fn list_values(inst: &LoxInstance) -> Option<Vec<Object>> {
+    let field = |inst: &LoxInstance, name: &str| inst.get(&Token::identifier(name)).ok();
+
+    if inst.class_name() != LIST_CLASS {
+        return None;
+    }
+
+    let size = match field(inst, "size") {
+        Some(Object::Literal(Literal::Number(n))) if n >= 0.0 => n as usize,
+        _ => return None,
+    };
+
+    // the size bounds the walk, should the nodes have been linked into a cycle
+    let mut values = Vec::with_capacity(size);
+    let mut node = field(inst, "head")?;
+    for _ in 0..size {
+        let next = match node {
+            Object::Instance(ref n) => {
+                values.push(field(n, "value")?);
+                field(n, "next")?
+            }
+            _ => return None,
+        };
+        node = next;
+    }
+
+    Some(values)
+}
+
+/// Writes the string to out as a quoted and escaped JSON string.
+pub fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
@@ -5,29 +5,88 @@ extern crate lazy_static;
 mod debug;
 
 mod result;
+mod suggest;
+mod datetime;
+mod json;
 
-pub mod ast;
+/// Declares modules that are only public with the `internals` feature.
+/// Their items remain usable through the public API, but cannot be named
+/// outside of the crate, so they may change without breaking dependents.
+macro_rules! internal {
+    ($($(#[$attr:meta])* mod $name:ident;)*) => {$(
+        #[cfg(feature = "internals")]
+        $(#[$attr])*
+        pub mod $name;
 
-pub mod functions;
+        #[cfg(not(feature = "internals"))]
+        $(#[$attr])*
+        mod $name;
+    )*}
+}
+
+internal! {
+    mod ast;
+    mod functions;
+}
+
+pub mod host;
+pub mod interrupt;
 pub mod object;
 
-#[macro_use]
-pub mod env;
+internal! {
+    #[macro_use]
+    mod env;
+
+    mod ordered;
+    mod shape;
+    mod class;
+    mod scanner;
+    mod parser;
+    mod compat;
+    mod interpreter;
+    mod resolver;
+}
+
+pub mod lint;
 
-pub mod class;
+internal! {
+    mod constant;
+    mod source;
+}
+
+pub mod codes;
+pub mod diagnostic;
+
+internal! {
+    mod doc;
+    mod display;
+}
 
-pub mod scanner;
-pub mod parser;
-pub mod interpreter;
-pub mod resolver;
+pub mod highlight;
+
+internal! {
+    mod output;
+    mod manifest;
+}
 
-pub mod output;
 pub mod run;
 
-pub use result::{Result, Error};
+internal! {
+    mod stats;
+    mod trace;
+}
+
+pub use result::{Result, Error, LexicalKind, ParseKind, RuntimeKind};
+pub use run::Runner;
+pub use object::Object;
+pub use ast::token::Literal;
+pub use lint::{Lints, Warning};
+pub use compat::Compat;
+pub use output::{Reader, Writer};
+pub use trace::Output;
 
 /// Boxer converts a type into its Boxed form
-pub trait Boxer {
+pub(crate) trait Boxer {
     /// Convert to a boxed version
     fn boxed(self) -> Box<Self> where Self : Sized { Box::new(self) }
 }
@@ -0,0 +1,144 @@
+//! A module describing opt-in warnings reported by static analysis.
+
+use std::fmt;
+
+/// Lint names a category of warnings, which may be toggled individually.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lint {
+    Shadowing,
+    DeadStores,
+    Unreachable,
+    ConstantConditions,
+}
+
+impl Lint {
+    /// Returns the Lint matching its command line name, if any.
+    pub fn from_name(name: &str) -> Option<Lint> {
+        match name {
+            "shadowing" => Some(Lint::Shadowing),
+            "dead-stores" => Some(Lint::DeadStores),
+            "unreachable" => Some(Lint::Unreachable),
+            "constant-conditions" => Some(Lint::ConstantConditions),
+            _ => None,
+        }
+    }
+
+    /// The command line name of the lint
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::Shadowing => "shadowing",
+            Lint::DeadStores => "dead-stores",
+            Lint::Unreachable => "unreachable",
+            Lint::ConstantConditions => "constant-conditions",
+        }
+    }
+}
+
+/// Severity distinguishes diagnostics that stop code from running from
+/// those that do not.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+/// Lints toggles the warnings reported while resolving. All lints are
+/// disabled by default. Denied lints are reported as errors, and the code
+/// is not run.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::lint::{Lint, Lints, Severity};
+/// # fn main() {
+/// assert!(!Lints::default().shadowing);
+/// assert!(Lints::all().shadowing);
+///
+/// let mut lints = Lints::all();
+/// lints.set(Lint::Shadowing, false);
+/// lints.deny = true;
+/// assert!(!lints.is_enabled(Lint::Shadowing));
+/// assert_eq!(Severity::Error, lints.severity());
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lints {
+    /// Warn when a local declaration shadows a binding in an outer scope
+    pub shadowing: bool,
+    /// Warn when a value assigned to a local is overwritten before being read
+    pub dead_stores: bool,
+    /// Warn on statements following an unconditional `return` or `break`
+    pub unreachable: bool,
+    /// Warn on `if` and `while` conditions built solely from literals
+    pub constant_conditions: bool,
+    /// Report warnings as errors
+    pub deny: bool,
+}
+
+impl Lints {
+    /// Enables every lint
+    pub fn all() -> Self {
+        Lints {
+            shadowing: true,
+            dead_stores: true,
+            unreachable: true,
+            constant_conditions: true,
+            deny: false,
+        }
+    }
+
+    /// Enables or disables the lint
+    pub fn set(&mut self, lint: Lint, on: bool) {
+        *self.flag(lint) = on;
+    }
+
+    /// Returns true if the lint is enabled
+    pub fn is_enabled(mut self, lint: Lint) -> bool {
+        *self.flag(lint)
+    }
+
+    /// The severity of the warnings raised
+    pub fn severity(self) -> Severity {
+        if self.deny { Severity::Error } else { Severity::Warning }
+    }
+
+    fn flag(&mut self, lint: Lint) -> &mut bool {
+        match lint {
+            Lint::Shadowing => &mut self.shadowing,
+            Lint::DeadStores => &mut self.dead_stores,
+            Lint::Unreachable => &mut self.unreachable,
+            Lint::ConstantConditions => &mut self.constant_conditions,
+        }
+    }
+}
+
+/// A Warning describes suspicious but valid code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// The lint that raised the warning
+    pub lint: Lint,
+    /// Whether the warning is reported as an error
+    pub severity: Severity,
+    /// The line the warning was raised on
+    pub line: u64,
+    /// A description of the problem
+    pub msg: String,
+    /// The lexeme the warning was raised near
+    pub near: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [line {}] {}: near {}", self.severity, self.line, self.msg, self.near)
+    }
+}
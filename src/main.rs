@@ -1,29 +1,259 @@
+extern crate ctrlc;
 extern crate rlox;
 
+use std::cell::RefCell;
 use std::env;
-use std::io::{stdin, BufReader};
-use std::path::Path;
+use std::fs::File;
+use std::io::{self, stdin, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::rc::Rc;
+use std::thread;
 
-use rlox::{Result, Error};
-use rlox::output::Reader::StdIn;
+use rlox::{Result, Error, Compat, Output, Writer};
+use rlox::Reader::StdIn;
+use rlox::codes;
+use rlox::diagnostic::{self, Diagnostic};
+use rlox::highlight::Format;
+use rlox::lint::{Lint, Lints};
 use rlox::run::Runner;
 
+/// The native stack given to the interpreter, enough for calls nested up to
+/// `MAX_CALL_DEPTH`, even in debug builds
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// The maximum depth of nested calls, raised from the library's default as
+/// scripts run on a larger stack
+const MAX_CALL_DEPTH: usize = 1000;
+
 fn main() {
+    let child = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("failed to spawn the interpreter thread");
+
+    // run always exits the process, so joining only fails on a panic
+    if child.join().is_err() {
+        exit(101);
+    }
+}
+
+fn run() {
     let mut r = Runner::default();
-    let args: Vec<String> = env::args().collect();
+    r.set_max_call_depth(MAX_CALL_DEPTH);
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    if let Some(idx) = args.iter().position(|a| a == "--no-std") {
+        args.remove(idx);
+        r.set_std(false);
+    }
+
+    let mut lints = Lints::default();
+    if let Some(idx) = args.iter().position(|a| a == "--warn") {
+        args.remove(idx);
+        lints = Lints::all();
+    }
+
+    // denying warnings implies reporting them
+    if let Some(idx) = args.iter().position(|a| a == "--deny-warnings") {
+        args.remove(idx);
+        lints = Lints { deny: true, ..Lints::all() };
+    }
+
+    r.set_lints(lints);
+
+    // allowed lints stay disabled even if a project manifest enables warnings
+    while let Some(idx) = args.iter().position(|a| a.starts_with("--allow=")) {
+        match Lint::from_name(&args.remove(idx)["--allow=".len()..]) {
+            Some(lint) => r.allow(lint),
+            None => report(&r, Err(Error::Usage)),
+        }
+    }
+
+    if let Some(idx) = args.iter().position(|a| a == "--stream") {
+        args.remove(idx);
+        r.set_stream(true);
+    }
+
+    if let Some(idx) = args.iter().position(|a| a == "--fold") {
+        args.remove(idx);
+        r.set_fold(true);
+    }
+
+    if let Some(idx) = args.iter().position(|a| a == "--timings" || a.starts_with("--timings=")) {
+        match args.remove(idx).split('=').nth(1).map_or(Some(Output::Table), Output::from_name) {
+            Some(out) => r.set_timings(Some(out)),
+            None => report(&r, Err(Error::Usage)),
+        }
+    }
+
+    if let Some(idx) = args.iter().position(|a| a == "--monkey-patching") {
+        args.remove(idx);
+        r.set_monkey_patching(true);
+    }
+
+    if let Some(idx) = args.iter().position(|a| a == "--report") {
+        args.remove(idx);
+        r.set_report(true);
+    }
+
+    if let Some(idx) = args.iter().position(|a| a.starts_with("--error-format=")) {
+        match diagnostic::Format::from_name(&args.remove(idx)["--error-format=".len()..]) {
+            Some(fmt) => r.set_error_format(fmt),
+            None => report(&r, Err(Error::Usage)),
+        }
+    }
+
+    if let Some(idx) = args.iter().position(|a| a.starts_with("--compat=")) {
+        match Compat::from_name(&args.remove(idx)["--compat=".len()..]) {
+            Some(compat) => r.set_compat(compat),
+            None => report(&r, Err(Error::Usage)),
+        }
+    }
+
+    // import paths are searched in order: flags, then LOX_PATH, then the
+    // project manifest
+    while let Some(idx) = args.iter().position(|a| a.starts_with("-I")) {
+        let flag = args.remove(idx);
+        let dir = match &flag[2..] {
+            "" if idx < args.len() => args.remove(idx),
+            "" => report(&r, Err(Error::Usage)),
+            dir => dir.to_owned(),
+        };
+        r.add_search_path(PathBuf::from(dir));
+    }
+
+    if let Some(paths) = env::var_os("LOX_PATH") {
+        for dir in env::split_paths(&paths) {
+            r.add_search_path(dir);
+        }
+    }
 
-    let res: Result<()> = match args.len() {
-        1 => r.prompt(StdIn(BufReader::new(stdin()))), // REPL if no script file
-        2 => r.file(Path::new(&args[1])),                       // Interpret a file otherwise
+    if let Some(idx) = args.iter().position(|a| a.starts_with("--record=")) {
+        match File::create(&args.remove(idx)["--record=".len()..]) {
+            Ok(f) => r.set_record(Some(Rc::new(RefCell::new(Writer::File(BufWriter::new(f)))))),
+            Err(e) => report(&r, Err(Error::IO(e))),
+        }
+    }
+
+    let replay = args.iter().position(|a| a.starts_with("--replay="))
+        .map(|idx| args.remove(idx)["--replay=".len()..].to_owned());
+
+    if args.first().map(String::as_str) == Some("run") {
+        let res = match args.len() {
+            1 => r.project(Path::new(".")),
+            2 => r.project(Path::new(&args[1])),
+            _ => Err(Error::Usage),
+        };
+        report(&r, res);
+    }
+
+    if args.first().map(String::as_str) == Some("check") {
+        let res = match args.len() {
+            2 => r.check(Path::new(&args[1])),
+            _ => Err(Error::Usage),
+        };
+        report(&r, res);
+    }
+
+    if args.first().map(String::as_str) == Some("explain") {
+        let res = match args.len() {
+            1 => {
+                list_codes();
+                Ok(())
+            }
+            2 => explain(&args[1]),
+            _ => Err(Error::Usage),
+        };
+        report(&r, res);
+    }
+
+    if args.first().map(String::as_str) == Some("doc") {
+        let res = match args.len() {
+            2 => r.doc(Path::new(&args[1])),
+            _ => Err(Error::Usage),
+        };
+        report(&r, res);
+    }
+
+    if args.first().map(String::as_str) == Some("highlight") {
+        let res = highlight(&mut r, &args[1..]);
+        report(&r, res);
+    }
+
+    let res: Result<()> = match (args.len(), replay) {
+        (0, replay) => repl(&mut r, replay.as_ref().map(Path::new)), // REPL if no script file
+        (1, None) => r.file(Path::new(&args[0])),                  // Interpret a file otherwise
         _ => Err(Error::Usage),                                      // Print usage
     };
 
+    report(&r, res);
+}
+
+/// Runs the REPL, where SIGINT stops the running line instead of exiting.
+/// The lines are read from stdin, or replayed from a recorded session.
+fn repl(r: &mut Runner, replay: Option<&Path>) -> Result<()> {
+    let interrupt = r.interrupt();
+    ctrlc::set_handler(move || interrupt.trigger())
+        .map_err(|e| io::Error::other(e.to_string()))?;
+
+    match replay {
+        Some(session) => r.replay(session),
+        None => r.prompt(StdIn(BufReader::new(stdin()))),
+    }
+}
+
+/// Prints every diagnostic code and its title.
+fn list_codes() {
+    for c in codes::CODES {
+        println!("{}  {}", c.code, c.title);
+    }
+}
+
+/// Prints the extended description of the code, exiting if it is unknown.
+fn explain(code: &str) -> Result<()> {
+    match codes::find(code) {
+        Some(c) => print!("{}", c.describe()),
+        None => {
+            eprintln!("unknown diagnostic code '{}'; run `rlox explain` to list them", code);
+            exit(1);
+        }
+    }
+    Ok(())
+}
+
+fn highlight(r: &mut Runner, args: &[String]) -> Result<()> {
+    let mut fmt = Format::Ansi;
+    let mut file = None;
+
+    for arg in args {
+        if let Some(name) = arg.strip_prefix("--format=") {
+            fmt = Format::from_name(name).ok_or(Error::Usage)?;
+        } else if file.is_none() {
+            file = Some(arg);
+        } else {
+            return Err(Error::Usage);
+        }
+    }
+
+    r.highlight(Path::new(file.ok_or(Error::Usage)?), fmt)
+}
+
+fn report(r: &Runner, res: Result<()>) -> ! {
+    // timings and reports are best effort, and never change the exit code
+    let _ = r.write_timings();
+    let _ = r.write_report();
+
     match res {
         Ok(_) => exit(0),
-        Err(e) => {
+        Err(e @ Error::Usage) => {
             eprintln!("{}", e);
             exit(1);
         }
+        Err(e @ Error::IO(_)) => {
+            eprintln!("{}", Diagnostic::from_error(&e, None).render(r.error_format()));
+            exit(1);
+        }
+        Err(_) => exit(1), // already reported to stderr by the runner
     }
 }
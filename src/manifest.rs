@@ -0,0 +1,164 @@
+//! A module describing the `lox.toml` project manifest.
+//!
+//! Only a flat subset of TOML is understood: `key = value` pairs of strings
+//! or booleans, blank lines, and `#` comments.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use result::{Error, ParseKind, Result};
+use source;
+
+/// The name of the manifest file within a project directory
+pub const FILE: &str = "lox.toml";
+
+/// A Manifest describes how to run a project.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::manifest::Manifest;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// # use std::path::Path;
+/// let m = Manifest::parse("entry = \"src/app.lox\"\nstd = false # no prelude\n").unwrap();
+/// assert_eq!(Path::new("src/app.lox"), m.entry);
+/// assert!(!m.std);
+/// assert!(!m.warn);
+/// assert!(m.path.is_empty());
+///
+/// assert!(Manifest::parse("backend = \"vm\"").is_err());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// The script to run, relative to the manifest. Defaults to `main.lox`.
+    pub entry: PathBuf,
+    /// Whether the standard library is preloaded. Defaults to true.
+    pub std: bool,
+    /// Whether all lints are reported. Defaults to false.
+    pub warn: bool,
+    /// Whether natives that block or read the wall clock are omitted.
+    /// Defaults to false.
+    pub sandbox: bool,
+    /// The directories searched for imported modules, relative to the
+    /// manifest. Written as a single string, separated like `LOX_PATH`.
+    pub path: Vec<PathBuf>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            entry: PathBuf::from("main.lox"),
+            std: true,
+            warn: false,
+            sandbox: false,
+            path: Vec::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest from the project directory, resolving the entry
+    /// point relative to it.
+    pub fn load(dir: &Path) -> Result<Manifest> {
+        let src = source::read(&dir.join(FILE))?;
+
+        let mut m = Manifest::parse(&src)?;
+        m.entry = dir.join(&m.entry);
+        m.path = m.path.iter().map(|p| dir.join(p)).collect();
+        Ok(m)
+    }
+
+    /// Parses the manifest source, returning the first malformed or unknown
+    /// entry as an error.
+    pub fn parse(src: &str) -> Result<Manifest> {
+        let mut m = Manifest::default();
+
+        for (idx, line) in src.lines().enumerate() {
+            let line_no = idx as u64 + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                return Err(Error::Parse(line_no, ParseKind::Other("tables are not supported".to_owned()), line.to_owned()));
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let val = match parts.next() {
+                Some(v) => v.trim(),
+                None => return Err(Error::Parse(line_no, ParseKind::Other("expected '=' after key".to_owned()), key.to_owned())),
+            };
+
+            match key {
+                "entry" => m.entry = PathBuf::from(string(line_no, val)?),
+                "std" => m.std = boolean(line_no, val)?,
+                "warn" => m.warn = boolean(line_no, val)?,
+                "sandbox" => m.sandbox = boolean(line_no, val)?,
+                "path" => m.path = env::split_paths(&string(line_no, val)?).collect(),
+                _ => return Err(Error::Parse(line_no, ParseKind::Other("unknown key".to_owned()), key.to_owned())),
+            }
+        }
+
+        Ok(m)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (idx, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..idx],
+            _ => (),
+        }
+    }
+
+    line
+}
+
+fn string(line: u64, val: &str) -> Result<String> {
+    if val.len() < 2 || !val.starts_with('"') || !val.ends_with('"') {
+        return Err(Error::Parse(line, ParseKind::Other("expected string".to_owned()), val.to_owned()));
+    }
+
+    let mut out = String::new();
+    let mut chars = val[1..val.len() - 1].chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            _ => return Err(Error::Parse(line, ParseKind::Other("invalid escape sequence".to_owned()), val.to_owned())),
+        }
+    }
+
+    Ok(out)
+}
+
+fn boolean(line: u64, val: &str) -> Result<bool> {
+    match val {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(Error::Parse(line, ParseKind::Other("expected boolean".to_owned()), val.to_owned())),
+    }
+}
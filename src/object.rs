@@ -4,8 +4,13 @@ use functions::Callable;
 use std::cmp::Ordering;
 use std::fmt;
 use std::cmp;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
+/// Object is a runtime value. Nil, booleans, and numbers are stored inline,
+/// while strings, functions, classes, and instances are reference counted, so
+/// cloning an Object, as happens on every variable read, never allocates.
 #[derive(Clone, Debug)]
 pub enum Object {
     Literal(token::Literal),
@@ -15,6 +20,9 @@ pub enum Object {
 }
 
 impl Object {
+    /// The shared `nil` value
+    pub const NIL: Object = Object::Literal(token::Literal::Nil);
+
     pub fn is_truthy(&self) -> bool {
         use ast::token::Literal::*;
 
@@ -28,6 +36,41 @@ impl Object {
             },
         }
     }
+
+    /// The name of the value's type, used when reporting errors
+    pub fn type_name(&self) -> &'static str {
+        use ast::token::Literal::*;
+
+        match *self {
+            Object::Func(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::Literal(Nil) => "nil",
+            Object::Literal(Boolean(_)) => "boolean",
+            Object::Literal(Number(_)) => "number",
+            Object::Literal(String(_)) => "string",
+        }
+    }
+
+    /// Returns a hash of the value, or None if the value cannot be hashed.
+    /// Equal values hash the same, so `0` and `-0` agree. Nil, booleans,
+    /// numbers, and strings hash the same across runs, while instances hash
+    /// by identity. Functions and classes are not hashable.
+    pub fn hash_code(&self) -> Option<u64> {
+        use ast::token::Literal::Number;
+
+        let mut h = DefaultHasher::new();
+
+        match *self {
+            // matches both 0 and -0
+            Object::Literal(Number(0.0)) => Number(0.0).hash(&mut h),
+            Object::Literal(ref lit) => lit.hash(&mut h),
+            Object::Instance(ref inst) => inst.hash(&mut h),
+            Object::Func(_) | Object::Class(_) => return None,
+        }
+
+        Some(h.finish())
+    }
 }
 
 #[cfg(feature = "debug-destructors")]
@@ -51,6 +94,7 @@ impl cmp::PartialEq for Object {
         use object::Object::Literal as ObjLit;
         match (self, other) {
             (&ObjLit(ref lhs), &ObjLit(ref rhs)) => lhs.eq(rhs),
+            (Object::Func(lhs), Object::Func(rhs)) => lhs.eq(rhs),
             _ => false
         }
     }
@@ -60,7 +104,7 @@ impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Object::Literal(ref lit) => fmt::Display::fmt(lit, f),
-            Object::Func(_) => write!(f, "<function>"),
+            Object::Func(ref func) => fmt::Display::fmt(func, f),
             Object::Class(ref cls) => fmt::Display::fmt(cls, f),
             Object::Instance(ref inst) => fmt::Display::fmt(inst, f),
         }
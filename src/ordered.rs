@@ -0,0 +1,90 @@
+//! A module describing an insertion-ordered map.
+
+use std::collections::HashMap;
+use std::slice::Iter;
+
+/// A map keyed by strings that iterates in insertion order.
+///
+/// Replacing the value of an existing key retains its original position.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::ordered::OrderedMap;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let mut m = OrderedMap::new();
+/// m.insert("b".to_owned(), 1);
+/// m.insert("a".to_owned(), 2);
+/// m.insert("b".to_owned(), 3);
+///
+/// let keys: Vec<&str> = m.keys().collect();
+/// assert_eq!(vec!["b", "a"], keys);
+/// assert_eq!(Some(&3), m.get("b"));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OrderedMap<V> {
+    index: HashMap<String, usize>,
+    entries: Vec<(String, V)>,
+}
+
+impl<V> Default for OrderedMap<V> {
+    fn default() -> Self {
+        OrderedMap::new()
+    }
+}
+
+impl<V> OrderedMap<V> {
+    pub fn new() -> Self {
+        OrderedMap::with_capacity(0)
+    }
+
+    pub fn with_capacity(n: usize) -> Self {
+        OrderedMap {
+            index: HashMap::with_capacity(n),
+            entries: Vec::with_capacity(n),
+        }
+    }
+
+    /// Inserts the value, returning the previous value for the key if present.
+    pub fn insert(&mut self, key: String, val: V) -> Option<V> {
+        if let Some(&idx) = self.index.get(&key) {
+            return Some(::std::mem::replace(&mut self.entries[idx].1, val));
+        }
+
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, val));
+        None
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.index.get(key).map(|&idx| &self.entries[idx].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the entries in insertion order
+    pub fn iter(&self) -> Iter<'_, (String, V)> {
+        self.entries.iter()
+    }
+
+    /// Iterates over the keys in insertion order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.0.as_str())
+    }
+}
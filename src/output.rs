@@ -1,3 +1,4 @@
+use std::fs;
 use std::io;
 use std::io::{Write, BufWriter};
 use std::rc::Rc;
@@ -10,9 +11,26 @@ pub enum Writer {
     StdOut(BufWriter<io::Stdout>),
     StdErr(BufWriter<io::Stderr>),
     Cursor(io::Cursor<Vec<u8>>),
+    File(BufWriter<fs::File>),
+    /// Forwards writes to the shared writer, retaining a copy
+    Tee(Rc<RefCell<Writer>>, Vec<u8>),
 }
 
 impl Writer {
+    /// Wraps the shared writer, copying everything written through it.
+    pub fn tee(w: &Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Writer::Tee(Rc::clone(w), Vec::new())))
+    }
+
+    /// Returns the text copied by a tee since the last call, or nothing for
+    /// other writers.
+    pub fn take_copy(w: &Rc<RefCell<Self>>) -> String {
+        match *w.borrow_mut() {
+            Writer::Tee(_, ref mut copy) => String::from_utf8_lossy(&copy.split_off(0)).into_owned(),
+            _ => String::new(),
+        }
+    }
+
     pub fn write(w: &Rc<RefCell<Self>>, txt: &str) -> Result<()> {
         write!(w.borrow_mut(), "{}", txt)?;
         Ok(())
@@ -37,6 +55,12 @@ impl io::Write for Writer {
             StdOut(ref mut fd) => fd.write(buf),
             StdErr(ref mut fd) => fd.write(buf),
             Cursor(ref mut c) => c.write(buf),
+            File(ref mut fd) => fd.write(buf),
+            Tee(ref w, ref mut copy) => {
+                let n = w.borrow_mut().write(buf)?;
+                copy.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
         }
     }
 
@@ -47,6 +71,8 @@ impl io::Write for Writer {
             StdOut(ref mut fd) => fd.flush(),
             StdErr(ref mut fd) => fd.flush(),
             Cursor(ref mut c) => c.flush(),
+            File(ref mut fd) => fd.flush(),
+            Tee(ref w, _) => w.borrow_mut().flush(),
         }
     }
 }
@@ -1,53 +1,143 @@
+use std::collections::VecDeque;
 use std::iter::Peekable;
 
 use ast::expr::Expr;
 use ast::stmt::{Stmt, FUNCTION_ARGS_MAX};
 use Boxer;
-use result::{Result, Error};
+use result::{Result, Error, ParseKind};
 use scanner::Scanner;
 use ast::token::{Type, Token, Literal};
 use ast::token::Type::*;
 use std::rc::Rc;
 
+/// The default maximum nesting of expressions and statements. Syntax trees
+/// are walked recursively, so deeper nesting risks overflowing the stack.
+pub const MAX_DEPTH: usize = 256;
+
 pub struct Parser<'a> {
     src: Peekable<Scanner<'a>>,
+    /// Errors recovered from while parsing the current statement, emitted
+    /// before any further statements are parsed
+    pending: VecDeque<Error>,
+    /// The line of the last consumed token, for errors raised once the
+    /// tokens are exhausted
+    line: u64,
+    /// The current nesting of expressions and statements, and its limit
+    depth: usize,
+    max_depth: usize,
+    /// The lines of the doc comments preceding the next token
+    docs: Vec<::std::string::String>,
+    /// The doc comment preceding the last consumed token, if any
+    doc: Option<Rc<str>>,
 }
 
 // Public methods on Parser
 impl<'a> Parser<'a> {
-    pub fn new(s: Scanner<'a>) -> Self { Parser { src: s.peekable() } }
+    pub fn new(s: Scanner<'a>) -> Self {
+        Parser {
+            src: s.peekable(),
+            pending: VecDeque::new(),
+            line: 0,
+            depth: 0,
+            max_depth: MAX_DEPTH,
+            docs: Vec::new(),
+            doc: None,
+        }
+    }
+
+    /// Configures the maximum nesting of expressions and statements, past
+    /// which parsing fails rather than risking a stack overflow.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Parses the entirety of the remaining source as a single expression.
+    pub fn parse_expression(&mut self) -> Result<Expr> {
+        let expr = self.expression();
+
+        if let Some(e) = self.pending.pop_front() {
+            return Err(e);
+        }
+
+        let expr = expr?;
+        self.must_next(&[Eof], "end of expression")?;
+        Ok(expr)
+    }
 }
 
 impl<'a> Iterator for Parser<'a> {
     type Item = Result<Stmt>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.src.peek().is_none() || self.check_next(&[Type::EOF]).is_some() {
+        if let Some(e) = self.pending.pop_front() {
+            return Some(Err(e));
+        }
+
+        if self.peek().is_none() || self.check_next(&[Type::Eof]).is_some() {
             return None;
         }
 
-        let res = self.statement();
-        if res.is_err() { self.synchronize(); }
+        match self.declaration() {
+            Ok(stmt) => if self.pending.is_empty() { return Some(Ok(stmt)); },
+            Err(e) => {
+                self.pending.push_back(e);
+                self.synchronize();
+            }
+        }
 
-        Some(res)
+        // a statement containing recovered errors is discarded
+        self.pending.pop_front().map(Err)
     }
 }
 
 // Private, statement-related methods on the Parser
 impl<'a> Parser<'a> {
+    fn declaration(&mut self) -> Result<Stmt> {
+        let n: Option<Result<Token>> = self.check_next(&[Var, Fun, Class, Export]);
+
+        if n.is_none() {
+            return self.statement();
+        }
+
+        let tkn: Token = n.unwrap()?;
+
+        let doc = self.doc.take();
+
+        match tkn.typ {
+            Var => self.decl_statement(),
+            Fun => self.function(doc),
+            Class => self.class_decl(doc),
+            Export => self.export_decl(tkn, doc),
+            _ => unreachable!(),
+        }
+    }
+
+    fn export_decl(&mut self, tkn: Token, doc: Option<Rc<str>>) -> Result<Stmt> {
+        let decl = match self.must_next(&[Var, Fun, Class], "declaration after 'export'")?.typ {
+            Var => self.decl_statement()?,
+            Fun => self.function(doc)?,
+            Class => self.class_decl(doc)?,
+            _ => unreachable!(),
+        };
+
+        Ok(Stmt::Export(tkn, decl.boxed()))
+    }
+
     fn statement(&mut self) -> Result<Stmt> {
+        self.nested("statement", Parser::statement_inner)
+    }
+
+    fn statement_inner(&mut self) -> Result<Stmt> {
         let n: Option<Result<Token>> = self.check_next(&[
             Semicolon,
             Print,
-            Var,
             LeftBrace,
             If,
             While,
             For,
             Break,
-            Fun,
             Return,
-            Class,
+            Import,
         ]);
 
         if n.is_none() {
@@ -59,47 +149,47 @@ impl<'a> Parser<'a> {
         match tkn.typ {
             Semicolon => Ok(Stmt::Empty),
             Print => self.print_statement(),
-            Var => self.decl_statement(),
             LeftBrace => self.block_statement(),
             If => self.if_statement(),
             While => self.while_statement(),
-            For => self.for_statement(),
+            For => self.for_statement(tkn),
             Break => self.break_statement(tkn),
-            Fun => self.function(),
             Return => self.return_statement(tkn),
-            Class => self.class_decl(),
+            Import => self.import_statement(tkn),
             _ => unreachable!(),
         }
     }
 
     fn print_statement(&mut self) -> Result<Stmt> {
         let expr: Expr = self.expression()?;
-        self.must_next(&[Semicolon])?;
+        self.must_next(&[Semicolon], "';' after value")?;
         Ok(Stmt::Print(expr))
     }
 
     fn if_statement(&mut self) -> Result<Stmt> {
-        self.must_next(&[LeftParen])?;
+        self.must_next(&[LeftParen], "'(' after 'if'")?;
         let expr: Expr = self.expression()?;
-        self.must_next(&[RightParen])?;
+        self.must_next(&[RightParen], "')' after if condition")?;
 
-        let then_stmt: Box<Stmt> = self.statement()?.boxed();
+        let then_stmt: Box<Stmt> = self.body("'if'")?.boxed();
 
         match self.check_next(&[Else]) {
             Some(Err(e)) => Err(e),
-            Some(Ok(_)) => Ok(Stmt::If(expr, then_stmt, Some(self.statement()?.boxed()))),
+            Some(Ok(_)) => Ok(Stmt::If(expr, then_stmt, Some(self.body("'else'")?.boxed()))),
             None => Ok(Stmt::If(expr, then_stmt, None)),
         }
     }
 
     fn while_statement(&mut self) -> Result<Stmt> {
+        self.must_next(&[LeftParen], "'(' after 'while'")?;
         let expr: Expr = self.expression()?;
-        let body: Box<Stmt> = self.statement()?.boxed();
+        self.must_next(&[RightParen], "')' after while condition")?;
+        let body: Box<Stmt> = self.body("'while'")?.boxed();
         Ok(Stmt::While(expr, body))
     }
 
-    fn for_statement(&mut self) -> Result<Stmt> {
-        self.must_next(&[LeftParen])?;
+    fn for_statement(&mut self, tkn: Token) -> Result<Stmt> {
+        self.must_next(&[LeftParen], "'(' after 'for'")?;
 
         let init: Option<Stmt> = match self.check_next(&[Semicolon, Var]) {
             None => Some(self.expr_statement()?),
@@ -113,15 +203,18 @@ impl<'a> Parser<'a> {
         let cond: Expr = match self.check_next(&[Semicolon]) {
             None => {
                 let expr = self.expression()?;
-                self.must_next(&[Semicolon])?;
+                self.must_next(&[Semicolon], "';' after loop condition")?;
                 expr
             }
             Some(t) => {
+                t?;
+
+                // an omitted condition is located at the loop it belongs to
                 Expr::Literal(Token {
                     typ: True,
                     lexeme: "true".to_owned(),
                     literal: Some(Literal::Boolean(true)),
-                    ..t?
+                    ..tkn
                 })
             }
         };
@@ -131,9 +224,9 @@ impl<'a> Parser<'a> {
         } else {
             Some(Stmt::Expression(self.expression()?))
         };
-        self.must_next(&[RightParen])?;
+        self.must_next(&[RightParen], "')' after for clauses")?;
 
-        let mut body: Stmt = self.statement()?;
+        let mut body: Stmt = self.body("'for'")?;
 
         if inc.is_some() {
             body = Stmt::Block(vec![body, inc.unwrap()]);
@@ -148,65 +241,97 @@ impl<'a> Parser<'a> {
         Ok(body)
     }
 
+    /// Parses the body of a control flow statement, which may not be a
+    /// declaration unless enclosed in a block. Imports and exports are
+    /// rejected likewise, as they are only allowed at the top level.
+    fn body(&mut self, context: &str) -> Result<Stmt> {
+        if !self.check(&[Var, Fun, Class, Import, Export]) {
+            return self.statement();
+        }
+
+        let tkn: Token = self.advance().expect("peeked declaration")?;
+        Err(Error::Parse(
+            tkn.line,
+            ParseKind::UnenclosedDeclaration(context.to_owned()),
+            tkn.lexeme))
+    }
+
     fn break_statement(&mut self, tkn: Token) -> Result<Stmt> {
-        self.must_next(&[Semicolon])?;
+        self.must_next(&[Semicolon], "';' after 'break'")?;
         Ok(Stmt::Break(tkn))
     }
 
     fn expr_statement(&mut self) -> Result<Stmt> {
         let expr: Expr = self.expression()?;
-        self.must_next(&[Semicolon])?;
+        self.must_next(&[Semicolon], "';' after expression")?;
         Ok(Stmt::Expression(expr))
     }
 
     fn decl_statement(&mut self) -> Result<Stmt> {
-        let id: Token = self.must_next(&[Identifier])?;
+        let id: Token = self.must_next(&[Identifier], "variable name")?;
 
-        if self.check_next(&[Equal]).is_none() {
-            return Ok(Stmt::Declaration(id, None));
-        }
-
-        let expr: Expr = self.expression()?;
+        let init = match self.check_next(&[Equal]) {
+            Some(eq) => { eq?; Some(self.expression()?.boxed()) }
+            None => None,
+        };
 
-        self.must_next(&[Semicolon])?;
+        self.must_next(&[Semicolon], "';' after variable declaration")?;
 
-        Ok(Stmt::Declaration(id, Some(expr.boxed())))
+        Ok(Stmt::Declaration(id, init))
     }
 
-    fn class_decl(&mut self) -> Result<Stmt> {
-        let id = self.must_next(&[Identifier])?;
+    fn class_decl(&mut self, doc: Option<Rc<str>>) -> Result<Stmt> {
+        let id = self.must_next(&[Identifier], "class name")?;
 
         let parent = if self.check_next(&[Less]).is_some() {
-            Some(Expr::Identifier(self.must_next(&[Identifier])?).boxed())
+            Some(Expr::Identifier(self.must_next(&[Identifier], "superclass name")?).boxed())
         } else { None };
 
-        self.must_next(&[LeftBrace])?;
+        self.must_next(&[LeftBrace], "'{' before class body")?;
 
         let mut methods = Vec::new();
-        while !self.check(&[RightBrace]) {
-            methods.push(self.function()?);
+        while !self.check(&[RightBrace, Eof]) && self.peek().is_some() {
+            match self.function(None) {
+                Ok(m) => methods.push(m),
+                Err(e) => {
+                    self.pending.push_back(e);
+                    self.synchronize_method();
+                }
+            }
         }
 
-        self.must_next(&[RightBrace])?;
+        self.must_next(&[RightBrace], "'}' after class body")?;
 
         methods.shrink_to_fit();
-        Ok(Stmt::Class(id, parent, methods))
+        Ok(Stmt::Class(id, parent, methods, doc))
     }
 
     fn block_statement(&mut self) -> Result<Stmt> {
         let mut stmts: Vec<Stmt> = Vec::new();
 
-        while self.check_next(&[RightBrace]).is_none() && self.src.peek().is_some() {
-            stmts.push(self.statement()?);
-        }
+        while self.check_next(&[RightBrace]).is_none() {
+            if self.check(&[Eof]) || self.peek().is_none() {
+                return Err(self.expected("'}' after block"));
+            }
 
+            match self.declaration() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(e) => {
+                    self.pending.push_back(e);
+                    self.synchronize_block();
+                }
+            }
+        }
 
         Ok(Stmt::Block(stmts))
     }
 
-    fn function(&mut self) -> Result<Stmt> {
-        let name: Token = self.must_next(&[Identifier])?;
-        self.must_next(&[LeftParen])?;
+    /// Parses a function following its `fun` keyword, or a method. Methods
+    /// have no keyword, so their doc comment precedes their name.
+    fn function(&mut self, doc: Option<Rc<str>>) -> Result<Stmt> {
+        let name: Token = self.must_next(&[Identifier], "function name")?;
+        let doc = doc.or_else(|| self.doc.take());
+        self.must_next(&[LeftParen], "'(' after function name")?;
 
         let mut params: Vec<Token> = Vec::new();
 
@@ -214,11 +339,11 @@ impl<'a> Parser<'a> {
             loop {
                 if params.len() >= FUNCTION_ARGS_MAX {
                     return Err(Error::Parse(name.line,
-                                            format!("cannot have more than {} arguments", FUNCTION_ARGS_MAX),
+                                            ParseKind::TooManyArguments(FUNCTION_ARGS_MAX),
                                             name.lexeme));
                 }
 
-                params.push(self.must_next(&[Identifier])?);
+                params.push(self.must_next(&[Identifier], "parameter name")?);
 
                 if self.check_next(&[Comma]).is_none() {
                     break;
@@ -226,10 +351,10 @@ impl<'a> Parser<'a> {
             }
         }
 
-        self.must_next(&[RightParen])?;
-        self.must_next(&[LeftBrace])?;
+        self.must_next(&[RightParen], "')' after parameters")?;
+        self.must_next(&[LeftBrace], "'{' before function body")?;
 
-        Ok(Stmt::Function(name, params, Rc::new(self.block_statement()?)))
+        Ok(Stmt::Function(name, params, Rc::new(self.block_statement()?), doc))
     }
 
     fn return_statement(&mut self, tkn: Token) -> Result<Stmt> {
@@ -239,15 +364,23 @@ impl<'a> Parser<'a> {
             Some(self.expression()?.boxed())
         };
 
-        self.must_next(&[Semicolon])?;
+        self.must_next(&[Semicolon], "';' after return value")?;
 
         Ok(Stmt::Return(tkn, expr))
     }
+
+    fn import_statement(&mut self, tkn: Token) -> Result<Stmt> {
+        let path = self.must_next(&[String], "module path after 'import'")?;
+        self.must_next(&[Semicolon], "';' after module path")?;
+        Ok(Stmt::Import(tkn, path))
+    }
 }
 
 // Private, expression-related methods on the Parser
 impl<'a> Parser<'a> {
-    fn expression(&mut self) -> Result<Expr> { self.assignment() }
+    fn expression(&mut self) -> Result<Expr> {
+        self.nested("expression", Parser::assignment)
+    }
 
     fn assignment(&mut self) -> Result<Expr> {
         let expr: Expr = self.logical_or()?;
@@ -259,9 +392,9 @@ impl<'a> Parser<'a> {
                 Expr::Identifier(tkn) =>
                     Ok(Expr::Assignment(tkn, self.assignment()?.boxed())),
                 Expr::Get(settee, prop) =>
-                    Ok(Expr::Set(settee.boxed(), prop, self.assignment()?.boxed())),
+                    Ok(Expr::Set(settee, prop, self.assignment()?.boxed())),
                 _ =>
-                    Err(Parser::unexpected(&eq)),
+                    Err(Error::Parse(eq.line, ParseKind::InvalidAssignment, eq.lexeme)),
             };
         }
 
@@ -269,89 +402,74 @@ impl<'a> Parser<'a> {
     }
 
     fn logical_or(&mut self) -> Result<Expr> {
-        let mut expr: Expr = self.logical_and()?;
-
-        while let Some(op) = self.check_next(&[Or]) {
-            expr = Expr::Binary(expr.boxed(), op?, self.logical_and()?.boxed());
-        }
-
-        Ok(expr)
+        self.binary(&[Or], Parser::logical_and)
     }
 
     fn logical_and(&mut self) -> Result<Expr> {
-        let mut expr: Expr = self.equality()?;
-
-        while let Some(op) = self.check_next(&[And]) {
-            expr = Expr::Binary(expr.boxed(), op?, self.equality()?.boxed());
-        }
-
-        Ok(expr)
+        self.binary(&[And], Parser::equality)
     }
 
     fn equality(&mut self) -> Result<Expr> {
-        let mut expr: Expr = self.comparison()?;
-
-        while let Some(op) = self.check_next(&[BangEqual, EqualEqual]) {
-            expr = Expr::Binary(expr.boxed(), op?, self.comparison()?.boxed());
-        }
-
-        Ok(expr)
+        self.binary(&[BangEqual, EqualEqual], Parser::comparison)
     }
 
     fn comparison(&mut self) -> Result<Expr> {
-        let mut expr: Expr = self.term()?;
-
-        while let Some(op) = self.check_next(&[Greater, GreaterEqual, Less, LessEqual]) {
-            expr = Expr::Binary(expr.boxed(), op?, self.term()?.boxed());
-        }
-
-        Ok(expr)
+        self.binary(&[Greater, GreaterEqual, Less, LessEqual], Parser::term)
     }
 
     fn term(&mut self) -> Result<Expr> {
-        let mut expr: Expr = self.factor()?;
-
-        while let Some(op) = self.check_next(&[Minus, Plus]) {
-            expr = Expr::Binary(expr.boxed(), op?, self.factor()?.boxed());
-        }
-
-        Ok(expr)
+        self.binary(&[Minus, Plus], Parser::factor)
     }
 
     fn factor(&mut self) -> Result<Expr> {
-        let mut expr: Expr = self.unary()?;
+        self.binary(&[Star, Slash], Parser::unary)
+    }
 
-        while let Some(op) = self.check_next(&[Star, Slash]) {
-            expr = Expr::Binary(expr.boxed(), op?, self.unary()?.boxed());
-        }
+    /// Parses a chain of left-associative binary operators.
+    fn binary(&mut self, ops: &[Type], operand: fn(&mut Self) -> Result<Expr>) -> Result<Expr> {
+        self.chained(|p| {
+            let mut expr: Expr = operand(p)?;
 
-        Ok(expr)
+            while let Some(op) = p.check_next(ops) {
+                let op = op?;
+                p.deepen("expression")?;
+                expr = Expr::Binary(expr.boxed(), op, operand(p)?.boxed());
+            }
+
+            Ok(expr)
+        })
     }
 
     fn unary(&mut self) -> Result<Expr> {
         if let Some(op) = self.check_next(&[Bang, Minus]) {
-            return Ok(Expr::Unary(op?, self.unary()?.boxed()));
+            let op = op?;
+            return Ok(Expr::Unary(op, self.nested("expression", Parser::unary)?.boxed()));
         }
 
         self.call()
     }
 
     fn call(&mut self) -> Result<Expr> {
-        let mut expr = self.primary()?;
+        self.chained(|p| {
+            let mut expr = p.primary()?;
 
-        loop {
-            expr = match self.check_next(&[LeftParen, Dot]) {
-                Some(Err(e)) => return Err(e),
-                Some(Ok(tkn)) => match tkn.typ {
-                    LeftParen => self.finish_call(expr)?,
-                    Dot => Expr::Get(expr.boxed(), self.must_next(&[Identifier])?),
-                    _ => unreachable!(),
-                },
-                None => break,
-            };
-        }
+            loop {
+                expr = match p.check_next(&[LeftParen, Dot]) {
+                    Some(Err(e)) => return Err(e),
+                    Some(Ok(tkn)) => {
+                        p.deepen("expression")?;
+                        match tkn.typ {
+                            LeftParen => p.finish_call(expr)?,
+                            Dot => Expr::Get(expr.boxed(), p.must_next(&[Identifier], "property name after '.'")?),
+                            _ => unreachable!(),
+                        }
+                    }
+                    None => break,
+                };
+            }
 
-        Ok(expr)
+            Ok(expr)
+        })
     }
 
     fn finish_call(&mut self, callee: Expr) -> Result<Expr> {
@@ -361,11 +479,17 @@ impl<'a> Parser<'a> {
             loop {
                 if args.len() >= 8 {
                     return Err(Error::Parse(0,
-                                            "cannot have more than 8 arguments".to_string(),
+                                            ParseKind::TooManyArguments(8),
                                             "".to_string()));
                 }
 
-                args.push(self.expression()?);
+                match self.expression() {
+                    Ok(arg) => args.push(arg),
+                    Err(e) => {
+                        self.pending.push_back(e);
+                        self.synchronize_argument();
+                    }
+                }
 
                 match self.check_next(&[Comma]) {
                     Some(r) => r?,
@@ -376,7 +500,7 @@ impl<'a> Parser<'a> {
 
         Ok(Expr::Call(
             callee.boxed(),
-            self.must_next(&[RightParen])?,
+            self.must_next(&[RightParen], "')' after arguments")?,
             args))
     }
 
@@ -392,70 +516,136 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(Ok(tkn)) = self.check_next(&[Super]) {
-            self.must_next(&[Dot])?;
-            let method = self.must_next(&[Identifier])?;
+            self.must_next(&[Dot], "'.' after 'super'")?;
+            let method = self.must_next(&[Identifier], "superclass method name")?;
+
             return Ok(Expr::Super(tkn, method));
         }
 
         if let Some(Ok(_)) = self.check_next(&[LeftParen]) {
             let expr = self.expression()?;
-            let _ = self.must_next(&[RightParen])?;
+            let _ = self.must_next(&[RightParen], "')' after expression")?;
             return Ok(Expr::Grouping(expr.boxed()));
         }
 
-        Err(self.peek_err())
+        Err(self.expected("expression"))
     }
 }
 
 // Token iterator related methods on the Parser
 impl<'a> Parser<'a> {
+    /// Parses a nested construct, failing if it would exceed the maximum
+    /// depth.
+    fn nested<T, F>(&mut self, what: &str, f: F) -> Result<T>
+        where F: FnOnce(&mut Self) -> Result<T>
+    {
+        self.deepen(what)?;
+        let out = f(self);
+        self.depth -= 1;
+        out
+    }
+
+    /// Parses a chain of operators, such as `a + b + c` or `f()()`. Chains
+    /// are built iteratively, but each link nests the chain so far one level
+    /// deeper for the passes walking it, so counts towards the maximum depth.
+    fn chained<F>(&mut self, f: F) -> Result<Expr>
+        where F: FnOnce(&mut Self) -> Result<Expr>
+    {
+        let depth = self.depth;
+        let out = f(self);
+        self.depth = depth;
+        out
+    }
+
+    /// Increments the depth, failing if that exceeds the maximum.
+    fn deepen(&mut self, what: &str) -> Result<()> {
+        if self.depth >= self.max_depth {
+            let near = match self.peek() {
+                Some(Ok(tkn)) => tkn.lexeme.clone(),
+                _ => "EOF".to_owned(),
+            };
+
+            return Err(Error::Parse(self.line, ParseKind::TooDeeplyNested(what.to_owned()), near));
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Peeks at the next token, first setting aside any doc comments.
+    fn peek(&mut self) -> Option<&Result<Token>> {
+        while let Some(&Ok(Token { typ: DocComment, .. })) = self.src.peek() {
+            if let Some(Ok(tkn)) = self.src.next() {
+                let text = tkn.lexeme.trim_start_matches('/').trim_end();
+                self.docs.push(text.strip_prefix(' ').unwrap_or(text).to_owned());
+            }
+        }
+
+        self.src.peek()
+    }
+
     fn check(&mut self, types: &[Type]) -> bool {
-        match self.src.peek() {
+        match self.peek() {
             Some(&Ok(ref t)) => t.in_types(types),
             _ => false,
         }
     }
 
+    fn advance(&mut self) -> Option<Result<Token>> {
+        self.peek();
+        let tkn = self.src.next();
+
+        self.doc = if self.docs.is_empty() { None } else { Some(self.docs.join("\n").into()) };
+        self.docs.clear();
+
+        if let Some(Ok(ref t)) = tkn {
+            self.line = t.line;
+        }
+
+        tkn
+    }
+
     fn check_next(&mut self, types: &[Type]) -> Option<Result<Token>> {
         if self.check(types) {
-            return self.src.next();
+            return self.advance();
         }
         None
     }
 
-    fn must_next(&mut self, types: &[Type]) -> Result<Token> {
+    fn must_next(&mut self, types: &[Type], context: &str) -> Result<Token> {
         if let Some(res) = self.check_next(types) {
             return res;
         }
 
-        Err(self.peek_err())
+        Err(self.expected(context))
     }
 
-    fn peek_err(&mut self) -> Error {
-        {
-            // peek for EOF and unexpected tokens
-            let pk: Option<&Result<Token>> = self.src.peek();
-
-            if pk.is_none() {
-                return Parser::eof();
+    fn expected(&mut self, context: &str) -> Error {
+        let (line, found, near) = match self.peek() {
+            None => (self.line, "EOF".to_owned(), "EOF".to_owned()),
+            Some(Ok(tkn)) if tkn.typ == Eof => (tkn.line, "EOF".to_owned(), "EOF".to_owned()),
+            Some(Ok(tkn)) => (tkn.line, format!("'{}'", tkn.lexeme), tkn.lexeme.clone()),
+            Some(Err(_)) => {
+                // lexical or other error encountered
+                return self.advance().unwrap().unwrap_err();
             }
+        };
 
-            if let Ok(tkn) = pk.unwrap().as_ref() {
-                return Parser::unexpected(tkn);
-            }
-        }
+        let kind = match context {
+            "expression" => ParseKind::ExpectedExpression(found),
+            _ => ParseKind::Expected { expected: context.to_owned(), found },
+        };
 
-        // lexical or other error encountered
-        self.src.next().unwrap().unwrap_err()
+        Error::Parse(line, kind, near)
     }
 
     fn synchronize(&mut self) {
         loop {
-            if let Some(&Err(_)) = self.src.peek() {
+            if let Some(&Err(_)) = self.peek() {
                 return;
             }
 
-            let tkn: Option<Result<Token>> = self.src.next();
+            let tkn: Option<Result<Token>> = self.advance();
 
             if tkn.is_none() { return; }
 
@@ -469,6 +659,8 @@ impl<'a> Parser<'a> {
                     While,
                     Print,
                     Return,
+                    Import,
+                    Export,
                 ]) {
                     return;
                 }
@@ -476,17 +668,92 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn eof() -> Error {
-        Error::Parse(0, "".to_string(), "unexpected EOF".to_string())
+    /// Skips to the start of the next statement within a block, leaving the
+    /// block's closing brace to be consumed by the caller.
+    fn synchronize_block(&mut self) {
+        let mut depth = 0;
+
+        loop {
+            match self.peek() {
+                Some(Ok(t)) if t.typ == Eof => return,
+                Some(Ok(t)) if depth == 0 && t.in_types(&[
+                    RightBrace,
+                    Class,
+                    Fun,
+                    Var,
+                    For,
+                    If,
+                    While,
+                    Print,
+                    Return,
+                    Import,
+                    Export,
+                ]) => return,
+                Some(Ok(_)) => (),
+                _ => return,
+            }
+
+            match self.advance() {
+                Some(Ok(ref t)) if t.typ == LeftBrace => depth += 1,
+                Some(Ok(ref t)) if t.typ == RightBrace => depth -= 1,
+                Some(Ok(ref t)) if t.typ == Semicolon && depth == 0 => return,
+                _ => (),
+            }
+        }
+    }
+
+    /// Skips past the remainder of a malformed method, stopping at the next
+    /// method or the class's closing brace.
+    fn synchronize_method(&mut self) {
+        let mut depth = 0;
+
+        loop {
+            match self.peek() {
+                Some(Ok(t)) if t.typ == Eof => return,
+                Some(Ok(t)) if depth == 0 && t.typ == RightBrace => return,
+                Some(Ok(_)) => (),
+                _ => return,
+            }
+
+            match self.advance() {
+                Some(Ok(ref t)) if t.typ == LeftBrace => depth += 1,
+                Some(Ok(ref t)) if t.typ == RightBrace => {
+                    depth -= 1;
+                    if depth == 0 { return; }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Skips to the next argument or the end of the argument list, leaving
+    /// the delimiter to be consumed by the caller.
+    fn synchronize_argument(&mut self) {
+        let mut depth = 0;
+
+        loop {
+            match self.peek() {
+                Some(Ok(t)) if t.in_types(&[Eof, Semicolon, LeftBrace, RightBrace]) => return,
+                Some(Ok(t)) if depth == 0 && t.in_types(&[Comma, RightParen]) => return,
+                Some(Ok(_)) => (),
+                _ => return,
+            }
+
+            match self.advance() {
+                Some(Ok(ref t)) if t.typ == LeftParen => depth += 1,
+                Some(Ok(ref t)) if t.typ == RightParen => depth -= 1,
+                _ => (),
+            }
+        }
     }
 
     fn unexpected(tkn: &Token) -> Error {
         let lex = match tkn.typ {
-            EOF => "EOF".to_string(),
+            Eof => "EOF".to_string(),
             _ => tkn.lexeme.clone(),
         };
 
-        Error::Parse(tkn.line, "unexpected token".to_string(), lex)
+        Error::Parse(tkn.line, ParseKind::UnexpectedToken, lex)
     }
 }
 
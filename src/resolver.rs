@@ -1,92 +1,151 @@
 use ast::expr::Visitor as ExprVisitor;
-use ast::expr::Expr;
+use ast::expr::{walk_expr, Expr};
 use ast::stmt::Visitor as StmtVisitor;
-use ast::stmt::Stmt;
-use result::{Result, Error};
+use ast::stmt::{walk_stmt, Stmt};
+use result::{Result, Error, ParseKind};
 use interpreter::Interpreter;
 use std::collections::HashMap;
 use functions::Type as FunctionType;
 use ast::token::Token;
+use ast::token::Type as TokenType;
 use std::rc::Rc;
 use class::{THIS_ID, SUPER_ID};
 use class::Type as ClassType;
 use functions::INITIALIZER_FUNC;
+use lint::{Lint, Lints, Warning};
+use constant;
 
 pub struct Resolver<'a> {
     interpreter: &'a mut Interpreter,
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<HashMap<String, Binding>>,
     current_function: FunctionType,
     current_class: ClassType,
+    lints: Lints,
+    warnings: Vec<Warning>,
+    /// The nesting depth of function declarations being resolved
+    depth: usize,
+    /// The straight-line region of code being resolved; stores are only
+    /// considered dead if overwritten within the same region
+    region: usize,
+    regions: usize,
+    /// Whether the statements being resolved follow an unconditional exit
+    terminated: bool,
+}
+
+/// A local variable declared within a scope
+struct Binding {
+    decl: Token,
+    defined: bool,
+    /// The function depth the variable was declared at
+    depth: usize,
+    /// Whether the variable is accessed from a nested function, and may be
+    /// read at any time
+    captured: bool,
+    /// The last assignment to the variable that has not yet been read, and
+    /// the region it occurred in
+    store: Option<(Token, usize)>,
+    /// The arity of the function declared by this binding, unless the
+    /// variable is ever reassigned
+    arity: Option<usize>,
+    /// The direct calls made through this binding, and their argument counts
+    calls: Vec<(Token, usize)>,
 }
 
 impl<'a> Resolver<'a> {
-    fn new(i: &'a mut Interpreter) -> Resolver {
+    fn new(i: &'a mut Interpreter, lints: Lints) -> Resolver<'a> {
         Self {
             interpreter: i,
             scopes: Vec::new(),
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            lints,
+            warnings: Vec::new(),
+            depth: 0,
+            region: 0,
+            regions: 0,
+            terminated: false,
         }
     }
 
-    pub fn resolve(i: &'a mut Interpreter, stmt: &Stmt) -> Result<&'a mut Interpreter> {
-        let mut res = Self::new(i);
-        stmt.accept(&mut res)?;
+    /// Resolves a standalone expression, as evaluated by `Interpreter::eval_expr`.
+    pub fn resolve_expr(i: &'a mut Interpreter, expr: &Expr) -> Result<&'a mut Interpreter> {
+        let mut res = Self::new(i, Lints::default());
+        expr.accept(&mut res)?;
+        Ok(res.interpreter)
+    }
+
+    /// Resolves the statement, appending any warnings raised by the enabled
+    /// lints to `warnings`.
+    pub fn resolve_with(i: &'a mut Interpreter, stmt: &Stmt, lints: Lints, warnings: &mut Vec<Warning>)
+                        -> Result<&'a mut Interpreter> {
+        let mut res = Self::new(i, lints);
+        let out = stmt.accept(&mut res);
+        warnings.append(&mut res.warnings);
+        out?;
+        Ok(res.interpreter)
+    }
+
+    /// Resolves every statement of a program in order with the same
+    /// resolver, stopping at the first error.
+    pub fn resolve_all(i: &'a mut Interpreter, stmts: &[Stmt], lints: Lints, warnings: &mut Vec<Warning>)
+                       -> Result<&'a mut Interpreter> {
+        let mut res = Self::new(i, lints);
+        let out = stmts.iter().try_for_each(|s| s.accept(&mut res));
+        warnings.append(&mut res.warnings);
+        out?;
         Ok(res.interpreter)
     }
 }
 
 impl<'a> ExprVisitor<Result<()>> for Resolver<'a> {
     fn visit_expr(&mut self, expr: &Expr) -> Result<()> {
-        Err(Error::Parse(
-            0,
-            format!("{:?}", expr),
-            "".to_owned(),
-        ))
+        walk_expr(self, expr)
     }
 
     fn visit_identifier(&mut self, expr: &Expr, id: &Token) -> Result<()> {
         let own_init: bool = self.scopes.last()
             .and_then(|s| s.get(&id.lexeme))
-            .map_or(false, |d| !*d);
+            .map_or(false, |b| !b.defined);
 
         if own_init {
             return Err(Error::Parse(
                 id.line,
-                "cannot read local variable in its own initializer.".to_owned(),
+                ParseKind::OwnInitializer,
                 id.lexeme.clone()));
         }
 
         self.resolve_local(id, expr);
+        self.read(id);
         Ok(())
     }
 
-    fn visit_literal(&mut self, _expr: &Expr, _lit: &Token) -> Result<()> {
-        Ok(())
-    }
-
-    fn visit_grouping(&mut self, _expr: &Expr, inside: &Expr) -> Result<()> {
-        inside.accept(self)
-    }
-
-    fn visit_unary(&mut self, _expr: &Expr, _op: &Token, rhs: &Expr) -> Result<()> {
-        rhs.accept(self)
-    }
+    fn visit_binary(&mut self, _expr: &Expr, lhs: &Expr, op: &Token, rhs: &Expr) -> Result<()> {
+        use ast::token::Type::{And, Or};
 
-    fn visit_binary(&mut self, _expr: &Expr, lhs: &Expr, _op: &Token, rhs: &Expr) -> Result<()> {
         lhs.accept(self)?;
-        rhs.accept(self)
+
+        match op.typ {
+            And | Or => self.in_region(|r| rhs.accept(r)),
+            _ => rhs.accept(self),
+        }
     }
 
     fn visit_assignment(&mut self, expr: &Expr, id: &Token, val: &Expr) -> Result<()> {
         val.accept(self)?;
         self.resolve_local(id, expr);
+        self.write(id);
         Ok(())
     }
 
-    fn visit_call(&mut self, _expr: &Expr, callee: &Expr, _paren: &Token, args: &[Expr]) -> Result<()> {
+    fn visit_call(&mut self, _expr: &Expr, callee: &Expr, paren: &Token, args: &[Expr]) -> Result<()> {
         callee.accept(self)?;
 
+        if let Expr::Identifier(ref id) = *callee {
+            if let Some(b) = self.binding(id) {
+                b.calls.push((paren.clone(), args.len()));
+            }
+        }
+
         for arg in args {
             arg.accept(self)?;
         }
@@ -94,20 +153,11 @@ impl<'a> ExprVisitor<Result<()>> for Resolver<'a> {
         Ok(())
     }
 
-    fn visit_get(&mut self, _expr: &Expr, callee: &Expr, _prop: &Token) -> Result<()> {
-        callee.accept(self)
-    }
-
-    fn visit_set(&mut self, _expr: &Expr, settee: &Expr, _prop: &Token, val: &Expr) -> Result<()> {
-        val.accept(self)?;
-        settee.accept(self)
-    }
-
     fn visit_this(&mut self, expr: &Expr, tkn: &Token) -> Result<()> {
         if self.current_class == ClassType::None {
             return Err(Error::Parse(
                 tkn.line,
-                "cannot use 'this' outside of a class".to_owned(),
+                ParseKind::ThisOutsideClass,
                 tkn.lexeme.to_owned(),
             ));
         }
@@ -120,11 +170,11 @@ impl<'a> ExprVisitor<Result<()>> for Resolver<'a> {
         match self.current_class {
             ClassType::None => Err(Error::Parse(
                 tkn.line,
-                "cannot use 'super' outside of a class".to_owned(),
+                ParseKind::SuperOutsideClass,
                 tkn.lexeme.to_owned())),
             ClassType::Class => Err(Error::Parse(
                 tkn.line,
-                "cannot use 'super' in a class with no superclass".to_owned(),
+                ParseKind::SuperWithoutSuperclass,
                 tkn.lexeme.to_owned())),
             ClassType::SubClass => {
                 self.resolve_local(tkn, expr);
@@ -135,14 +185,22 @@ impl<'a> ExprVisitor<Result<()>> for Resolver<'a> {
 }
 
 impl<'a> StmtVisitor<Result<()>> for Resolver<'a> {
-    fn visit_stmt(&mut self, _stmt: &Stmt) -> Result<()> { Ok(()) }
+    fn visit_stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        walk_stmt(self, stmt)
+    }
 
-    fn visit_expr_stmt(&mut self, _stmt: &Stmt, expr: &Expr) -> Result<()> {
-        expr.accept(self)
+    fn visit_break(&mut self, _stmt: &Stmt, _tkn: &Token) -> Result<()> {
+        self.terminated = true;
+        Ok(())
+    }
+
+    fn visit_import(&mut self, _stmt: &Stmt, tkn: &Token, _path: &Token) -> Result<()> {
+        self.top_level("imports", tkn)
     }
 
-    fn visit_print(&mut self, _stmt: &Stmt, expr: &Expr) -> Result<()> {
-        expr.accept(self)
+    fn visit_export(&mut self, _stmt: &Stmt, tkn: &Token, decl: &Stmt) -> Result<()> {
+        self.top_level("exports", tkn)?;
+        decl.accept(self)
     }
 
     fn visit_decl(&mut self, _stmt: &Stmt, id: &Token, init: Option<&Expr>) -> Result<()> {
@@ -158,33 +216,59 @@ impl<'a> StmtVisitor<Result<()>> for Resolver<'a> {
     fn visit_block(&mut self, _stmt: &Stmt, body: &[Stmt]) -> Result<()> {
         self.begin_scope();
 
+        // blocks nested within unreachable code were reported by their parent
+        let mut warned = self.terminated;
         for s in body {
+            if self.terminated && !warned {
+                warned = self.check_unreachable(s);
+            }
+
             s.accept(self)?;
         }
 
-        self.end_scope();
-
-        Ok(())
+        self.end_scope()
     }
 
     fn visit_if(&mut self, _stmt: &Stmt, cond: &Expr, then: &Stmt, els: Option<&Stmt>) -> Result<()> {
+        self.check_condition(cond);
         cond.accept(self)?;
-        then.accept(self)?;
+        self.in_region(|r| then.accept(r))?;
+
+        let then_terminated = self.terminated;
+        self.terminated = false;
 
         if let Some(stmt) = els {
-            stmt.accept(self)?;
+            self.in_region(|r| stmt.accept(r))?;
         }
 
+        self.terminated = then_terminated && self.terminated;
+
         Ok(())
     }
 
     fn visit_while(&mut self, _stmt: &Stmt, cond: &Expr, body: &Stmt) -> Result<()> {
-        cond.accept(self)?;
-        body.accept(self)
+        // `while (true)` is the idiomatic infinite loop
+        match *cond {
+            Expr::Literal(ref tkn) if tkn.typ == TokenType::True => (),
+            _ => self.check_condition(cond),
+        }
+
+        self.in_region(|r| {
+            cond.accept(r)?;
+            body.accept(r)
+        })?;
+
+        self.terminated = false;
+        Ok(())
     }
 
     fn visit_func(&mut self, _stmt: &Stmt, id: &Token, params: &[Token], body: Rc<Stmt>) -> Result<()> {
         self.declare_and_define(id)?;
+
+        if let Some(b) = self.scopes.last_mut().and_then(|s| s.get_mut(&id.lexeme)) {
+            b.arity = Some(params.len());
+        }
+
         self.resolve_function(params, body.as_ref(), FunctionType::Function)
     }
 
@@ -193,10 +277,10 @@ impl<'a> StmtVisitor<Result<()>> for Resolver<'a> {
 
         match self.current_function {
             None => return Err(Error::Parse(tkn.line,
-                                            "cannot return from top-level code".to_owned(),
+                                            ParseKind::TopLevelReturn,
                                             tkn.lexeme.to_owned())),
             Initializer => return Err(Error::Parse(tkn.line,
-                                                   "cannot return a value from an initializer".to_owned(),
+                                                   ParseKind::InitializerReturn,
                                                    tkn.lexeme.to_owned())),
             _ => ()
         };
@@ -205,6 +289,7 @@ impl<'a> StmtVisitor<Result<()>> for Resolver<'a> {
             expr.accept(self)?;
         }
 
+        self.terminated = true;
         Ok(())
     }
 
@@ -214,18 +299,24 @@ impl<'a> StmtVisitor<Result<()>> for Resolver<'a> {
         self.current_class = ClassType::Class;
 
         if let Some(expr) = parent {
+            if let Expr::Identifier(ref p) = *expr {
+                if p.lexeme == id.lexeme {
+                    return Err(Error::Parse(
+                        p.line,
+                        ParseKind::InheritsFromItself,
+                        p.lexeme.to_owned()));
+                }
+            }
+
             self.current_class = ClassType::SubClass;
             expr.accept(self)?;
             self.begin_scope();
             self.declare_and_define(&SUPER_ID)?;
         }
 
-        self.begin_scope();
-        self.declare_and_define(&THIS_ID)?;
-
         for method in methods {
             match *method {
-                Stmt::Function(ref id, ref params, ref body) => {
+                Stmt::Function(ref id, ref params, ref body, _) => {
                     let typ = if id.lexeme.eq(INITIALIZER_FUNC) {
                         FunctionType::Initializer
                     } else { FunctionType::Method };
@@ -236,8 +327,7 @@ impl<'a> StmtVisitor<Result<()>> for Resolver<'a> {
             };
         }
 
-        self.end_scope();
-        if parent.is_some() { self.end_scope(); }
+        if parent.is_some() { self.end_scope()?; }
         self.current_class = prev;
 
         Ok(())
@@ -247,14 +337,63 @@ impl<'a> StmtVisitor<Result<()>> for Resolver<'a> {
 impl<'a> Resolver<'a> {
     fn begin_scope(&mut self) { self.scopes.push(HashMap::new()); }
 
-    fn end_scope(&mut self) { self.scopes.pop(); }
+    fn top_level(&self, what: &str, tkn: &Token) -> Result<()> {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Err(Error::Parse(
+            tkn.line,
+            ParseKind::NotTopLevel(what.to_owned()),
+            tkn.lexeme.to_owned()))
+    }
+
+    /// Closes the innermost scope, checking the argument counts of direct
+    /// calls to the functions declared within it.
+    fn end_scope(&mut self) -> Result<()> {
+        let scope = match self.scopes.pop() {
+            Some(scope) => scope,
+            None => return Ok(()),
+        };
+
+        let mismatch = scope.values()
+            .filter_map(|b| b.arity.map(|n| (n, &b.calls)))
+            .flat_map(|(n, calls)| calls.iter()
+                .filter(move |&&(_, argc)| argc != n)
+                .map(move |&(ref paren, argc)| (paren, n, argc)))
+            .min_by_key(|&(paren, _, _)| (paren.line, paren.offset));
+
+        match mismatch {
+            Some((paren, n, argc)) => Err(Error::Parse(
+                paren.line,
+                ParseKind::ArityMismatch { expected: n, found: argc },
+                paren.lexeme.to_owned())),
+            None => Ok(()),
+        }
+    }
 
     fn declare(&mut self, id: &Token) -> Result<()> {
+        // this and super are implicitly declared, and can only be shadowed
+        // by other classes
+        if self.lints.shadowing && id.typ == TokenType::Identifier {
+            self.check_shadowing(id);
+        }
+
+        let depth = self.depth;
         if let Some(scope) = self.scopes.last_mut() {
-            if scope.insert(id.lexeme.to_owned(), false).is_some() {
+            let binding = Binding {
+                decl: id.clone(),
+                defined: false,
+                depth,
+                captured: false,
+                store: None,
+                arity: None,
+                calls: Vec::new(),
+            };
+            if scope.insert(id.lexeme.to_owned(), binding).is_some() {
                 return Err(Error::Parse(
                     id.line,
-                    "variable already defined with that name in this scope".to_owned(),
+                    ParseKind::AlreadyDeclared,
                     id.lexeme.to_owned()));
             }
         }
@@ -263,13 +402,30 @@ impl<'a> Resolver<'a> {
     }
 
     fn define(&mut self, id: &Token) -> Result<()> {
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(id.lexeme.to_owned(), true);
+        if let Some(binding) = self.scopes.last_mut().and_then(|s| s.get_mut(&id.lexeme)) {
+            binding.defined = true;
         }
 
         Ok(())
     }
 
+    fn check_shadowing(&mut self, id: &Token) {
+        let outer = match self.scopes.split_last() {
+            Some((_, outer)) => outer,
+            None => return,
+        };
+
+        let shadowed = outer.iter().rev()
+            .filter_map(|s| s.get(&id.lexeme))
+            .next()
+            .map(|b| b.decl.line);
+
+        if let Some(line) = shadowed {
+            let msg = format!("`{}` shadows the declaration on line {}", id.lexeme, line);
+            self.warn(Lint::Shadowing, id, msg);
+        }
+    }
+
     fn declare_and_define(&mut self, id: &Token) -> Result<()> {
         self.declare(id)?;
         self.define(id)
@@ -287,17 +443,111 @@ impl<'a> Resolver<'a> {
 
     fn resolve_function(&mut self, params: &[Token], body: &Stmt, typ: FunctionType) -> Result<()> {
         let prev = self.current_function;
+        let terminated = self.terminated;
         self.current_function = typ;
+        self.terminated = false;
+        self.depth += 1;
         self.begin_scope();
 
+        // methods are bound to `this` when called, alongside their parameters
+        if typ == FunctionType::Method || typ == FunctionType::Initializer {
+            self.declare_and_define(&THIS_ID)?;
+        }
+
         for param in params {
             self.declare_and_define(param)?;
         }
 
-        body.accept(self)?;
+        self.in_region(|r| body.accept(r))?;
 
-        self.end_scope();
+        self.end_scope()?;
+        self.depth -= 1;
+        self.terminated = terminated;
         self.current_function = prev;
         Ok(())
     }
+
+    fn in_region<F>(&mut self, f: F) -> Result<()> where F: FnOnce(&mut Self) -> Result<()> {
+        let prev = self.region;
+        self.regions += 1;
+        self.region = self.regions;
+
+        let res = f(self);
+
+        self.region = prev;
+        res
+    }
+
+    fn binding(&mut self, id: &Token) -> Option<&mut Binding> {
+        let depth = self.depth;
+
+        let b = self.scopes.iter_mut().rev()
+            .filter_map(|s| s.get_mut(&id.lexeme))
+            .next()?;
+
+        b.captured = b.captured || b.depth != depth;
+        Some(b)
+    }
+
+    fn read(&mut self, id: &Token) {
+        if let Some(b) = self.binding(id) {
+            b.store = None;
+        }
+    }
+
+    fn write(&mut self, id: &Token) {
+        let (region, lints) = (self.region, self.lints);
+
+        let dead = self.binding(id).and_then(|b| {
+            // reassigned functions can no longer be checked statically
+            b.arity = None;
+
+            if b.captured {
+                return None;
+            }
+
+            b.store.replace((id.clone(), region))
+                .filter(|&(_, r)| r == region)
+        });
+
+        if let Some((tkn, _)) = dead {
+            if lints.dead_stores {
+                let msg = format!("value assigned to `{}` is never read", tkn.lexeme);
+                self.warn(Lint::DeadStores, &tkn, msg);
+            }
+        }
+    }
+
+    fn check_condition(&mut self, cond: &Expr) {
+        if !self.lints.constant_conditions {
+            return;
+        }
+
+        if let Some(truthy) = constant::condition(self.interpreter, cond) {
+            self.warn(Lint::ConstantConditions, cond.token(), format!("condition is always {}", truthy));
+        }
+    }
+
+    fn check_unreachable(&mut self, stmt: &Stmt) -> bool {
+        let tkn = match stmt.token() {
+            Some(tkn) => tkn,
+            None => return false,
+        };
+
+        if self.lints.unreachable {
+            self.warn(Lint::Unreachable, tkn, "unreachable statement".to_owned());
+        }
+
+        true
+    }
+
+    fn warn(&mut self, lint: Lint, near: &Token, msg: String) {
+        self.warnings.push(Warning {
+            lint,
+            severity: self.lints.severity(),
+            line: near.line,
+            msg,
+            near: near.lexeme.to_owned(),
+        });
+    }
 }
@@ -1,12 +1,12 @@
 //! A module describing Lox-specific Result and Error types
 
-use object::Object;
-
 use std::result;
 use std::error;
 use std::fmt;
 use std::io;
 
+use lint::Lint;
+
 /// A Lox-Specific Result Type
 pub type Result<T> = result::Result<T, Error>;
 
@@ -18,15 +18,222 @@ pub enum Error {
     /// Returned if there is an error reading from a file or stdin
     IO(io::Error),
     /// Returned if the scanner encounters an error
-    Lexical(u64, String, String),
-    /// Returned if the parser encounters an error
-    Parse(u64, String, String),
+    Lexical(u64, LexicalKind, String),
+    /// Returned if the parser or resolver encounters an error
+    Parse(u64, ParseKind, String),
     /// Returned if there is an error at runtime
-    Runtime(u64, String, String),
-    /// Sentinel error for break statements
-    Break(u64),
-    /// Sentinel error for return statements
-    Return(u64, Object),
+    Runtime(u64, RuntimeKind, String),
+}
+
+/// The kinds of errors encountered at runtime, allowing embedders to match on
+/// the failure rather than its message.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::{Error, RuntimeKind};
+/// # fn main() {
+/// let e = Error::Runtime(3, RuntimeKind::TypeMismatch {
+///     expected: "number".to_owned(),
+///     found: "string".to_owned(),
+/// }, "-".to_owned());
+///
+/// assert_eq!("Runtime Error [line 3] expected number, found string: near -", e.to_string());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeKind {
+    /// The named variable was accessed before being defined, with the
+    /// closest visible name, if any
+    UndefinedVariable { name: String, suggestion: Option<String> },
+    /// The named local variable was defined twice in the same scope
+    AlreadyDefined(String),
+    /// An operand or callee had the wrong type
+    TypeMismatch { expected: String, found: String },
+    /// The named function was called with the wrong number of arguments
+    ArityMismatch { callee: String, expected: usize, found: usize },
+    /// The named property is neither a field nor a method of the instance,
+    /// with the closest of those, if any
+    PropertyNotFound { name: String, suggestion: Option<String> },
+    /// A number was divided by zero
+    DivisionByZero,
+    /// Execution was stopped by an `Interrupt`
+    Interrupted,
+    /// Calls nested past the interpreter's maximum depth. The trace lists
+    /// the callees innermost first, with consecutive repeats collapsed into
+    /// a count.
+    StackOverflow(Vec<(String, usize)>),
+    /// A native function rejected its arguments
+    InvalidArgument(String),
+    /// The named module was not found at any of the candidate paths
+    ModuleNotFound { name: String, candidates: Vec<String> },
+    /// Any other failure, described by its message
+    Other(String),
+}
+
+impl fmt::Display for RuntimeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RuntimeKind::UndefinedVariable { ref name, suggestion: None } =>
+                write!(f, "variable `{}` is undefined", name),
+            RuntimeKind::UndefinedVariable { ref name, suggestion: Some(ref s) } =>
+                write!(f, "variable `{}` is undefined; did you mean `{}`?", name, s),
+            RuntimeKind::AlreadyDefined(ref name) =>
+                write!(f, "variable `{}` already defined", name),
+            RuntimeKind::TypeMismatch { ref expected, ref found } =>
+                write!(f, "expected {}, found {}", expected, found),
+            RuntimeKind::ArityMismatch { ref callee, expected, found } =>
+                write!(f, "expected {} arguments to '{}' but got {}", expected, callee, found),
+            RuntimeKind::PropertyNotFound { ref name, suggestion: None } =>
+                write!(f, "undefined property `{}`", name),
+            RuntimeKind::PropertyNotFound { ref name, suggestion: Some(ref s) } =>
+                write!(f, "undefined property `{}`; did you mean `{}`?", name, s),
+            RuntimeKind::DivisionByZero => write!(f, "divide by zero"),
+            RuntimeKind::Interrupted => write!(f, "interrupted"),
+            RuntimeKind::StackOverflow(ref trace) => {
+                let frames: Vec<String> = trace.iter()
+                    .map(|&(ref name, n)| match n {
+                        1 => format!("'{}'", name),
+                        n => format!("'{}' x{}", name, n),
+                    })
+                    .collect();
+                write!(f, "stack overflow in {}", frames.join(" < "))
+            }
+            RuntimeKind::ModuleNotFound { ref name, ref candidates } if candidates.is_empty() =>
+                write!(f, "module '{}' not found, as no import paths are configured", name),
+            RuntimeKind::ModuleNotFound { ref name, ref candidates } =>
+                write!(f, "module '{}' not found, tried {}", name, candidates.join(", ")),
+            RuntimeKind::InvalidArgument(ref msg) | RuntimeKind::Other(ref msg) =>
+                write!(f, "{}", msg),
+        }
+    }
+}
+
+/// The kinds of errors encountered while scanning source.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexicalKind {
+    /// A character outside of Lox's syntax
+    UnexpectedCharacter,
+    /// A string literal was not closed before the end of the source
+    UnterminatedString,
+    /// A numeric literal could not be read as a number
+    InvalidNumber,
+    /// The source is not UTF-8, with the offset of the first invalid byte
+    InvalidUtf8(usize),
+}
+
+impl fmt::Display for LexicalKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LexicalKind::UnexpectedCharacter => write!(f, "unexpected character"),
+            LexicalKind::UnterminatedString => write!(f, "unterminated string"),
+            LexicalKind::InvalidNumber => write!(f, "invalid numeric"),
+            LexicalKind::InvalidUtf8(at) => write!(f, "invalid UTF-8 at byte {}", at),
+        }
+    }
+}
+
+/// The kinds of errors encountered before running, while parsing and
+/// resolving source, allowing embedders to match on the failure rather than
+/// its message.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::{Error, ParseKind};
+/// # fn main() {
+/// let e = Error::Parse(2, ParseKind::Expected {
+///     expected: "';' after value".to_owned(),
+///     found: "EOF".to_owned(),
+/// }, "EOF".to_owned());
+///
+/// assert_eq!("Parse Error [line 2] expected ';' after value, found EOF: near EOF", e.to_string());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseKind {
+    /// The described token was expected, but another was found
+    Expected { expected: String, found: String },
+    /// The token found cannot begin an expression
+    ExpectedExpression(String),
+    /// The token cannot appear where it was found
+    UnexpectedToken,
+    /// Only variables and properties may be assigned to
+    InvalidAssignment,
+    /// A declaration or call exceeded the maximum number of arguments
+    TooManyArguments(usize),
+    /// The described construct was nested past the parser's maximum depth
+    TooDeeplyNested(String),
+    /// A declaration was the body of the described control flow statement
+    UnenclosedDeclaration(String),
+    /// A local variable was read in its own initializer
+    OwnInitializer,
+    /// A `return` statement appeared outside of a function
+    TopLevelReturn,
+    /// A value was returned from an initializer
+    InitializerReturn,
+    /// `this` was used outside of a method
+    ThisOutsideClass,
+    /// `super` was used outside of a method
+    SuperOutsideClass,
+    /// `super` was used in a class without a superclass
+    SuperWithoutSuperclass,
+    /// A class named itself as its superclass
+    InheritsFromItself,
+    /// A class inherited from a value that is not a class
+    InvalidSuperclass,
+    /// A local variable was declared twice in the same scope
+    AlreadyDeclared,
+    /// A local function was called with the wrong number of arguments
+    ArityMismatch { expected: usize, found: usize },
+    /// The described declaration appeared outside of top-level code
+    NotTopLevel(String),
+    /// A warning of the lint, denied by the configured lints
+    Denied(Lint, String),
+    /// Any other failure, described by its message
+    Other(String),
+}
+
+impl fmt::Display for ParseKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseKind::Expected { ref expected, ref found } =>
+                write!(f, "expected {}, found {}", expected, found),
+            ParseKind::ExpectedExpression(ref found) =>
+                write!(f, "expected expression, found {}", found),
+            ParseKind::UnexpectedToken => write!(f, "unexpected token"),
+            ParseKind::InvalidAssignment => write!(f, "invalid assignment target"),
+            ParseKind::TooManyArguments(max) => write!(f, "cannot have more than {} arguments", max),
+            ParseKind::TooDeeplyNested(ref what) => write!(f, "{} too deeply nested", what),
+            ParseKind::UnenclosedDeclaration(ref context) =>
+                write!(f, "declarations in {} bodies must be enclosed in a block", context),
+            ParseKind::OwnInitializer => write!(f, "cannot read local variable in its own initializer."),
+            ParseKind::TopLevelReturn => write!(f, "cannot return from top-level code"),
+            ParseKind::InitializerReturn => write!(f, "cannot return a value from an initializer"),
+            ParseKind::ThisOutsideClass => write!(f, "cannot use 'this' outside of a class"),
+            ParseKind::SuperOutsideClass => write!(f, "cannot use 'super' outside of a class"),
+            ParseKind::SuperWithoutSuperclass => write!(f, "cannot use 'super' in a class with no superclass"),
+            ParseKind::InheritsFromItself => write!(f, "a class cannot inherit from itself"),
+            ParseKind::InvalidSuperclass => write!(f, "superclass must be a class"),
+            ParseKind::AlreadyDeclared => write!(f, "variable already defined with that name in this scope"),
+            ParseKind::ArityMismatch { expected, found } =>
+                write!(f, "expected {} arguments but got {}", expected, found),
+            ParseKind::NotTopLevel(ref what) => write!(f, "{} must be at the top level", what),
+            ParseKind::Denied(_, ref msg) | ParseKind::Other(ref msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error {
+    /// The line the error was raised on, if it came from source code
+    pub fn line(&self) -> Option<u64> {
+        match *self {
+            Error::Usage | Error::IO(_) => None,
+            Error::Lexical(line, _, _) | Error::Parse(line, _, _) | Error::Runtime(line, _, _) => Some(line),
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -38,18 +245,34 @@ impl From<io::Error> for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::Usage => write!(f, "Usage: rlox [script]"),
+            Error::Usage => write!(f, concat!(
+                "Usage: rlox",
+                " [--no-std]",
+                " [--warn]",
+                " [--deny-warnings]",
+                " [--allow=lint]...",
+                " [--stream]",
+                " [--fold]",
+                " [--timings[=table|json]]",
+                " [--report]",
+                " [--monkey-patching]",
+                " [--error-format=human|json]",
+                " [--compat=lox|rlox]",
+                " [-I dir]...",
+                " [script]\n",
+                "       rlox [--record=session.lox] [--replay=session.lox]\n",
+                "       rlox highlight [--format=ansi|html|json] script\n",
+                "       rlox check [--warn] [--deny-warnings] [--allow=lint]... script\n",
+                "       rlox run [dir]\n",
+                "       rlox explain [code]\n",
+                "       rlox doc script")),
             Error::IO(ref e) => e.fmt(f),
-            Error::Lexical(ref line, ref msg, ref whence) =>
-                write!(f, "Lexical Error [line {}] {}: {:?}", line, msg, whence),
-            Error::Parse(ref line, ref msg, ref near) =>
-                write!(f, "Parse Error [line {}] {}: near {}", line, msg, &near),
-            Error::Runtime(ref line, ref msg, ref near) =>
-                write!(f, "Runtime Error [line {}] {}: near {}", line, msg, &near),
-            Error::Break(ref line) =>
-                write!(f, "Runtime Error [line {}] unexpected break statement", line),
-            Error::Return(ref line, _) =>
-                write!(f, "Runtime Error [line {}] unexpected return statement", line),
+            Error::Lexical(ref line, ref kind, ref whence) =>
+                write!(f, "Lexical Error [line {}] {}: {:?}", line, kind, whence),
+            Error::Parse(ref line, ref kind, ref near) =>
+                write!(f, "Parse Error [line {}] {}: near {}", line, kind, &near),
+            Error::Runtime(ref line, ref kind, ref near) =>
+                write!(f, "Runtime Error [line {}] {}: near {}", line, kind, &near),
         }
     }
 }
@@ -62,8 +285,6 @@ impl error::Error for Error {
             Error::Lexical(_, _, _) => "lexical error",
             Error::Parse(_, _, _) => "parse error",
             Error::Runtime(_, _, _) => "runtime error",
-            Error::Break(_) => "break error",
-            Error::Return(_, _) => "return error",
         }
     }
 
@@ -1,23 +1,121 @@
-use std::fs::File;
 use std::io::prelude::*;
 use std::io;
-use std::path::Path;
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::cell::RefCell;
 
-use interpreter::Interpreter;
+use interpreter::{Interpreter, InterpreterOptions};
+use object::Object;
+use ast::expr::Expr;
+use ast::stmt::Stmt;
+use ast::token::{Literal, Token};
+use env::Env;
 use output::{Writer, Reader};
-use parser::StmtIterator;
+use parser::{self, Parser, StmtIterator};
 use resolver::Resolver;
-use result::Result;
+use result::{Result, Error, ParseKind, RuntimeKind};
 use scanner::TokenIterator;
-use debug::time;
+use highlight::{highlight, Format};
+use lint::{Lint, Lints, Severity, Warning};
+use diagnostic::{self, Diagnostic};
+use manifest::Manifest;
+use host::Clock;
+use interrupt::Interrupt;
+use trace::{Output, Tracer};
+use stats;
+use compat::Compat;
+use constant;
+use source;
+use doc;
+
+/// The Lox standard library, loaded in order before any user code is run
+const STDLIB: [(&str, &str); 3] = [
+    ("list", include_str!("../stdlib/list.lox")),
+    ("map", include_str!("../stdlib/map.lox")),
+    ("string", include_str!("../stdlib/string.lox")),
+];
 
 pub struct Runner {
     stdout: Rc<RefCell<Writer>>,
     stderr: Rc<RefCell<Writer>>,
+    /// The options of subsequently created interpreters
+    options: InterpreterOptions,
+    std: bool,
+    /// The number of sources run, used to keep tokens from separate sources
+    /// distinct when resolving
+    sources: u64,
+    lints: Lints,
+    allowed: Vec<Lint>,
+    prune: bool,
+    fold: bool,
+    stream: bool,
+    max_depth: usize,
+    interrupt: Interrupt,
+    tracer: Rc<Tracer>,
+    timings: Option<Output>,
+    report: bool,
+    record: Option<Rc<RefCell<Writer>>>,
+    search_path: Vec<PathBuf>,
+    /// The directory of the script being run, searched first for imports
+    base: PathBuf,
+    /// The directory of the module being run, against which relative
+    /// imports are resolved
+    dir: PathBuf,
+    /// The modules already imported, which are not run again
+    modules: Vec<Module>,
+    /// The names declared at the top level of the module being run
+    declared: Declared,
+    /// The file being run, named in diagnostics
+    path: Option<PathBuf>,
+    error_format: diagnostic::Format,
+}
+
+/// A module run by an import
+struct Module {
+    path: PathBuf,
+    /// The module's globals, once it has finished running
+    env: Option<Rc<Env>>,
+    exports: Vec<String>,
+}
+
+#[derive(Default)]
+struct Declared {
+    all: Vec<String>,
+    exported: Vec<String>,
 }
 
+impl Declared {
+    fn push(&mut self, id: &Token, exported: bool) {
+        if !self.all.contains(&id.lexeme) {
+            self.all.push(id.lexeme.to_owned());
+        }
+
+        if exported && !self.exported.contains(&id.lexeme) {
+            self.exported.push(id.lexeme.to_owned());
+        }
+    }
+
+    /// The names exported by the module. Modules without any `export`
+    /// declarations export everything they declare.
+    fn exports(self) -> Vec<String> {
+        if self.exported.is_empty() { self.all } else { self.exported }
+    }
+}
+
+/// Begins a REPL line printing a detailed view of an expression's value
+const INSPECT_COMMAND: &str = ":inspect ";
+
+/// Begins a REPL line printing the documentation of an expression's value
+const HELP_COMMAND: &str = ":help ";
+
+/// Prefixes the output of a line in a recorded REPL session
+pub const OUTPUT_PREFIX: &str = "//>";
+
+/// Prefixes the errors of a line in a recorded REPL session
+pub const ERROR_PREFIX: &str = "//!";
+
 impl Default for Runner {
     fn default() -> Self {
         Runner::new(
@@ -32,62 +130,946 @@ impl Runner {
         Runner {
             stdout,
             stderr,
+            options: InterpreterOptions::default(),
+            std: true,
+            sources: 0,
+            lints: Lints::default(),
+            allowed: Vec::new(),
+            prune: false,
+            fold: false,
+            stream: false,
+            max_depth: parser::MAX_DEPTH,
+            interrupt: Interrupt::default(),
+            tracer: Rc::new(Tracer::new(false)),
+            timings: None,
+            report: false,
+            record: None,
+            search_path: Vec::new(),
+            base: PathBuf::new(),
+            dir: PathBuf::new(),
+            modules: Vec::new(),
+            declared: Declared::default(),
+            path: None,
+            error_format: diagnostic::Format::default(),
+        }
+    }
+
+    /// Configures subsequently created interpreters. Whether they run at
+    /// the REPL is decided by the Runner, overriding `repl`.
+    pub fn set_options(&mut self, options: InterpreterOptions) {
+        self.options = options;
+    }
+
+    pub fn options(&self) -> &InterpreterOptions {
+        &self.options
+    }
+
+    /// Toggles sandboxing of subsequently created interpreters, omitting
+    /// natives that block or read the wall clock.
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.options.sandbox = sandbox;
+    }
+
+    /// Toggles the `defineMethod` native in subsequently created
+    /// interpreters, allowing methods to be added to or replaced on existing
+    /// classes.
+    pub fn set_monkey_patching(&mut self, monkey_patching: bool) {
+        self.options.monkey_patching = monkey_patching;
+    }
+
+    /// Toggles whether the standard library is preloaded into subsequently
+    /// created interpreters.
+    pub fn set_std(&mut self, std: bool) {
+        self.std = std;
+    }
+
+    /// Configures the warnings reported while resolving user code.
+    pub fn set_lints(&mut self, lints: Lints) {
+        self.lints = lints;
+    }
+
+    /// Disables the lint, keeping it disabled even when a project manifest
+    /// enables warnings.
+    pub fn allow(&mut self, lint: Lint) {
+        self.lints.set(lint, false);
+        self.allowed.push(lint);
+    }
+
+    /// Toggles removal of branches and loops whose constant conditions
+    /// prevent them from ever running, prior to resolving.
+    pub fn set_prune(&mut self, prune: bool) {
+        self.prune = prune;
+    }
+
+    /// Toggles replacement of constant expressions with the literals they
+    /// evaluate to, prior to running.
+    pub fn set_fold(&mut self, fold: bool) {
+        self.fold = fold;
+    }
+
+    /// Toggles resolving and running each statement as soon as it is
+    /// parsed, rather than parsing the whole program first. This bounds the
+    /// memory used by very long, generated scripts.
+    pub fn set_stream(&mut self, stream: bool) {
+        self.stream = stream;
+    }
+
+    /// Configures the maximum nesting of expressions and statements in
+    /// subsequently parsed sources.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Configures the format errors and warnings are written to stderr in.
+    pub fn set_error_format(&mut self, fmt: diagnostic::Format) {
+        self.error_format = fmt;
+    }
+
+    pub fn error_format(&self) -> diagnostic::Format {
+        self.error_format
+    }
+
+    /// Configures the maximum depth of nested calls in subsequently created
+    /// interpreters.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.options.max_call_depth = max_call_depth;
+    }
+
+    /// Configures the clock observed by natives in subsequently created
+    /// interpreters.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.options.clock = clock;
+    }
+
+    /// Fixes the seed of `random()` in subsequently created interpreters.
+    /// Without a seed, one is derived from the wall clock.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.options.seed = Some(seed);
+    }
+
+    /// Toggles whether dividing by zero is a runtime error in subsequently
+    /// created interpreters, rather than producing an infinity or NaN.
+    pub fn set_strict_division(&mut self, strict: bool) {
+        self.options.strict_division = strict;
+    }
+
+    /// Selects the language semantics of subsequently created interpreters,
+    /// and whether the REPL terminates lines missing a semicolon.
+    pub fn set_compat(&mut self, compat: Compat) {
+        self.options.compat = compat;
+    }
+
+    /// Returns a handle that stops code run by this Runner once triggered.
+    /// The REPL resets it before each line, so an interrupted line does not
+    /// end the session.
+    pub fn interrupt(&self) -> Interrupt {
+        self.interrupt.clone()
+    }
+
+    /// Toggles recording the time spent scanning and parsing, resolving, and
+    /// interpreting, summarized in the provided format by `write_timings`.
+    pub fn set_timings(&mut self, timings: Option<Output>) {
+        self.tracer = Rc::new(Tracer::new(timings.is_some()));
+        self.timings = timings;
+    }
+
+    /// Toggles recording the time spent in each phase along with counts of
+    /// the statements executed and the envs and objects created, summarized
+    /// by `write_report`. Counts are kept per thread, so are reset here.
+    pub fn set_report(&mut self, report: bool) {
+        if report && !self.tracer.is_enabled() {
+            self.tracer = Rc::new(Tracer::new(true));
+        }
+        stats::reset();
+        stats::enable(report);
+        self.report = report;
+    }
+
+    /// Writes the phase timings and counts to stderr, if enabled via
+    /// `set_report`.
+    pub fn write_report(&self) -> Result<()> {
+        if !self.report {
+            return Ok(());
+        }
+
+        let report = format!("{}\n{}", self.tracer.summary(Output::Table), stats::snapshot().summary());
+        Writer::write(&self.stderr, &report)?;
+        Writer::flush(&self.stderr)
+    }
+
+    /// Writes the timing summary to stderr, if enabled via `set_timings`.
+    pub fn write_timings(&self) -> Result<()> {
+        match self.timings {
+            Some(out) => {
+                Writer::write(&self.stderr, &self.tracer.summary(out))?;
+                Writer::flush(&self.stderr)
+            }
+            None => Ok(()),
         }
     }
 
+    /// Appends the directory to those searched for imported modules. The
+    /// directory of the script being run is always searched first, followed
+    /// by these in the order they were added.
+    pub fn add_search_path(&mut self, dir: PathBuf) {
+        self.search_path.push(dir);
+    }
+
     pub fn file(&mut self, f: &Path) -> Result<()> {
-        let mut src = String::new();
+        self.path = Some(f.to_path_buf());
+        let src = {
+            let _span = self.tracer.span("read");
+            self.read(f)?
+        };
 
-        time("read file", ||
-            File::open(f).and_then(|mut h| h.read_to_string(&mut src)))?;
+        self.base = f.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+        self.dir = self.base.clone();
+        self.modules.clear();
 
-        let stdout = Rc::clone(&self.stdout);
-        let mut i = Interpreter::new(false, stdout);
+        let mut i = self.interpreter(false);
+        self.prelude(&mut i)?;
+
+        let _span = self.tracer.span("script");
+        self.run(&mut i, &src)
+    }
+
+    /// Runs the project in the directory, configured by its `lox.toml`
+    /// manifest. Errors in the manifest are reported to stderr.
+    pub fn project(&mut self, dir: &Path) -> Result<()> {
+        let m = match Manifest::load(dir) {
+            Ok(m) => m,
+            Err(e @ Error::IO(_)) => return Err(e),
+            Err(e) => {
+                self.write_error(&e)?;
+                Writer::flush(&self.stderr)?;
+                return Err(e);
+            }
+        };
+
+        self.set_std(m.std);
+        self.set_sandbox(m.sandbox);
+        self.search_path.extend(m.path);
+        if m.warn {
+            let mut lints = Lints { deny: self.lints.deny, ..Lints::all() };
+            for &lint in &self.allowed {
+                lints.set(lint, false);
+            }
+            self.set_lints(lints);
+        }
+
+        self.file(&m.entry)
+    }
+
+    /// Statically checks the file without executing it, reporting every
+    /// parse and resolve error, along with any enabled lints, to stderr in
+    /// line order, followed by a count of each. The first error encountered,
+    /// including denied warnings, is returned.
+    pub fn check(&mut self, f: &Path) -> Result<()> {
+        self.path = Some(f.to_path_buf());
+        let src = self.read(f)?;
+
+        // the interpreter only collects resolved locals; nothing is run
+        let opts = InterpreterOptions { sandbox: true, ..self.options };
+        let mut i = Interpreter::new(opts, Rc::clone(&self.stdout), Rc::clone(&self.stderr));
+
+        let mut first = None;
+        let mut diags: Vec<(u64, Severity, String)> = Vec::new();
+        for res in self.parser(&src) {
+            let mut warnings = Vec::new();
+            let res = res.and_then(|stmt| Resolver::resolve_with(&mut i, &stmt, self.lints, &mut warnings));
+
+            for w in warnings {
+                diags.push((w.line, w.severity, self.render_warning(&w)));
+                if w.severity == Severity::Error {
+                    first = first.or(Some(Error::Parse(w.line, ParseKind::Denied(w.lint, w.msg), w.near)));
+                }
+            }
+
+            if let Err(e) = res {
+                diags.push((e.line().unwrap_or_default(), Severity::Error, self.render_error(&e)));
+                first = first.or(Some(e));
+            }
+        }
+
+        // sorting is stable, so diagnostics on the same line keep their order
+        diags.sort_by_key(|d| d.0);
+        for (_, _, msg) in &diags {
+            Writer::writeln(&self.stderr, msg)?;
+        }
+
+        // the summary is not itself a diagnostic, so is left out of JSON
+        if !diags.is_empty() && self.error_format == diagnostic::Format::Human {
+            let errors = diags.iter().filter(|d| d.1 == Severity::Error).count();
+            Writer::writeln(&self.stderr, &summary(diags.len() - errors, errors))?;
+        }
+
+        Writer::flush(&self.stderr)?;
+        first.map_or(Ok(()), Err)
+    }
+
+    /// Writes a Markdown reference of the file's functions and classes,
+    /// built from their doc comments, to stdout. Parse errors are reported
+    /// to stderr, and the first is returned.
+    pub fn doc(&mut self, f: &Path) -> Result<()> {
+        self.path = Some(f.to_path_buf());
+        let src = self.read(f)?;
+
+        let mut stmts = Vec::new();
+        let mut first = None;
+        for res in self.parser(&src) {
+            match res {
+                Ok(stmt) => stmts.push(stmt),
+                Err(e) => {
+                    self.write_error(&e)?;
+                    first = first.or(Some(e));
+                }
+            }
+        }
+
+        if let Some(e) = first {
+            Writer::flush(&self.stderr)?;
+            return Err(e);
+        }
+
+        let title = f.file_stem().map_or_else(|| f.display().to_string(), |s| s.to_string_lossy().into_owned());
+        Writer::write(&self.stdout, &doc::markdown(&title, &stmts))?;
+        Writer::flush(&self.stdout)
+    }
+
+    /// Writes the file's source to stdout, syntax highlighted in the
+    /// provided format.
+    pub fn highlight(&mut self, f: &Path, fmt: Format) -> Result<()> {
+        let src = self.read(f)?;
+
+        match highlight(&src, fmt) {
+            Ok(ref out) if out.ends_with('\n') => {
+                Writer::write(&self.stdout, out)?;
+                Writer::flush(&self.stdout)
+            }
+            Ok(out) => {
+                Writer::writeln(&self.stdout, &out)?;
+                Writer::flush(&self.stdout)
+            }
+            Err(e) => {
+                self.write_error(&e)?;
+                Writer::flush(&self.stderr)?;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn prompt(&mut self, stdin: Reader) -> Result<()> {
+        self.interact(stdin, false)
+    }
+
+    /// Runs the REPL over the lines of a recorded session, skipping their
+    /// recorded output. Each line is shown after the prompt, as if typed.
+    pub fn replay(&mut self, session: &Path) -> Result<()> {
+        let input: String = self.read(session)?
+            .lines()
+            .filter(|l| !l.starts_with(OUTPUT_PREFIX) && !l.starts_with(ERROR_PREFIX))
+            .map(|l| format!("{}\n", l))
+            .collect();
 
-        time("total run", || { self.run(&mut i, &src) })
+        self.interact(Reader::Cursor(io::Cursor::new(input.into_bytes())), true)
     }
 
-    pub fn prompt(&mut self, mut stdin: Reader) -> Result<()> {
+    /// Records the lines entered at the REPL to the writer as a Lox script,
+    /// each followed by its output and errors as comments prefixed with
+    /// `OUTPUT_PREFIX` and `ERROR_PREFIX`. The session may be replayed with
+    /// `replay`, or run as a script.
+    pub fn set_record(&mut self, record: Option<Rc<RefCell<Writer>>>) {
+        self.record = record;
+    }
+
+    fn interact(&mut self, stdin: Reader, echo: bool) -> Result<()> {
+        let (stdout, stderr) = (Rc::clone(&self.stdout), Rc::clone(&self.stderr));
+        self.path = None;
+        self.base = PathBuf::new();
+        self.dir = PathBuf::new();
+        self.modules.clear();
+
+        // while recording, the output of each line is copied into the session
+        if self.record.is_some() {
+            self.stdout = Writer::tee(&stdout);
+            self.stderr = Writer::tee(&stderr);
+        }
+
+        let res = self.session(stdin, &stdout, echo);
+
+        self.stdout = stdout;
+        self.stderr = stderr;
+        res
+    }
+
+    /// Runs the REPL, writing the prompt to the terminal so that it is never
+    /// recorded. If echoing, lines are written after the prompt as read.
+    fn session(&mut self, mut stdin: Reader, term: &Rc<RefCell<Writer>>, echo: bool) -> Result<()> {
         let mut src = String::new();
-        let stdout = Rc::clone(&self.stdout);
-        let mut i = Interpreter::new(true, stdout);
+        let mut i = self.interpreter(true);
+        self.prelude(&mut i)?;
 
-        Writer::writeln(&self.stdout, "RLOX : Press ctrl+c to exit")?;
+        let mut results = 0;
+
+        Writer::writeln(term, "RLOX : Press ctrl+d to exit")?;
         loop {
-            Writer::write(&self.stdout, "> ")?;
-            Writer::flush(&self.stdout)?;
-            stdin.read_line(&mut src)?;
-
-            if let Some(c) = src.pop() {
-                if c == ';' {
-                    src.push(c);
-                } else {
-                    src.push(c);
-                    src.push(';');
+            Writer::write(term, "> ")?;
+            Writer::flush(term)?;
+            let start = src.len();
+            if stdin.read_line(&mut src)? == 0 {
+                // end the prompt's line before handing back the terminal
+                Writer::writeln(term, "")?;
+                Writer::flush(term)?;
+                return Writer::flush(&self.stderr);
+            }
+
+            if echo {
+                Writer::write(term, &src[start..])?;
+            }
+
+            // doc comments are held until the declaration they document
+            if src.lines().all(|l| l.trim_start().starts_with("///")) {
+                continue;
+            }
+
+            // interrupts received while waiting for input are discarded
+            self.interrupt.reset();
+
+            let line = self.recorded_line(&src);
+
+            if let Some(expr) = src.trim().strip_prefix(INSPECT_COMMAND) {
+                let _ = self.inspect(&mut i, expr, false);
+                self.record_line(&line)?;
+                src.clear();
+                continue;
+            }
+
+            if let Some(expr) = src.trim().strip_prefix(HELP_COMMAND) {
+                let _ = self.inspect(&mut i, expr, true);
+                self.record_line(&line)?;
+                src.clear();
+                continue;
+            }
+
+            // lines holding a single expression have their values retained
+            if let Ok(expr) = self.parse_expression(src.trim().trim_end_matches(';')) {
+                if self.echo(&mut i, &expr, results + 1).is_ok() {
+                    results += 1;
                 }
+
+                self.record_line(&line)?;
+                src.clear();
+                continue;
             }
 
-            if let Err(e) = time("line run", || self.run(&mut i, &src)) {
-                Writer::writeln(&self.stderr, &format!("{}", e))?;
-                Writer::flush(&self.stderr)?;
+            // the reference REPL runs lines as written
+            if self.options.compat == Compat::Rlox {
+                if let Some(c) = src.pop() {
+                    if c == ';' {
+                        src.push(c);
+                    } else {
+                        src.push(c);
+                        src.push(';');
+                    }
+                }
             }
 
+            // errors have already been reported to stderr by run
+            let span = self.tracer.span("line");
+            let _ = self.run(&mut i, &src);
+            drop(span);
+
+            self.record_line(&line)?;
             src.clear();
         }
     }
 
+    /// Evaluates the expression, printing a detailed view of its value, or
+    /// its documentation if `help` is set.
+    fn inspect(&mut self, i: &mut Interpreter, expr: &str, help: bool) -> Result<()> {
+        let out = self.eval_expression(i, expr.trim()).and_then(|obj| {
+            let p = i.printer();
+            Writer::writeln(&self.stdout, &if help { p.help(&obj) } else { p.inspect(&obj) })
+        });
+
+        Writer::flush(&self.stdout)?;
+
+        if let Err(ref e) = out {
+            self.write_error(e)?;
+            Writer::flush(&self.stderr)?;
+        }
+
+        out
+    }
+
+    /// Returns the line as it is recorded, terminated so that the session
+    /// may be run as a script.
+    fn recorded_line(&self, src: &str) -> String {
+        let mut line = src.trim().to_owned();
+
+        if line.is_empty() || line.starts_with(INSPECT_COMMAND) || line.starts_with(HELP_COMMAND) {
+            return line;
+        }
+
+        if !line.ends_with([';', '}']) && self.options.compat == Compat::Rlox {
+            line.push(';');
+        }
+
+        line
+    }
+
+    /// Appends the line to the session being recorded, followed by the
+    /// output and errors it produced.
+    fn record_line(&self, line: &str) -> Result<()> {
+        let record = match self.record {
+            Some(ref r) => r,
+            None => return Ok(()),
+        };
+
+        let mut out = format!("{}\n", line);
+        for &(w, prefix) in &[(&self.stdout, OUTPUT_PREFIX), (&self.stderr, ERROR_PREFIX)] {
+            for l in Writer::take_copy(w).lines() {
+                out.push_str(&format!("{} {}\n", prefix, l));
+            }
+        }
+
+        // flushed per line, so the session survives the REPL exiting abruptly
+        Writer::write(record, &out)?;
+        Writer::flush(record)
+    }
+
+    /// Reads the source file, reporting any decoding error to stderr.
+    fn read(&self, f: &Path) -> Result<String> {
+        match source::read(f) {
+            Err(e @ Error::IO(_)) => Err(e),
+            Err(e) => {
+                self.write_error(&e)?;
+                Writer::flush(&self.stderr)?;
+                Err(e)
+            }
+            ok => ok,
+        }
+    }
+
+    fn interpreter(&self, repl: bool) -> Interpreter {
+        let stdout = Rc::clone(&self.stdout);
+        let stderr = Rc::clone(&self.stderr);
+        let options = InterpreterOptions { repl, ..self.options };
+
+        let mut i = Interpreter::new(options, stdout, stderr);
+        i.set_interrupt(self.interrupt.clone());
+        i
+    }
+
+    /// Loads the standard library into the interpreter's global env, unless
+    /// disabled via `set_std`.
+    pub fn prelude(&mut self, i: &mut Interpreter) -> Result<()> {
+        if !self.std {
+            return Ok(());
+        }
+
+        // the standard library is not linted, is parsed with the default
+        // maximum depth, and is written for rlox's semantics, which its
+        // functions keep when called by lox scripts
+        let lints = self.lints;
+        self.lints = Lints::default();
+        let max_depth = mem::replace(&mut self.max_depth, parser::MAX_DEPTH);
+        let compat = i.options().compat;
+        i.set_compat(Compat::Rlox);
+
+        let tracer = Rc::clone(&self.tracer);
+        let _span = tracer.span("prelude");
+        let res = STDLIB.iter().try_for_each(|&(name, src)| {
+            let _span = tracer.span(name);
+            self.run(i, src)
+        });
+
+        self.lints = lints;
+        self.max_depth = max_depth;
+        i.set_compat(compat);
+        res
+    }
+
+    /// Evaluates the source as a single expression against the interpreter,
+    /// returning its value. Unlike `run`, errors are returned without being
+    /// reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate rlox;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::interpreter::{Interpreter, InterpreterOptions};
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::Writer;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::run::Runner;
+    /// # #[cfg(not(feature = "internals"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "internals")]
+    /// # fn main() {
+    /// # use std::cell::RefCell;
+    /// # use std::io::Cursor;
+    /// # use std::rc::Rc;
+    /// let out = Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    /// let mut r = Runner::new(Rc::clone(&out), Rc::clone(&out));
+    /// let mut i = Interpreter::new(InterpreterOptions::default(), Rc::clone(&out), out);
+    ///
+    /// r.run(&mut i, "var x = 20;").unwrap();
+    /// let obj = r.eval_expression(&mut i, "x * 2 + 2").unwrap();
+    /// assert_eq!("42", format!("{}", obj));
+    ///
+    /// assert!(r.eval_expression(&mut i, "x = ").is_err());
+    /// # }
+    /// ```
+    pub fn eval_expression(&mut self, i: &mut Interpreter, src: &str) -> Result<Object> {
+        let expr = self.parse_expression(src)?;
+        Resolver::resolve_expr(i, &expr)?.eval_expr(&expr)
+    }
+
+    fn parse_expression(&mut self, src: &str) -> Result<Expr> {
+        self.parser(src).parse_expression()
+    }
+
+    /// Creates a parser for a new source, whose tokens are kept distinct
+    /// from those of any previous sources.
+    fn parser<'s>(&mut self, src: &'s str) -> Parser<'s> {
+        self.sources += 1;
+
+        let mut tokens = src.chars().tokens();
+        tokens.set_source(self.sources);
+
+        let mut p = tokens.statements();
+        p.set_max_depth(self.max_depth);
+        p
+    }
+
+    /// Evaluates an expression entered at the prompt, printing its value and
+    /// binding it to the globals `_` and `_n`.
+    fn echo(&mut self, i: &mut Interpreter, expr: &Expr, n: usize) -> Result<()> {
+        let res = {
+            let _span = self.tracer.span("line");
+            Resolver::resolve_expr(i, expr).and_then(|i| i.eval_expr(expr))
+        };
+
+        let out = res.and_then(|obj| {
+            Writer::writeln(&self.stdout, &i.printer().print(&obj))?;
+            i.define_global(&format!("_{}", n), obj.clone())?;
+            i.define_global("_", obj)
+        });
+
+        Writer::flush(&self.stdout)?;
+
+        if let Err(ref e) = out {
+            self.write_error(e)?;
+            Writer::flush(&self.stderr)?;
+        }
+
+        out
+    }
+
+    /// Runs the source against the interpreter, reporting any errors to
+    /// stderr. Parse errors are skipped over, while the first resolve or
+    /// runtime error halts the run and is returned.
+    ///
+    /// The whole program is parsed and resolved before any of it runs,
+    /// unless streaming is enabled via `set_stream`.
     pub fn run(&mut self, i: &mut Interpreter, src: &str) -> Result<()> {
-        for res in src.chars().tokens().statements() {
+        let mut program = Vec::new();
+
+        // streamed statements are parsed while running, so parsing is only
+        // timed on its own when the whole program is parsed first
+        let span = self.tracer.span(if self.stream { "stream" } else { "parse" });
+        for res in self.parser(src) {
             match res {
-                Err(e) => Writer::write(&self.stderr, &format!("{}", e))?,
-                Ok(stmt) => {
-                    let i = time("resolve", || Resolver::resolve(i, &stmt))?;
-                    time("interpret", || stmt.accept(i))?
+                Err(e) => {
+                    self.write_error(&e)?;
+                    Writer::flush(&self.stderr)?;
                 }
+                Ok(stmt) if self.stream => self.execute(i, vec![stmt])?,
+                Ok(stmt) => program.push(stmt),
+            }
+        }
+        drop(span);
+
+        self.execute(i, program)
+    }
+
+    /// Resolves then runs the statements, reporting any warnings and the
+    /// first error to stderr. Warnings are reported in line order along
+    /// with any resolve error, followed by a count of each when a whole
+    /// program is run.
+    fn execute(&mut self, i: &mut Interpreter, stmts: Vec<Stmt>) -> Result<()> {
+        let mut warnings = Vec::new();
+        let (lints, prune, fold) = (self.lints, self.prune, self.fold);
+        let whole = !self.stream && !i.options().repl;
+        let res = {
+            let _span = self.tracer.span("resolve");
+            Resolver::resolve_all(i, &stmts, lints, &mut warnings)
+        };
+
+        let reported = !warnings.is_empty();
+        if reported {
+            let mut diags: Vec<(u64, Severity, String)> = warnings.iter()
+                .map(|w| (w.line, w.severity, self.render_warning(w)))
+                .collect();
+            if let Err(ref e) = res {
+                diags.push((e.line().unwrap_or_default(), Severity::Error, self.render_error(e)));
+            }
+
+            // sorting is stable, so diagnostics on the same line keep their order
+            diags.sort_by_key(|d| d.0);
+            for (_, _, msg) in &diags {
+                Writer::writeln(&self.stderr, msg)?;
+            }
+
+            if whole && self.error_format == diagnostic::Format::Human {
+                let errors = diags.iter().filter(|d| d.1 == Severity::Error).count();
+                Writer::writeln(&self.stderr, &summary(diags.len() - errors, errors))?;
             }
-            Writer::flush(&self.stdout)?;
             Writer::flush(&self.stderr)?;
         }
+
+        // denied warnings were reported above, so are returned unreported
+        if let Some(w) = warnings.into_iter().find(|w| w.severity == Severity::Error) {
+            return Err(Error::Parse(w.line, ParseKind::Denied(w.lint, w.msg), w.near));
+        }
+
+        let i = match res {
+            Ok(i) => i,
+            Err(e) if reported => return Err(e),
+            Err(e) => return self.report(e),
+        };
+
+        for stmt in stmts {
+            // modules report their own errors as they run
+            if let Stmt::Import(ref tkn, ref path) = stmt {
+                self.import(i, tkn, path)?;
+                continue;
+            }
+
+            match stmt {
+                Stmt::Export(_, ref decl) => if let Some(id) = decl.token() {
+                    self.declared.push(id, true);
+                },
+                Stmt::Declaration(ref id, _) | Stmt::Function(ref id, _, _, _) | Stmt::Class(ref id, _, _, _) =>
+                    self.declared.push(id, false),
+                _ => (),
+            }
+
+            // pruning and folding follow resolution so dead code is still checked
+            let stmt = if prune { constant::prune(i, stmt) } else { stmt };
+            let stmt = if fold { constant::fold(i, stmt) } else { stmt };
+            let res = {
+                let _span = self.tracer.span("interpret");
+                i.execute(&stmt)
+            };
+            Writer::flush(&self.stdout)?;
+
+            if let Err(e) = res {
+                return self.report(e);
+            }
+        }
+
+        Writer::flush(&self.stderr)
+    }
+
+    fn render_error(&self, e: &Error) -> String {
+        Diagnostic::from_error(e, self.path.as_deref()).render(self.error_format)
+    }
+
+    fn render_warning(&self, w: &Warning) -> String {
+        Diagnostic::from_warning(w, self.path.as_deref()).render(self.error_format)
+    }
+
+    fn write_error(&self, e: &Error) -> Result<()> {
+        Writer::writeln(&self.stderr, &self.render_error(e))
+    }
+
+    /// Writes the error to stderr, then returns it.
+    fn report(&self, e: Error) -> Result<()> {
+        self.write_error(&e)?;
+        Writer::flush(&self.stderr)?;
+        Err(e)
+    }
+
+    /// Runs the imported module in its own globals, then defines its exports
+    /// as globals of the importer. Each module is run at most once, with
+    /// later imports receiving the current values of its exports. A module
+    /// imported while it is still running, as in a cycle, provides nothing.
+    fn import(&mut self, i: &mut Interpreter, tkn: &Token, path: &Token) -> Result<()> {
+        let name = match path.literal {
+            Some(Literal::String(ref s)) => s.to_string(),
+            _ => unreachable!("import paths are string literals"),
+        };
+
+        let candidates = self.module_candidates(&name);
+        let file = match candidates.iter().find(|c| c.is_file()) {
+            Some(f) => f.canonicalize()?,
+            None => return self.report(Error::Runtime(
+                tkn.line,
+                RuntimeKind::ModuleNotFound {
+                    name,
+                    candidates: candidates.iter().map(|c| c.display().to_string()).collect(),
+                },
+                path.lexeme.to_owned())),
+        };
+
+        if let Some(m) = self.modules.iter().find(|m| m.path == file) {
+            return match m.env {
+                Some(ref env) => bind_exports(i, env, &m.exports),
+                None => Ok(()),
+            };
+        }
+
+        // diagnostics name the module until it finishes running
+        let importer = self.path.replace(file.clone());
+        let src = match self.read(&file) {
+            Ok(src) => src,
+            Err(e) => {
+                self.path = importer;
+                return Err(e);
+            }
+        };
+        let idx = self.modules.len();
+        self.modules.push(Module { path: file.clone(), env: None, exports: Vec::new() });
+
+        let tracer = Rc::clone(&self.tracer);
+        let _span = tracer.span("import");
+
+        let outer = i.replace_env(Interpreter::globals(i.options()));
+        let dir = mem::replace(&mut self.dir, file.parent().map_or_else(PathBuf::new, Path::to_path_buf));
+        let declared = mem::take(&mut self.declared);
+
+        // the prelude's declarations are not exported
+        let res = self.prelude(i).and_then(|_| {
+            self.declared = Declared::default();
+            self.run(i, &src)
+        });
+
+        let exports = mem::replace(&mut self.declared, declared).exports();
+        self.dir = dir;
+        self.path = importer;
+        let env = i.replace_env(outer);
+
+        // a module that failed is run, and fails, again on its next import
+        if let Err(e) = res {
+            self.modules.remove(idx);
+            return Err(e);
+        }
+
+        bind_exports(i, &env, &exports)?;
+        self.modules[idx].env = Some(env);
+        self.modules[idx].exports = exports;
         Ok(())
     }
+
+    /// The paths a module may be found at, in the order they are searched.
+    /// Names without the `.lox` extension have it added. Names starting
+    /// with `./` or `../` are relative to the importing module.
+    fn module_candidates(&self, name: &str) -> Vec<PathBuf> {
+        let file = if name.ends_with(".lox") { name.to_owned() } else { format!("{}.lox", name) };
+
+        if name.starts_with("./") || name.starts_with("../") {
+            return vec![self.dir.join(file)];
+        }
+
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        for dir in iter::once(&self.base).chain(self.search_path.iter()) {
+            let path = dir.join(&file);
+            if !candidates.contains(&path) {
+                candidates.push(path);
+            }
+        }
+
+        candidates
+    }
+}
+
+/// The outcome of `eval_str`
+#[derive(Debug)]
+pub struct Evaluation {
+    /// The value of the source's final statement if it is an expression, or
+    /// nil otherwise
+    pub value: Object,
+    /// Everything printed while running
+    pub output: String,
+}
+
+/// Runs the source in a fresh, sandboxed interpreter with the standard
+/// library, capturing everything it prints rather than writing to stdout.
+/// The clock and `random()` are deterministic. The first error is returned
+/// rather than reported.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # use rlox::run::eval_str;
+/// # fn main() {
+/// let res = eval_str("var greeting = \"hi\"; print greeting; greeting + \"!\";").unwrap();
+/// assert_eq!("hi\n", res.output);
+/// assert_eq!("hi!", format!("{}", res.value));
+///
+/// assert!(eval_str("print undefined;").is_err());
+/// # }
+/// ```
+pub fn eval_str(src: &str) -> Result<Evaluation> {
+    let out = Rc::new(RefCell::new(Writer::Cursor(io::Cursor::new(Vec::new()))));
+    let err = Rc::new(RefCell::new(Writer::Cursor(io::Cursor::new(Vec::new()))));
+
+    let mut r = Runner::new(Rc::clone(&out), err);
+    r.set_options(InterpreterOptions {
+        sandbox: true,
+        clock: Clock::Virtual(0.0),
+        seed: Some(0),
+        ..InterpreterOptions::default()
+    });
+
+    let mut stmts = r.parser(src).collect::<Result<Vec<Stmt>>>()?;
+    let last = match stmts.pop() {
+        Some(Stmt::Expression(expr)) => Some(expr),
+        Some(stmt) => {
+            stmts.push(stmt);
+            None
+        }
+        None => None,
+    };
+
+    let mut i = r.interpreter(false);
+    r.prelude(&mut i)?;
+    r.execute(&mut i, stmts)?;
+
+    let value = match last {
+        Some(expr) => Resolver::resolve_expr(&mut i, &expr)?.eval_expr(&expr)?,
+        None => Object::NIL,
+    };
+
+    let output = match *out.borrow() {
+        Writer::Cursor(ref c) => String::from_utf8_lossy(c.get_ref()).into_owned(),
+        _ => unreachable!("output is captured by a cursor"),
+    };
+
+    Ok(Evaluation { value, output })
+}
+
+/// Defines the module's exports as globals of the interpreter.
+fn bind_exports(i: &mut Interpreter, env: &Env, exports: &[String]) -> Result<()> {
+    for name in exports {
+        let id = Token::identifier(name);
+
+        i.define_global(name, env.get_at(&id, None)?)?;
+    }
+
+    Ok(())
+}
+
+/// Counts the diagnostics reported while resolving, e.g. "3 warnings, 1 error".
+fn summary(warnings: usize, errors: usize) -> String {
+    let plural = |n: usize, what: &str| match n {
+        1 => format!("1 {}", what),
+        n => format!("{} {}s", n, what),
+    };
+
+    format!("{}, {}", plural(warnings, "warning"), plural(errors, "error"))
 }
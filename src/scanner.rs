@@ -4,8 +4,8 @@ use std::collections::{HashSet, VecDeque};
 use std::ops::Index;
 use std::str::Chars;
 
-use result::{Result, Error};
-use ast::token::{Token, Type, Literal};
+use result::{Result, Error, LexicalKind};
+use ast::token::{Token, Type, Literal, Lexeme};
 
 /// Scanner is an iterator that consumes a `Chars` iterator, returning `Result<Token>`.
 ///
@@ -14,8 +14,13 @@ use ast::token::{Token, Type, Literal};
 /// # Examples
 /// ```
 /// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
 /// # use rlox::scanner::*;
+/// # #[cfg(feature = "internals")]
 /// # use rlox::ast::token;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
 /// # fn main() {
 /// let code = "num = 123";
 /// let mut scanner = Scanner::new(code.chars());
@@ -32,33 +37,160 @@ use ast::token::{Token, Type, Literal};
 /// assert_eq!(token::Literal::Number(123.), lit.literal.expect("should have a literal"));
 ///
 /// let eof = scanner.next().expect("should have token").unwrap();
-/// assert_eq!(token::Type::EOF, eof.typ);
+/// assert_eq!(token::Type::Eof, eof.typ);
 ///
 /// assert!(scanner.next().is_none());
 /// # }
 /// ```
 #[derive(Debug)]
 pub struct Scanner<'a> {
+    input: &'a str,
     src: Chars<'a>,
     peeks: VecDeque<char>,
     lexeme: String,
     line: u64,
     offset: u64,
+    /// The line and offset at the end of the last emitted token, used to
+    /// locate the EOF token ahead of any trailing whitespace or comments
+    end: (u64, u64),
     eof: bool,
+    comments: bool,
+    /// The byte positions of the current lexeme's start and of the next
+    /// character to be read
+    start: usize,
+    pos: usize,
+    source: u64,
 }
 
 impl<'a> Scanner<'a> {
     /// Creates a new Scanner off a Chars iterator.
     pub fn new(c: Chars<'a>) -> Self {
         Scanner {
+            input: c.as_str(),
             src: c,
             peeks: VecDeque::with_capacity(2),
             lexeme: "".to_string(),
             line: 1,
             offset: 0,
+            end: (1, 0),
             eof: false,
+            comments: false,
+            start: 0,
+            pos: 0,
+            source: 0,
         }
     }
+
+    /// Toggles whether comments are emitted as `Comment` tokens instead of
+    /// being discarded, allowing tooling to preserve them. Doc comments,
+    /// beginning with exactly `///`, are always emitted as `DocComment`
+    /// tokens.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate rlox;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::scanner::*;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::ast::token;
+    /// # #[cfg(not(feature = "internals"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "internals")]
+    /// # fn main() {
+    /// let code = "/* a /* nested */ comment */ x // trailing";
+    /// let mut scanner = Scanner::new(code.chars());
+    /// scanner.set_comments(true);
+    ///
+    /// let block = scanner.next().expect("should have token").unwrap();
+    /// assert_eq!(token::Type::Comment, block.typ);
+    /// assert_eq!("/* a /* nested */ comment */", block.lexeme);
+    ///
+    /// let ident = scanner.next().expect("should have token").unwrap();
+    /// assert_eq!(token::Type::Identifier, ident.typ);
+    ///
+    /// let line = scanner.next().expect("should have token").unwrap();
+    /// assert_eq!(token::Type::Comment, line.typ);
+    /// assert_eq!("// trailing", line.lexeme);
+    ///
+    /// let mut scanner = Scanner::new("/// documented\nfun".chars());
+    /// let doc = scanner.next().expect("should have token").unwrap();
+    /// assert_eq!(token::Type::DocComment, doc.typ);
+    /// assert_eq!("/// documented", doc.lexeme);
+    /// # }
+    /// ```
+    pub fn set_comments(&mut self, comments: bool) {
+        self.comments = comments;
+    }
+
+    /// Sets the source identifier attached to each scanned token.
+    pub fn set_source(&mut self, source: u64) {
+        self.source = source;
+    }
+
+    /// Converts the Scanner into a lossless iterator of Lexemes, which
+    /// includes comments and preserves all whitespace as trivia.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate rlox;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::scanner::*;
+    /// # #[cfg(feature = "internals")]
+    /// # use rlox::ast::token;
+    /// # #[cfg(not(feature = "internals"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "internals")]
+    /// # fn main() {
+    /// let code = "var s = \"a\\\"b\"; // done\nprint s";
+    /// let lexemes: Vec<token::Lexeme> = Scanner::new(code.chars())
+    ///     .lossless()
+    ///     .map(|l| l.expect("should scan"))
+    ///     .collect();
+    ///
+    /// assert_eq!(" ", lexemes[1].trivia);
+    /// assert_eq!(8..14, lexemes[3].span);
+    /// assert_eq!(token::Type::Comment, lexemes[5].token.typ);
+    /// assert_eq!("\n", lexemes[6].trivia);
+    ///
+    /// let round_trip: String = lexemes.iter()
+    ///     .map(|l| format!("{}{}", l.trivia, l.text))
+    ///     .collect();
+    /// assert_eq!(code, round_trip);
+    /// # }
+    /// ```
+    pub fn lossless(mut self) -> Lossless<'a> {
+        self.set_comments(true);
+        Lossless { scanner: self, last: 0 }
+    }
+}
+
+/// An iterator of Lexemes produced by a lossless Scanner.
+pub struct Lossless<'a> {
+    scanner: Scanner<'a>,
+    last: usize,
+}
+
+impl<'a> Iterator for Lossless<'a> {
+    type Item = Result<Lexeme<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tkn = match self.scanner.next()? {
+            Ok(tkn) => tkn,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let src = self.scanner.input;
+        let span = self.scanner.start..self.scanner.pos;
+        let trivia = &src[self.last..span.start];
+        self.last = span.end;
+
+        Some(Ok(Lexeme {
+            token: tkn,
+            trivia,
+            text: &src[span.clone()],
+            span,
+        }))
+    }
 }
 
 impl<'a> Scanner<'a> {
@@ -67,10 +199,17 @@ impl<'a> Scanner<'a> {
             return None;
         }
 
+        if self.lexeme.is_empty() {
+            self.start = self.pos;
+        }
+
         match self.peeks.len() {
             0 => self.src.next(),
             _ => self.peeks.pop_front(),
-        }.or_else(|| {
+        }.inspect(|c| {
+            // the '\0' sentinel pushed by lookahead is not part of the source
+            if *c != '\0' { self.pos += c.len_utf8(); }
+        }).or_else(|| {
             self.eof = true;
             Some('\0')
         }).and_then(|c| {
@@ -137,12 +276,26 @@ impl<'a> Scanner<'a> {
             literal: lit,
             line: self.line,
             offset: self.offset - self.lexeme.len() as u64,
+            source: self.source,
             lexeme: self.lexeme.clone(),
         }))
     }
 
-    fn err(&self, msg: &str) -> Option<Result<Token>> {
-        Some(Err(Error::Lexical(self.line, msg.to_string(), self.lexeme.clone())))
+    fn eof_token(&mut self) -> Option<Result<Token>> {
+        self.eof = true;
+
+        Some(Ok(Token {
+            typ: Type::Eof,
+            literal: None,
+            line: self.end.0,
+            offset: self.end.1,
+            source: self.source,
+            lexeme: self.lexeme.clone(),
+        }))
+    }
+
+    fn err(&self, kind: LexicalKind) -> Option<Result<Token>> {
+        Some(Err(Error::Lexical(self.line, kind, self.lexeme.clone())))
     }
 
     fn match_static_token(&mut self, c: char, m: Type, u: Type) -> Option<Result<Token>> {
@@ -161,8 +314,8 @@ impl<'a> Scanner<'a> {
                 '\n' => self.line += 1,
                 '"' if last == '\\' => { self.lexeme.pop(); }
                 '"' => break,
-                '\0' => return self.err("unterminated string"),
-                _ => return self.err("unexpected character"),
+                '\0' => return self.err(LexicalKind::UnterminatedString),
+                _ => return self.err(LexicalKind::UnexpectedCharacter),
             };
 
             self.advance();
@@ -176,7 +329,7 @@ impl<'a> Scanner<'a> {
             .take(self.lexeme.len() - 2)
             .collect();
 
-        self.literal_token(Type::String, Some(Literal::String(lit)))
+        self.literal_token(Type::String, Some(Literal::String(lit.into())))
     }
 
     fn number(&mut self) -> Option<Result<Token>> {
@@ -185,13 +338,19 @@ impl<'a> Scanner<'a> {
         if self.peek() == '.' && self.peek_next().is_digit(10) {
             self.advance();
             while self.peek().is_digit(10) { self.advance(); };
+
+            // a second fraction, as in 1.2.3, cannot be a property access
+            if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+                while self.peek() == '.' || self.peek().is_ascii_digit() { self.advance(); };
+                return self.err(LexicalKind::InvalidNumber);
+            }
         }
 
         if let Ok(lit) = self.lexeme.clone().parse::<f64>() {
             return self.literal_token(Type::Number, Some(Literal::Number(lit)));
         }
 
-        self.err("invalid numeric")
+        self.err(LexicalKind::InvalidNumber)
     }
 
     fn identifier(&mut self) -> Option<Result<Token>> {
@@ -209,31 +368,59 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    fn line_comment(&mut self) {
+    fn line_comment(&mut self) -> Option<Result<Token>> {
+        let (line, offset) = (self.line, self.offset - 1);
         self.advance_until(&['\n']);
-        self.lexeme.clear();
+        self.comment_token(line, offset)
     }
 
-    fn block_comment(&mut self) {
+    fn block_comment(&mut self) -> Option<Result<Token>> {
+        let (line, offset) = (self.line, self.offset - 1);
+        let mut depth = 1;
+
         self.advance(); // *
 
-        loop {
-            let last = self.advance_until(&['\n', '/']);
-            let next = self.peek();
-            match (last, next) {
-                (_, '\n') => self.line += 1,
+        while depth > 0 {
+            match (self.peek(), self.peek_next()) {
+                ('\0', _) => break,
+                ('/', '*') => {
+                    self.advance();
+                    self.advance();
+                    depth += 1;
+                }
                 ('*', '/') => {
-                    self.advance(); // *
-                    self.advance(); // /
-                    break;
+                    self.advance();
+                    self.advance();
+                    depth -= 1;
+                }
+                ('\n', _) => {
+                    self.advance();
+                    self.line += 1;
+                    self.offset = 0;
                 }
-                (_, '\0') => break,
-                (_, _) => (), // noop
+                _ => { self.advance(); }
             }
-            self.advance();
         }
 
-        self.lexeme.clear();
+        self.comment_token(line, offset)
+    }
+
+    fn comment_token(&mut self, line: u64, offset: u64) -> Option<Result<Token>> {
+        let doc = self.lexeme.starts_with("///") && !self.lexeme.starts_with("////");
+
+        if !self.comments && !doc {
+            self.lexeme.clear();
+            return None;
+        }
+
+        Some(Ok(Token {
+            typ: if doc { Type::DocComment } else { Type::Comment },
+            lexeme: self.lexeme.clone(),
+            literal: None,
+            line,
+            offset,
+            source: self.source,
+        }))
     }
 }
 
@@ -241,20 +428,25 @@ impl<'a> Iterator for Scanner<'a> {
     type Item = Result<Token>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        use ast::token::Type::*;
-
         if self.eof {
             return None;
         }
 
+        let tkn = self.scan();
+        self.end = (self.line, self.offset);
+        tkn
+    }
+}
+
+impl<'a> Scanner<'a> {
+    fn scan(&mut self) -> Option<Result<Token>> {
+        use ast::token::Type::*;
+
         self.lexeme.clear();
 
         loop {
             match self.advance().unwrap() {
-                '\0' => {
-                    self.eof = true;
-                    return self.static_token(EOF);
-                }
+                '\0' => return self.eof_token(),
 
                 '(' => return self.static_token(LeftParen),
                 ')' => return self.static_token(RightParen),
@@ -274,11 +466,27 @@ impl<'a> Iterator for Scanner<'a> {
 
                 '"' => return self.string(),
 
-                '/' => match self.peek() {
-                    '/' => self.line_comment(),
-                    '*' => self.block_comment(),
-                    _ => return self.static_token(Slash),
-                },
+                // a leading shebang line lets scripts be run directly, as with
+                // `#!/usr/bin/env rlox`
+                '#' if self.start == 0 && self.peek() == '!' => {
+                    let comment = self.line_comment();
+
+                    if comment.is_some() {
+                        return comment;
+                    }
+                }
+
+                '/' => {
+                    let comment = match self.peek() {
+                        '/' => self.line_comment(),
+                        '*' => self.block_comment(),
+                        _ => return self.static_token(Slash),
+                    };
+
+                    if comment.is_some() {
+                        return comment;
+                    }
+                }
 
                 c if c.is_whitespace() => {
                     self.lexeme.clear();
@@ -291,7 +499,7 @@ impl<'a> Iterator for Scanner<'a> {
                 c if c.is_digit(10) => return self.number(),
                 c if is_alphanumeric(c) => return self.identifier(),
 
-                _ => return self.err("unexpected character"),
+                _ => return self.err(LexicalKind::UnexpectedCharacter),
             }
         }
     }
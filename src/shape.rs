@@ -0,0 +1,71 @@
+//! A module describing the layout of instance fields.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A Shape maps field names to slot indices, in the order the fields were
+/// first assigned. Instances store only their shape and a vector of slots.
+///
+/// Adding a field transitions to a child shape, which is cached, so
+/// instances assigned the same fields in the same order share a shape.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::shape::Shape;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// # use std::rc::Rc;
+/// let root = Shape::root();
+/// let xy = root.with("x").with("y");
+///
+/// assert_eq!(Some(1), xy.slot("y"));
+/// assert_eq!(vec!["x", "y"], xy.names().collect::<Vec<_>>());
+/// assert!(Rc::ptr_eq(&xy, &root.with("x").with("y")));
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Shape {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    transitions: RefCell<HashMap<String, Rc<Shape>>>,
+}
+
+impl Shape {
+    /// Creates the shape of an instance without fields
+    pub fn root() -> Rc<Shape> {
+        Rc::new(Shape::default())
+    }
+
+    /// Returns the slot of the named field, if the shape has it
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.index.get(name).cloned()
+    }
+
+    /// Returns the shape with the named field added in the next slot.
+    pub fn with(&self, name: &str) -> Rc<Shape> {
+        if let Some(next) = self.transitions.borrow().get(name) {
+            return Rc::clone(next);
+        }
+
+        let mut names = self.names.clone();
+        names.push(name.to_owned());
+
+        let mut index = self.index.clone();
+        index.insert(name.to_owned(), self.names.len());
+
+        let next = Rc::new(Shape { names, index, transitions: RefCell::default() });
+        self.transitions.borrow_mut().insert(name.to_owned(), Rc::clone(&next));
+        next
+    }
+
+    /// Iterates over the field names in slot order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+}
@@ -0,0 +1,45 @@
+//! A module describing the reading of source files.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use result::{Error, LexicalKind, Result};
+
+/// The UTF-8 byte order mark, which some editors prefix files with
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Reads the file as source text. See `decode`.
+pub fn read(path: &Path) -> Result<String> {
+    let mut bytes = Vec::new();
+    File::open(path).and_then(|mut h| h.read_to_end(&mut bytes))?;
+    decode(bytes)
+}
+
+/// Decodes source text, dropping a leading byte order mark and normalizing
+/// `\r\n` line endings to `\n`. Invalid UTF-8 is reported as a lexical error
+/// at the offending byte.
+pub fn decode(mut bytes: Vec<u8>) -> Result<String> {
+    let skipped = if bytes.starts_with(BOM) {
+        bytes.drain(..BOM.len());
+        BOM.len()
+    } else {
+        0
+    };
+
+    let src = String::from_utf8(bytes).map_err(|e| {
+        let at = e.utf8_error().valid_up_to();
+        let bytes = e.as_bytes();
+        let line = bytes[..at].iter().filter(|&&b| b == b'\n').count() as u64 + 1;
+
+        Error::Lexical(line,
+                       LexicalKind::InvalidUtf8(at + skipped),
+                       format!("0x{:02X}", bytes[at]))
+    })?;
+
+    if src.contains('\r') {
+        return Ok(src.replace("\r\n", "\n"));
+    }
+
+    Ok(src)
+}
@@ -0,0 +1,159 @@
+//! A module describing the counting of work done while running, as
+//! summarized by `rlox --report`.
+//!
+//! Envs and objects are created where no interpreter is at hand, so counts
+//! are kept per thread rather than per Runner. Nothing is counted unless
+//! enabled, as by `Runner::set_report`.
+
+use std::cell::Cell;
+
+/// The work counted while running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// A statement executed
+    Statement,
+    /// An env created for a scope or call
+    Env,
+    /// A function created by a declaration. Binding a method to an instance
+    /// creates none.
+    Function,
+    Class,
+    Instance,
+    /// A string created by concatenation
+    String,
+}
+
+/// Stats are the counts recorded since the last reset.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::run::eval_str;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::stats;
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// let src = "class A {} var a = A(); for (var i = 0; i < 3; i = i + 1) {}";
+///
+/// stats::reset();
+/// eval_str(src).unwrap();
+/// assert_eq!(0, stats::snapshot().instances);
+///
+/// stats::enable(true);
+/// eval_str(src).unwrap();
+///
+/// let s = stats::snapshot();
+/// assert_eq!(1, s.instances);
+/// assert!(s.statements > 3);
+/// assert!(s.peak_envs <= s.envs);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub statements: u64,
+    pub envs: u64,
+    /// The most envs alive at once
+    pub peak_envs: u64,
+    pub functions: u64,
+    pub classes: u64,
+    pub instances: u64,
+    pub strings: u64,
+    live_envs: u64,
+}
+
+thread_local! {
+    static STATS: Cell<Stats> = Cell::new(Stats::default());
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Toggles counting on the current thread.
+pub fn enable(on: bool) {
+    ENABLED.with(|e| e.set(on));
+}
+
+/// Returns true if counting is enabled on the current thread.
+pub fn is_enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Counts one unit of the work, if enabled.
+pub fn record(c: Counter) {
+    if !is_enabled() {
+        return;
+    }
+
+    update(|s| match c {
+        Counter::Statement => s.statements += 1,
+        Counter::Env => {
+            s.envs += 1;
+            s.live_envs += 1;
+            s.peak_envs = s.peak_envs.max(s.live_envs);
+        }
+        Counter::Function => s.functions += 1,
+        Counter::Class => s.classes += 1,
+        Counter::Instance => s.instances += 1,
+        Counter::String => s.strings += 1,
+    });
+}
+
+/// Live is held by an env, counting it as created and alive until it is
+/// dropped, so the peak reflects those alive at once. Envs created while
+/// counting is disabled are never counted.
+#[derive(Debug, Default)]
+pub struct Live(bool);
+
+impl Live {
+    /// Counts an env created, returning its guard
+    pub fn env() -> Live {
+        record(Counter::Env);
+        Live(is_enabled())
+    }
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        if self.0 {
+            update(|s| s.live_envs = s.live_envs.saturating_sub(1));
+        }
+    }
+}
+
+/// Clears the counts. Envs alive before the reset are not counted as live
+/// afterward.
+pub fn reset() {
+    STATS.with(|s| s.set(Stats::default()));
+}
+
+/// Returns the counts recorded since the last reset.
+pub fn snapshot() -> Stats {
+    STATS.with(Cell::get)
+}
+
+fn update<F: FnOnce(&mut Stats)>(f: F) {
+    STATS.with(|cell| {
+        let mut s = cell.get();
+        f(&mut s);
+        cell.set(s);
+    });
+}
+
+impl Stats {
+    /// Renders the counts as an aligned table.
+    pub fn summary(&self) -> String {
+        let rows = [
+            ("statements executed", self.statements),
+            ("envs created", self.envs),
+            ("envs alive at peak", self.peak_envs),
+            ("functions created", self.functions),
+            ("classes created", self.classes),
+            ("instances created", self.instances),
+            ("strings created", self.strings),
+        ];
+
+        rows.iter().map(|&(name, n)| format!("{:<20}  {:>10}\n", name, n)).collect()
+    }
+}
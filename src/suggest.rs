@@ -0,0 +1,53 @@
+//! A module describing the suggestion of names close to a misspelled one.
+
+/// The largest edit distance at which a name is still suggested
+const MAX_DISTANCE: usize = 2;
+
+/// Returns the candidate nearest to the name, ignoring case, if any is
+/// within two edits and a third of the name's length. Ties are broken
+/// alphabetically, so the suggestion does not depend on the order of the
+/// candidates.
+pub fn closest<'a, I>(name: &str, candidates: I) -> Option<String>
+    where I: IntoIterator<Item = &'a str>
+{
+    let target: Vec<char> = name.to_lowercase().chars().collect();
+
+    candidates.into_iter()
+        .filter(|c| *c != name)
+        .filter_map(|c| {
+            let d = distance(&target, &c.to_lowercase().chars().collect::<Vec<_>>());
+            // short names are within two edits of nearly anything
+            if d <= MAX_DISTANCE && d * 3 <= target.len() {
+                Some((d, c))
+            } else {
+                None
+            }
+        })
+        .min()
+        .map(|(_, c)| c.to_owned())
+}
+
+/// The edit distance between the two strings, counting insertions,
+/// deletions, substitutions, and swaps of adjacent characters as one edit
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j - 1] + cost).min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
@@ -0,0 +1,153 @@
+//! A module describing the timing of the phases a Runner steps through.
+//!
+//! Spans are named and nest, so each is recorded under the path of the
+//! spans enclosing it (e.g. `prelude/list/resolve`). Repeated spans, such as
+//! each line run by the REPL, are aggregated into a single entry.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use ordered::OrderedMap;
+
+/// The format of the timing summary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    /// An aligned table, one span per row
+    Table,
+    /// A JSON array of objects with `span`, `count`, and `ms` keys
+    Json,
+}
+
+impl Output {
+    /// Returns the Output matching its command line name, if any.
+    pub fn from_name(name: &str) -> Option<Output> {
+        match name {
+            "table" => Some(Output::Table),
+            "json" => Some(Output::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Tracer records the time spent within each span. A disabled Tracer
+/// records nothing and does not read the clock.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rlox;
+/// # #[cfg(feature = "internals")]
+/// # use rlox::trace::{Output, Tracer};
+/// # #[cfg(not(feature = "internals"))]
+/// # fn main() {}
+/// # #[cfg(feature = "internals")]
+/// # fn main() {
+/// # use std::rc::Rc;
+/// let t = Rc::new(Tracer::new(true));
+///
+/// {
+///     let _run = t.span("run");
+///     for _ in 0..3 {
+///         let _line = t.span("line");
+///     }
+/// }
+///
+/// let json = t.summary(Output::Json);
+/// assert!(json.starts_with(r#"[{"span":"run","count":1,"#));
+/// assert!(json.contains(r#"{"span":"run/line","count":3,"#));
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Tracer {
+    enabled: bool,
+    stack: RefCell<Vec<String>>,
+    spans: RefCell<OrderedMap<(u64, Duration)>>,
+}
+
+impl Tracer {
+    pub fn new(enabled: bool) -> Tracer {
+        Tracer {
+            enabled,
+            ..Tracer::default()
+        }
+    }
+
+    /// Returns true if spans are being recorded
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Opens a span nested within those currently open, which is recorded
+    /// when the returned guard is dropped.
+    pub fn span(self: &Rc<Self>, name: &str) -> Span {
+        if !self.enabled {
+            return Span { tracer: Rc::clone(self), start: None };
+        }
+
+        let mut stack = self.stack.borrow_mut();
+        stack.push(name.to_owned());
+
+        // spans are listed in the order they were first opened, so parents
+        // precede their children
+        let path = stack.join("/");
+        let mut spans = self.spans.borrow_mut();
+        if !spans.contains_key(&path) {
+            spans.insert(path, (0, Duration::default()));
+        }
+
+        Span { tracer: Rc::clone(self), start: Some(Instant::now()) }
+    }
+
+    /// Renders the time recorded for each span
+    pub fn summary(&self, out: Output) -> String {
+        let spans = self.spans.borrow();
+
+        match out {
+            Output::Table => {
+                let width = spans.keys().map(str::len).max().unwrap_or(0).max("span".len());
+
+                let mut s = format!("{:<w$}  {:>8}  {:>12}\n", "span", "count", "total", w = width);
+                for &(ref path, (count, total)) in spans.iter() {
+                    s.push_str(&format!("{:<w$}  {:>8}  {:>10.3}ms\n", path, count, millis(total), w = width));
+                }
+                s
+            }
+            Output::Json => {
+                let rows: Vec<String> = spans.iter()
+                    .map(|&(ref path, (count, total))| format!(
+                        r#"{{"span":{:?},"count":{},"ms":{:.3}}}"#, path, count, millis(total)))
+                    .collect();
+                format!("[{}]\n", rows.join(","))
+            }
+        }
+    }
+
+    fn close(&self, elapsed: Duration) {
+        let mut stack = self.stack.borrow_mut();
+        let path = stack.join("/");
+        stack.pop();
+
+        let mut spans = self.spans.borrow_mut();
+        let (count, total) = spans.get(&path).cloned().unwrap_or_default();
+        spans.insert(path, (count + 1, total + elapsed));
+    }
+}
+
+/// Span is the guard for an open span, recording its duration when dropped
+pub struct Span {
+    tracer: Rc<Tracer>,
+    start: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            self.tracer.close(start.elapsed());
+        }
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1e3 + f64::from(d.subsec_nanos()) / 1e6
+}
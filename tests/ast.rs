@@ -0,0 +1,135 @@
+#![cfg(feature = "internals")]
+
+extern crate rlox;
+
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::rc::Rc;
+
+use rlox::ast::expr::Expr;
+use rlox::ast::stmt::Stmt;
+use rlox::interpreter::{Interpreter, InterpreterOptions};
+use rlox::lint::Lints;
+use rlox::output::Writer;
+use rlox::resolver::Resolver;
+use rlox::{Error, ParseKind, Result};
+
+/// Resolves and executes the hand-built program, returning what it printed
+fn run(stmts: &[Stmt]) -> Result<String> {
+    let out = Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    {
+        let mut i = Interpreter::new(InterpreterOptions::default(), Rc::clone(&out), Rc::clone(&out));
+        let i = Resolver::resolve_all(&mut i, stmts, Lints::default(), &mut Vec::new())?;
+        for stmt in stmts {
+            i.execute(stmt)?;
+        }
+    }
+
+    let out = out.borrow();
+    match *out {
+        Writer::Cursor(ref c) => Ok(String::from_utf8_lossy(c.get_ref()).into_owned()),
+        _ => unreachable!("stdout is captured by a cursor"),
+    }
+}
+
+fn num(n: f64) -> Expr {
+    Expr::number(n)
+}
+
+fn id(name: &str) -> Expr {
+    Expr::identifier(name)
+}
+
+#[test]
+fn functions_built_by_hand_are_executed() {
+    let stmts = vec![
+        Stmt::function("fib", &["n"], vec![
+            Stmt::if_else(Expr::binary(id("n"), "<", num(2.0)), Stmt::ret(Some(id("n"))), None),
+            Stmt::ret(Some(Expr::binary(
+                Expr::call(id("fib"), vec![Expr::binary(id("n"), "-", num(1.0))]),
+                "+",
+                Expr::call(id("fib"), vec![Expr::binary(id("n"), "-", num(2.0))])))),
+        ]),
+        Stmt::print(Expr::call(id("fib"), vec![num(10.0)])),
+    ];
+
+    assert_eq!("55\n", run(&stmts).expect("program should run"));
+}
+
+#[test]
+fn closures_built_by_hand_are_resolved() {
+    let stmts = vec![
+        Stmt::function("counter", &[], vec![
+            Stmt::var("n", Some(num(0.0))),
+            Stmt::function("inc", &[], vec![
+                Stmt::expression(Expr::assign("n", Expr::binary(id("n"), "+", num(1.0)))),
+                Stmt::ret(Some(id("n"))),
+            ]),
+            Stmt::ret(Some(id("inc"))),
+        ]),
+        Stmt::var("c", Some(Expr::call(id("counter"), vec![]))),
+        Stmt::expression(Expr::call(id("c"), vec![])),
+        Stmt::print(Expr::call(id("c"), vec![])),
+        Stmt::block(vec![
+            Stmt::var("c", Some(Expr::unary("-", num(1.0)))),
+            Stmt::print(id("c")),
+        ]),
+    ];
+
+    assert_eq!("2\n-1\n", run(&stmts).expect("program should run"));
+}
+
+#[test]
+fn classes_built_by_hand_are_executed() {
+    let stmts = vec![
+        Stmt::class("Shape", None, vec![
+            Stmt::function("describe", &[], vec![
+                Stmt::ret(Some(Expr::binary(Expr::string("area "), "+",
+                                            Expr::call(Expr::get(Expr::this(), "area"), vec![])))),
+            ]),
+        ]),
+        Stmt::class("Square", Some("Shape"), vec![
+            Stmt::function("init", &["side"], vec![
+                Stmt::expression(Expr::set(Expr::this(), "side", id("side"))),
+            ]),
+            Stmt::function("area", &[], vec![
+                Stmt::ret(Some(Expr::binary(Expr::get(Expr::this(), "side"), "*",
+                                            Expr::get(Expr::this(), "side")))),
+            ]),
+            Stmt::function("describe", &[], vec![
+                Stmt::ret(Some(Expr::call(Expr::super_method("describe"), vec![]))),
+            ]),
+        ]),
+        Stmt::print(Expr::call(Expr::get(Expr::call(id("Square"), vec![num(3.0)]), "describe"), vec![])),
+    ];
+
+    assert_eq!("area 9\n", run(&stmts).expect("program should run"));
+}
+
+#[test]
+fn loops_built_by_hand_break() {
+    let stmts = vec![
+        Stmt::var("i", Some(num(0.0))),
+        Stmt::while_loop(Expr::boolean(true), Stmt::block(vec![
+            Stmt::if_else(Expr::binary(id("i"), "==", num(3.0)), Stmt::brk(), None),
+            Stmt::expression(Expr::assign("i", Expr::binary(id("i"), "+", num(1.0)))),
+        ])),
+        Stmt::print(id("i")),
+        Stmt::print(Expr::grouping(Expr::unary("!", Expr::nil()))),
+    ];
+
+    assert_eq!("3\ntrue\n", run(&stmts).expect("program should run"));
+}
+
+#[test]
+fn invalid_programs_built_by_hand_are_rejected_by_the_resolver() {
+    match run(&[Stmt::ret(Some(num(1.0)))]) {
+        Err(Error::Parse(_, ParseKind::TopLevelReturn, _)) => (),
+        res => panic!("a top-level return should not resolve: {:?}", res),
+    }
+
+    match run(&[Stmt::print(Expr::this())]) {
+        Err(Error::Parse(_, ParseKind::ThisOutsideClass, _)) => (),
+        res => panic!("this outside of a class should not resolve: {:?}", res),
+    }
+}
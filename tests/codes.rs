@@ -0,0 +1,69 @@
+extern crate rlox;
+
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::thread;
+
+use rlox::codes::CODES;
+use rlox::diagnostic::Format;
+use rlox::Writer;
+use rlox::lint::Lints;
+use rlox::run::Runner;
+
+/// The native stack the examples run on, matching the `rlox` binary's, as
+/// the E0204 example nests past the default maximum depth
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Runs the example as a script, returning the diagnostics it wrote as JSON
+fn diagnostics(code: &str, example: &str) -> String {
+    // the invalid UTF-8 example describes its raw byte with an escape
+    let src: Vec<u8> = example.split("\\xFF")
+        .map(|part| part.as_bytes().to_vec())
+        .collect::<Vec<_>>()
+        .join(&0xFF);
+
+    let path = env::temp_dir().join(format!("rlox-explain-{}.lox", code));
+    fs::write(&path, src).expect("example should be written");
+
+    let stderr = Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    {
+        let stdout = Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+        let mut r = Runner::new(stdout, Rc::clone(&stderr));
+        r.set_error_format(Format::Json);
+
+        // the examples run under default options, except for the codes only
+        // raised once the option enabling them is set
+        match code {
+            "E0006" => r.set_strict_division(true),
+            "E0007" => r.interrupt().trigger(),
+            c if c.starts_with('W') => r.set_lints(Lints::all()),
+            _ => (),
+        }
+
+        let _ = r.file(&path);
+    }
+    fs::remove_file(&path).expect("example should be removed");
+
+    let out = stderr.borrow();
+    match *out {
+        Writer::Cursor(ref c) => String::from_utf8_lossy(c.get_ref()).into_owned(),
+        _ => unreachable!("stderr is captured by a cursor"),
+    }
+}
+
+#[test]
+fn every_example_raises_its_code() {
+    for c in CODES.iter().filter(|c| !c.example.is_empty()) {
+        let out = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || diagnostics(c.code, c.example))
+            .expect("example thread should spawn")
+            .join()
+            .expect("example should not panic");
+        let expected = format!("\"code\":\"{}\"", c.code);
+        assert!(out.contains(&expected), "example of {} reported:\n{}", c.code, out);
+    }
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "internals")]
+
+extern crate rlox;
+
+use rlox::ast::token::{Literal, Token};
+use rlox::env::Env;
+use rlox::object::Object;
+use rlox::{Error, RuntimeKind};
+
+fn num(n: f64) -> Object {
+    Object::Literal(Literal::Number(n))
+}
+
+fn get(env: &Env, name: &str, dist: Option<usize>) -> String {
+    match env.get_at(&Token::identifier(name), dist.as_ref()) {
+        Ok(val) => format!("{}", val),
+        Err(e) => format!("{}", e),
+    }
+}
+
+fn define(env: &Env, name: &str, val: f64) {
+    env.define(&Token::identifier(name), num(val)).expect("variable should be defined");
+}
+
+#[test]
+fn globals_may_be_redefined_but_locals_may_not() {
+    let root = Env::new(true);
+    define(&root, "a", 1.0);
+    define(&root, "a", 2.0);
+    assert_eq!("2", get(&root, "a", None));
+
+    let local = Env::from(&root);
+    define(&local, "a", 3.0);
+    match local.define(&Token::identifier("a"), num(4.0)) {
+        Err(Error::Runtime(_, RuntimeKind::AlreadyDefined(ref name), _)) => assert_eq!("a", name),
+        res => panic!("redefining a local should fail: {:?}", res),
+    }
+}
+
+#[test]
+fn distance_selects_the_shadowed_variable() {
+    let root = Env::new(true);
+    define(&root, "a", 1.0);
+
+    let outer = Env::from(&root);
+    define(&outer, "a", 2.0);
+
+    let inner = Env::from(&outer);
+    define(&inner, "a", 3.0);
+
+    assert_eq!("3", get(&inner, "a", Some(0)));
+    assert_eq!("2", get(&inner, "a", Some(1)));
+    assert_eq!("1", get(&inner, "a", Some(2)));
+    assert_eq!("1", get(&inner, "a", None));
+}
+
+#[test]
+fn assignment_at_distance_writes_the_ancestor() {
+    let root = Env::new(true);
+    define(&root, "a", 1.0);
+
+    let outer = Env::from(&root);
+    define(&outer, "a", 2.0);
+    let inner = Env::from(&outer);
+
+    inner.assign_at(&Token::identifier("a"), num(20.0), Some(&1)).unwrap();
+    inner.assign_at(&Token::identifier("a"), num(10.0), None).unwrap();
+
+    assert_eq!("20", get(&outer, "a", Some(0)));
+    assert_eq!("10", get(&root, "a", None));
+}
+
+#[test]
+fn unresolved_lookups_skip_locals() {
+    let root = Env::new(true);
+    let local = Env::from(&root);
+    define(&local, "count", 1.0);
+
+    assert_eq!("1", get(&local, "count", Some(0)));
+    assert_eq!("Runtime Error [line 0] variable `count` is undefined: near count", get(&local, "count", None));
+    assert!(local.assign_at(&Token::identifier("count"), num(2.0), None).is_err());
+}
+
+#[test]
+fn distance_past_the_root_is_an_error() {
+    let root = Env::new(true);
+    let local = Env::from(&root);
+    define(&root, "a", 1.0);
+
+    assert_eq!("Runtime Error [line 0] ancestor is undefined at depth 2: near a", get(&local, "a", Some(2)));
+    assert!(local.assign_at(&Token::identifier("a"), num(2.0), Some(&2)).is_err());
+}
+
+#[test]
+fn depth_counts_ancestors() {
+    let root = Env::new(true);
+    let outer = Env::from(&root);
+    let inner = Env::from_weak(&outer);
+
+    assert_eq!(0, root.depth());
+    assert_eq!(1, outer.depth());
+    assert_eq!(2, inner.depth());
+}
+
+#[test]
+fn flatten_omits_shadowed_variables() {
+    let root = Env::new(true);
+    define(&root, "a", 1.0);
+    define(&root, "b", 2.0);
+
+    let local = Env::from(&root);
+    define(&local, "a", 3.0);
+
+    let vals: Vec<String> = local.flatten().into_iter()
+        .filter(|(name, _)| name.len() == 1)
+        .map(|(name, val)| format!("{}={}", name, val))
+        .collect();
+    assert_eq!(vec!["a=3", "b=2"], vals);
+    assert!(local.flatten().iter().any(|(name, _)| name == "clock"));
+}
+
+#[test]
+fn dropped_weak_parent_is_an_error() {
+    let root = Env::new(true);
+    define(&root, "a", 1.0);
+
+    let closure = Env::from_weak(&root);
+    define(&closure, "b", 2.0);
+    drop(root);
+
+    let err = "Runtime Error [line 0] cannot access `a`: its enclosing env was dropped: near a";
+    assert_eq!(err, get(&closure, "a", Some(1)));
+    assert_eq!(err, get(&closure, "a", Some(0)));
+    assert_eq!(err, get(&closure, "a", None));
+    assert!(closure.assign_at(&Token::identifier("a"), num(3.0), None).is_err());
+    assert!(closure.define_global(&Token::identifier("a"), num(3.0)).is_err());
+
+    // the closure's own variables remain accessible
+    assert_eq!("2", get(&closure, "b", Some(0)));
+    assert_eq!(1, closure.depth());
+    assert_eq!(1, closure.flatten().len());
+}
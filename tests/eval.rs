@@ -0,0 +1,282 @@
+extern crate rlox;
+
+use rlox::run::eval_str;
+#[cfg(feature = "internals")]
+use rlox::stats;
+
+/// Runs the source, returning what it printed
+fn output(src: &str) -> String {
+    eval_str(src).unwrap_or_else(|e| panic!("source should run: {}\n{}", e, src)).output
+}
+
+/// Runs the source, returning the value of its final expression
+fn value(src: &str) -> String {
+    format!("{}", eval_str(src).unwrap_or_else(|e| panic!("source should run: {}\n{}", e, src)).value)
+}
+
+/// Runs the source, returning its error
+fn error(src: &str) -> String {
+    match eval_str(src) {
+        Ok(res) => panic!("source should fail, but printed {:?}\n{}", res.output, src),
+        Err(e) => format!("{}", e),
+    }
+}
+
+#[test]
+fn block_scopes_shadow_and_restore() {
+    assert_eq!("inner\nouter\n", output(r#"
+        var a = "outer";
+        {
+          var a = "inner";
+          print a;
+        }
+        print a;
+    "#));
+}
+
+#[test]
+fn assignment_reaches_the_enclosing_scope() {
+    assert_eq!("2", value(r#"
+        var a = 1;
+        {
+          a = 2;
+        }
+        a;
+    "#));
+}
+
+#[test]
+fn locals_cannot_read_themselves_while_initializing() {
+    assert_eq!(
+        "Parse Error [line 4] cannot read local variable in its own initializer.: near a",
+        error(r#"
+        var a = "outer";
+        {
+          var a = a;
+        }
+    "#));
+}
+
+#[test]
+fn closures_bind_the_variable_in_scope_when_declared() {
+    assert_eq!("global\nglobal\n", output(r#"
+        var a = "global";
+        {
+          fun showA() {
+            print a;
+          }
+
+          showA();
+          var a = "block";
+          showA();
+        }
+    "#));
+}
+
+#[test]
+fn closures_capture_variables_not_values() {
+    assert_eq!("1\n2\n3\n", output(r#"
+        fun makeCounter() {
+          var i = 0;
+          fun count() {
+            i = i + 1;
+            print i;
+          }
+          return count;
+        }
+
+        var counter = makeCounter();
+        counter();
+        counter();
+        counter();
+    "#));
+}
+
+#[test]
+fn closures_share_captured_variables() {
+    assert_eq!("after", value(r#"
+        var get;
+        var set;
+        fun outer() {
+          var x = "before";
+          fun g() { return x; }
+          fun s() { x = "after"; }
+          get = g;
+          set = s;
+        }
+
+        outer();
+        set();
+        get();
+    "#));
+}
+
+#[test]
+fn methods_are_inherited() {
+    assert_eq!("Fry until golden brown.\n", output(r#"
+        class Doughnut {
+          cook() {
+            print "Fry until golden brown.";
+          }
+        }
+
+        class BostonCream < Doughnut {}
+
+        BostonCream().cook();
+    "#));
+}
+
+#[test]
+fn super_calls_the_superclass_method() {
+    assert_eq!("Fry until golden brown.\nPipe full of custard and coat with chocolate.\n", output(r#"
+        class Doughnut {
+          cook() {
+            print "Fry until golden brown.";
+          }
+        }
+
+        class BostonCream < Doughnut {
+          cook() {
+            super.cook();
+            print "Pipe full of custard and coat with chocolate.";
+          }
+        }
+
+        BostonCream().cook();
+    "#));
+}
+
+#[test]
+fn super_is_bound_to_the_declaring_class() {
+    assert_eq!("A method\n", output(r#"
+        class A {
+          method() {
+            print "A method";
+          }
+        }
+
+        class B < A {
+          method() {
+            print "B method";
+          }
+
+          test() {
+            super.method();
+          }
+        }
+
+        class C < B {}
+
+        C().test();
+    "#));
+}
+
+#[test]
+fn initializers_return_this() {
+    assert_eq!("3", value(r#"
+        class Point {
+          init(x, y) {
+            this.x = x;
+            this.y = y;
+          }
+        }
+
+        var p = Point(1, 2);
+        p.init(3, 4).x;
+    "#));
+}
+
+#[test]
+fn bound_methods_remember_this() {
+    assert_eq!("Jane\n", output(r#"
+        class Person {
+          sayName() {
+            print this.name;
+          }
+        }
+
+        var jane = Person();
+        jane.name = "Jane";
+
+        var method = jane.sayName;
+        method();
+    "#));
+}
+
+#[test]
+fn classes_cannot_inherit_from_themselves() {
+    assert_eq!("Parse Error [line 1] a class cannot inherit from itself: near Oops", error("class Oops < Oops {}"));
+}
+
+#[test]
+fn statements_evaluate_to_nil() {
+    assert_eq!("nil", value("var a = 1;"));
+    assert_eq!("nil", value(""));
+}
+
+#[test]
+#[cfg(feature = "internals")]
+fn accessing_methods_allocates_nothing() {
+    let counts = |body: &str| {
+        stats::reset();
+        stats::enable(true);
+        output(&format!("class A {{ m() {{ return 1; }} }} var a = A(); for (var i = 0; i < 100; i = i + 1) {}", body));
+        stats::snapshot()
+    };
+
+    let (base, accessed) = (counts(";"), counts("a.m;"));
+    assert_eq!(base.envs, accessed.envs);
+    assert_eq!(base.functions, accessed.functions);
+
+    // each call allocates an env holding `this` and the arguments, and one
+    // for its body
+    let called = counts("a.m();");
+    assert_eq!(base.envs + 100 * 2, called.envs);
+}
+
+#[test]
+fn monkey_patching_is_opt_in() {
+    assert_eq!("Runtime Error [line 1] variable `defineMethod` is undefined: near defineMethod", error("defineMethod;"));
+}
+
+#[test]
+fn large_objects_do_not_grow_shapes_quadratically() {
+    let keys: Vec<String> = (0..20000).map(|i| format!(r#"\"k{}\": {}"#, i, i)).collect();
+    assert_eq!("19999", value(&format!(r#"
+        var o = jsonParse("{{{}}}");
+        o.k19999;
+    "#, keys.join(", "))));
+}
+
+#[test]
+fn the_default_call_depth_fits_the_test_thread_stack() {
+    let err = error(r#"
+        fun forever(n) {
+          return forever(n + 1);
+        }
+        forever(0);
+    "#);
+    assert!(err.contains("stack overflow"), "{}", err);
+}
+
+#[test]
+fn json_nesting_is_limited() {
+    let deep = format!("{}1{}", r#"{\"a\": "#.repeat(200000), "}".repeat(200000));
+    let err = error(&format!(r#"jsonParse("{}");"#, deep));
+    assert!(err.contains("invalid JSON: values nested deeper than 128"), "{}", err);
+
+    let deep = format!("{}{}", "[".repeat(200000), "]".repeat(200000));
+    let err = error(&format!(r#"jsonParse("{}");"#, deep));
+    assert!(err.contains("invalid JSON: values nested deeper than 128"), "{}", err);
+
+    assert_eq!("Runtime Error [line 9] cannot stringify values nested deeper than 128: near )", error(r#"
+        class Node {
+          init(next) { this.next = next; }
+        }
+        var n = nil;
+        for (var i = 0; i < 200; i = i + 1) {
+          n = Node(n);
+        }
+        jsonStringify(n);
+    "#));
+}
@@ -1,26 +1,48 @@
 extern crate rlox;
 
 use std::cell::RefCell;
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
 use std::io::{Cursor, SeekFrom};
 use std::io::prelude::*;
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::string::String;
+use std::sync::Mutex;
 
+use rlox::{Compat, Reader, Writer};
+use rlox::diagnostic;
+use rlox::host::Clock;
+use rlox::lint::{Lint, Lints};
 use rlox::run::Runner;
-use rlox::output::Writer;
 
 const TEST_DATA: &str = "testdata";
 
+/// Set to 1 to rewrite the expected output files from the actual output,
+/// after reviewing a change in behavior
+const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+/// The outputs written to each expected output file while updating, as
+/// several configurations may share a file
+static BLESSED: Mutex<BTreeMap<PathBuf, String>> = Mutex::new(BTreeMap::new());
+
 macro_rules! test_case {
     ($name:ident, $input:expr, $output:expr) => {
         #[test]
-        fn $name() { run_golden_master($input, $output) }
+        fn $name() { run_golden_master($input, $output, None, |_| ()) }
+    };
+    ($name:ident, $input:expr, $output:expr, $errput:expr) => {
+        #[test]
+        fn $name() { run_golden_master($input, $output, Some($errput), |_| ()) }
+    };
+    ($name:ident, $input:expr, $output:expr, $errput:expr, $configure:expr) => {
+        #[test]
+        fn $name() { run_golden_master($input, $output, Some($errput), $configure) }
     };
 }
 
-fn run_golden_master(input: &str, output: &str) {
+fn run_golden_master(input: &str, output: &str, errput: Option<&str>, configure: fn(&mut Runner)) {
     let i: PathBuf = [TEST_DATA, input].iter().collect();
     let o: PathBuf = [TEST_DATA, output].iter().collect();
 
@@ -31,32 +53,229 @@ fn run_golden_master(input: &str, output: &str) {
 
     {
         let mut r = Runner::new(Rc::clone(&stdout), Rc::clone(&stderr));
-        r.file(&i).expect("file should interpret successfully");
+        configure(&mut r);
+        let res = r.file(&i);
+
+        if errput.is_none() {
+            res.expect("file should interpret successfully");
+        }
     }
 
-    let mut expected = String::new();
+    assert_golden(&o, &read_writer(stdout));
+
+    if let Some(errput) = errput {
+        let e: PathBuf = [TEST_DATA, errput].iter().collect();
+        assert_golden(&e, &read_writer(stderr));
+    }
+}
+
+fn run_repl_golden_master(input: &str, output: &str) {
+    let i: PathBuf = [TEST_DATA, input].iter().collect();
+    let o: PathBuf = [TEST_DATA, output].iter().collect();
+
+    let stdin = Reader::Cursor(Cursor::new(read_file(&i).into_bytes()));
+    let stdout =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    let stderr =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+
     {
-        File::open(&o)
-            .expect("failed to open output file")
-            .read_to_string(&mut expected)
-            .expect("failed to read output file");
+        let mut r = Runner::new(Rc::clone(&stdout), Rc::clone(&stderr));
+        r.prompt(stdin).expect("REPL should exit cleanly");
     }
 
+    assert_golden(&o, &read_writer(stdout));
+    assert_eq!("", &read_writer(stderr));
+}
+
+fn run_record_golden_master(input: &str, session: &str) {
+    let i: PathBuf = [TEST_DATA, input].iter().collect();
+    let s: PathBuf = [TEST_DATA, session].iter().collect();
+
+    let stdin = Reader::Cursor(Cursor::new(read_file(&i).into_bytes()));
+    let out =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    let record =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+
+    {
+        let mut r = Runner::new(Rc::clone(&out), Rc::clone(&out));
+        r.set_record(Some(Rc::clone(&record)));
+        r.prompt(stdin).expect("REPL should exit cleanly");
+    }
+
+    assert_golden(&s, &read_writer(record));
+}
+
+fn run_replay_golden_master(session: &str, output: &str, errput: &str) {
+    let s: PathBuf = [TEST_DATA, session].iter().collect();
+    let o: PathBuf = [TEST_DATA, output].iter().collect();
+    let e: PathBuf = [TEST_DATA, errput].iter().collect();
+
+    let stdout =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    let stderr =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+
+    {
+        let mut r = Runner::new(Rc::clone(&stdout), Rc::clone(&stderr));
+        r.replay(&s).expect("replay should exit cleanly");
+    }
+
+    assert_golden(&o, &read_writer(stdout));
+    assert_golden(&e, &read_writer(stderr));
+}
+
+fn run_check_golden_master(input: &str, errput: &str) {
+    let i: PathBuf = [TEST_DATA, input].iter().collect();
+    let e: PathBuf = [TEST_DATA, errput].iter().collect();
+
+    let stdout =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    let stderr =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+
+    {
+        let mut r = Runner::new(Rc::clone(&stdout), Rc::clone(&stderr));
+        r.set_lints(Lints::all());
+        r.check(&i).expect_err("check should report errors");
+    }
+
+    assert_eq!("", &read_writer(stdout));
+    assert_golden(&e, &read_writer(stderr));
+}
+
+fn run_doc_golden_master(input: &str, output: &str) {
+    let i: PathBuf = [TEST_DATA, input].iter().collect();
+    let o: PathBuf = [TEST_DATA, output].iter().collect();
+
+    let stdout =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    let stderr =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
 
-    let mut actual = String::new();
     {
-        match Rc::try_unwrap(stdout)
-            .expect("unable to unwrap stdout")
-            .into_inner() {
-            Writer::Cursor(ref mut c) => {
-                c.seek(SeekFrom::Start(0)).expect("cannot seek to head of cursor");
-                c.read_to_string(&mut actual).expect("cannot read actual output");
+        let mut r = Runner::new(Rc::clone(&stdout), Rc::clone(&stderr));
+        r.doc(&i).expect("file should parse successfully");
+    }
+
+    assert_golden(&o, &read_writer(stdout));
+    assert_eq!("", &read_writer(stderr));
+}
+
+fn run_project_golden_master(dir: &str, output: &str, errput: &str, configure: fn(&mut Runner)) {
+    let d: PathBuf = [TEST_DATA, dir].iter().collect();
+    let o: PathBuf = [TEST_DATA, output].iter().collect();
+    let e: PathBuf = [TEST_DATA, errput].iter().collect();
+
+    let stdout =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+    let stderr =
+        Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+
+    {
+        let mut r = Runner::new(Rc::clone(&stdout), Rc::clone(&stderr));
+        configure(&mut r);
+        let _ = r.project(&d);
+    }
+
+    assert_golden(&o, &read_writer(stdout));
+    assert_golden(&e, &read_writer(stderr));
+}
+
+/// Compares the actual output to the expected output file, failing with a
+/// line diff if they differ. With `UPDATE_GOLDEN=1`, the file is rewritten
+/// with the actual output instead, unless another configuration sharing the
+/// file already wrote a different output.
+fn assert_golden(path: &PathBuf, actual: &str) {
+    if env::var(UPDATE_GOLDEN).map(|v| v == "1").unwrap_or(false) {
+        let mut blessed = BLESSED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(other) = blessed.get(path) {
+            if other != actual {
+                panic!("configurations sharing {} disagree (- blessed, + actual):\n{}\ngive them separate files",
+                       path.display(), diff(other, actual));
             }
-            _ => unreachable!(),
-        };
+            return;
+        }
+
+        fs::write(path, actual).expect("failed to write output file");
+        blessed.insert(path.clone(), actual.to_owned());
+        return;
+    }
+
+    let expected = read_file(path);
+    if expected != actual {
+        panic!("{} does not match the actual output (- expected, + actual):\n{}\nrerun with {}=1 to accept the actual output",
+               path.display(), diff(&expected, actual), UPDATE_GOLDEN);
+    }
+}
+
+/// Renders a line diff of the two outputs, prefixing lines only expected
+/// with `-` and lines only in the actual output with `+`.
+fn diff(expected: &str, actual: &str) -> String {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push_str(&format!("  {}\n", a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out.push_str(&format!("+ {}\n", b[j]));
+            j += 1;
+        } else {
+            out.push_str(&format!("- {}\n", a[i]));
+            i += 1;
+        }
+    }
+
+    if lcs[0][0] == a.len() && a.len() == b.len() {
+        out.push_str("(the outputs differ only in line endings)\n");
     }
 
-    assert_eq!(&expected, &actual)
+    out
+}
+
+fn read_file(path: &PathBuf) -> String {
+    let mut expected = String::new();
+
+    File::open(path)
+        .expect("failed to open output file")
+        .read_to_string(&mut expected)
+        .expect("failed to read output file");
+
+    expected
+}
+
+fn read_writer(w: Rc<RefCell<Writer>>) -> String {
+    let mut actual = String::new();
+
+    match Rc::try_unwrap(w)
+        .expect("unable to unwrap writer")
+        .into_inner() {
+        Writer::Cursor(ref mut c) => {
+            c.seek(SeekFrom::Start(0)).expect("cannot seek to head of cursor");
+            c.read_to_string(&mut actual).expect("cannot read actual output");
+        }
+        _ => unreachable!(),
+    };
+
+    actual
 }
 
 test_case!(expr, "expr.lox", "expr.lox.out");
@@ -66,6 +285,121 @@ test_case!(counter, "counter.lox", "counter.lox.out");
 test_case!(loops, "loops.lox", "loops.lox.out");
 test_case!(function, "function.lox", "function.lox.out");
 test_case!(lambda, "lambda.lox", "lambda.lox.out");
+test_case!(function_identity, "function_identity.lox", "function_identity.lox.out");
 test_case!(scopes, "scopes.lox", "scopes.lox.out");
 test_case!(stmts, "stmts.lox", "stmts.lox.out");
 test_case!(inheritance, "inheritance.lox", "inheritance.lox.out");
+test_case!(eprint, "eprint.lox", "eprint.lox.out", "eprint.lox.err");
+test_case!(time, "time.lox", "time.lox.out");
+test_case!(json, "json.lox", "json.lox.out", "json.lox.err");
+test_case!(json_no_std, "json_no_std.lox", "json_no_std.lox.out", "json_no_std.lox.err", |r| r.set_std(false));
+test_case!(hash, "hash.lox", "hash.lox.out", "hash.lox.err");
+test_case!(lifecycle, "lifecycle.lox", "lifecycle.lox.out", "lifecycle.lox.err");
+test_case!(finalize_loop, "finalize_loop.lox", "finalize_loop.lox.out");
+test_case!(finalize_error, "finalize_error.lox", "finalize_error.lox.out", "finalize_error.lox.err");
+test_case!(recursion, "recursion.lox", "recursion.lox.out", "recursion.lox.err", |r| r.set_max_call_depth(16));
+test_case!(imports, "imports.lox", "imports.lox.out", "imports.lox.err", |r| r.add_search_path(PathBuf::from("testdata/modules/lib")));
+test_case!(module_scope, "module_scope.lox", "module_scope.lox.out", "module_scope.lox.err");
+test_case!(stdlib, "stdlib.lox", "stdlib.lox.out");
+test_case!(display, "display.lox", "display.lox.out");
+test_case!(display_limits, "display_limits.lox", "display_limits.lox.out");
+test_case!(class_decl, "class_decl.lox", "class_decl.lox.out", "class_decl.lox.err");
+test_case!(class_decl_stream, "class_decl.lox", "class_decl.lox.stream.out", "class_decl.lox.err", |r| r.set_stream(true));
+test_case!(properties, "properties.lox", "properties.lox.out", "properties.lox.err");
+test_case!(this_super, "this_super.lox", "this_super.lox.out", "this_super.lox.err");
+test_case!(parens, "parens.lox", "parens.lox.out", "parens.lox.err");
+test_case!(recovery, "recovery.lox", "recovery.lox.out", "recovery.lox.err");
+test_case!(eof, "eof.lox", "eof.lox.out", "eof.lox.err");
+test_case!(comments, "comments.lox", "comments.lox.out", "comments.lox.err");
+
+#[test]
+fn repl() { run_repl_golden_master("repl.lox", "repl.lox.out") }
+test_case!(globals, "globals.lox", "globals.lox.out");
+test_case!(shadowing, "shadowing.lox", "shadowing.lox.out", "shadowing.lox.err", |r| r.set_lints(Lints::all()));
+test_case!(dead_code, "dead_code.lox", "dead_code.lox.out", "dead_code.lox.err", |r| r.set_lints(Lints::all()));
+test_case!(arity, "arity.lox", "arity.lox.out", "arity.lox.err");
+test_case!(arity_stream, "arity.lox", "arity.lox.stream.out", "arity.lox.err", |r| r.set_stream(true));
+test_case!(call_arity, "call_arity.lox", "call_arity.lox.out", "call_arity.lox.err");
+test_case!(nesting, "nesting.lox", "nesting.lox.out", "nesting.lox.err", |r| r.set_max_depth(8));
+test_case!(crlf_bom, "crlf_bom.lox", "crlf_bom.lox.out");
+test_case!(invalid_utf8, "invalid_utf8.lox", "invalid_utf8.lox.out", "invalid_utf8.lox.err");
+test_case!(shebang, "shebang.lox", "shebang.lox.out");
+test_case!(declarations, "declarations.lox", "declarations.lox.out", "declarations.lox.err");
+test_case!(local_functions, "local_functions.lox", "local_functions.lox.out");
+test_case!(for_loops, "for_loops.lox", "for_loops.lox.out", "for_loops.lox.err", |r| r.set_lints(Lints::all()));
+test_case!(constant, "constant.lox", "constant.lox.out", "constant.lox.err", |r| r.set_lints(Lints::all()));
+test_case!(diagnostics, "diagnostics.lox", "diagnostics.lox.out", "diagnostics.lox.err", |r| {
+    r.set_lints(Lints::all());
+    r.set_error_format(diagnostic::Format::Json);
+});
+test_case!(deny, "deny.lox", "deny.lox.out", "deny.lox.err", |r| {
+    let mut lints = Lints { deny: true, ..Lints::all() };
+    lints.set(Lint::Shadowing, false);
+    r.set_lints(lints);
+});
+test_case!(constant_pruned, "constant.lox", "constant.lox.out", "constant.lox.err", |r| {
+    r.set_lints(Lints::all());
+    r.set_prune(true);
+});
+test_case!(folding, "folding.lox", "folding.lox.out", "folding.lox.err");
+test_case!(folding_folded, "folding.lox", "folding.lox.out", "folding.lox.err", |r| r.set_fold(true));
+test_case!(program, "program.lox", "program.lox.out", "program.lox.err");
+test_case!(program_streamed, "program.lox", "program.lox.stream.out", "program.lox.err", |r| r.set_stream(true));
+test_case!(interrupt, "interrupt.lox", "interrupt.lox.out", "interrupt.lox.err", |r| r.interrupt().trigger());
+test_case!(sandbox, "sandbox.lox", "sandbox.lox.out", "sandbox.lox.err", |r| r.set_sandbox(true));
+test_case!(host, "host.lox", "host.lox.out", "host.lox.err", |r| {
+    r.set_clock(Clock::Virtual(951782400000.0));
+    r.set_seed(7);
+});
+test_case!(division, "division.lox", "division.lox.out");
+test_case!(concat, "concat.lox", "concat.lox.out");
+test_case!(logical, "logical.lox", "logical.lox.out");
+test_case!(compat, "compat.lox", "compat.lox.out");
+test_case!(compat_lox, "compat.lox", "compat_lox.lox.out", "compat_lox.lox.err", |r| r.set_compat(Compat::Lox));
+test_case!(monkey_patching, "monkey_patching.lox", "monkey_patching.lox.out", "monkey_patching.lox.err", |r| r.set_monkey_patching(true));
+test_case!(division_strict, "division.lox", "division_strict.lox.out", "division_strict.lox.err", |r| r.set_strict_division(true));
+
+#[test]
+fn repl_eof() { run_repl_golden_master("repl_eof.lox", "repl_eof.lox.out") }
+
+#[test]
+fn repl_results() { run_repl_golden_master("repl_results.lox", "repl_results.lox.out") }
+
+#[test]
+fn repl_inspect() { run_repl_golden_master("repl_inspect.lox", "repl_inspect.lox.out") }
+
+#[test]
+fn repl_help() { run_repl_golden_master("repl_help.lox", "repl_help.lox.out") }
+
+#[test]
+fn repl_record() { run_record_golden_master("repl_record.lox", "repl_session.lox") }
+
+#[test]
+fn repl_replay() { run_replay_golden_master("repl_session.lox", "repl_session.lox.replay.out", "repl_session.lox.replay.err") }
+
+test_case!(repl_session, "repl_session.lox", "repl_session.lox.out", "repl_session.lox.err");
+
+#[test]
+fn json_numbers() { run_replay_golden_master("json_numbers.lox", "json_numbers.lox.out", "json_numbers.lox.err") }
+
+#[test]
+fn import_failed() { run_replay_golden_master("import_failed.lox", "import_failed.lox.out", "import_failed.lox.err") }
+
+#[test]
+fn suggest() { run_replay_golden_master("suggest.lox", "suggest.lox.out", "suggest.lox.err") }
+
+#[test]
+fn check() { run_check_golden_master("check.lox", "check.lox.err") }
+
+#[test]
+fn project() { run_project_golden_master("project", "project.out", "project.err", |_| ()) }
+
+#[test]
+fn project_warn() {
+    run_project_golden_master("project_warn", "project_warn.out", "project_warn.err", |r| r.allow(Lint::Shadowing))
+}
+
+#[test]
+fn doc() { run_doc_golden_master("docs.lox", "docs.md") }
+
+test_case!(docs, "docs.lox", "docs.lox.out");
@@ -0,0 +1,320 @@
+#![cfg(feature = "internals")]
+
+extern crate rlox;
+
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::rc::Rc;
+
+use rlox::ast::printer::AstPrinter;
+use rlox::ast::stmt::Stmt;
+use rlox::interpreter::{Interpreter, InterpreterOptions};
+use rlox::output::Writer;
+use rlox::parser::StmtIterator;
+use rlox::run::Runner;
+use rlox::scanner::TokenIterator;
+use rlox::Result;
+
+const TEST_DATA: &str = "testdata";
+
+/// The number of generated programs checked by each property
+const GENERATED: u64 = 300;
+
+fn parse(src: &str) -> Result<Vec<Stmt>> {
+    src.chars().tokens().statements().collect()
+}
+
+#[test]
+fn print_then_parse_is_equivalent() {
+    let mut checked = 0;
+
+    for entry in fs::read_dir(TEST_DATA).expect("testdata should be readable") {
+        let path = entry.expect("entry should be readable").path();
+        if path.extension().is_none_or(|ext| ext != "lox") {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        File::open(&path).and_then(|mut f| f.read_to_end(&mut bytes))
+            .expect("file should be readable");
+
+        // files exercising encoding errors have no source to round trip
+        let src = match String::from_utf8(bytes) {
+            Ok(src) => src,
+            Err(_) => continue,
+        };
+
+        // files exercising parse errors have no tree to round trip
+        let stmts = match parse(&src) {
+            Ok(stmts) => stmts,
+            Err(_) => continue,
+        };
+
+        let printed = AstPrinter::default().print(&stmts);
+        let reparsed = parse(&printed)
+            .unwrap_or_else(|e| panic!("{}: printed source should parse: {}\n{}", name(&path), e, printed));
+
+        assert_eq!(printed, AstPrinter::default().print(&reparsed), "{}", name(&path));
+        checked += 1;
+    }
+
+    assert!(checked > 0, "no files were round tripped");
+}
+
+fn name(path: &Path) -> String {
+    path.display().to_string()
+}
+
+#[test]
+fn generated_programs_round_trip() {
+    for seed in 0..GENERATED {
+        let src = Gen::new(seed).program();
+        let stmts = parse(&src)
+            .unwrap_or_else(|e| panic!("seed {}: generated source should parse: {}\n{}", seed, e, src));
+
+        let printed = AstPrinter::default().print(&stmts);
+        let reparsed = parse(&printed)
+            .unwrap_or_else(|e| panic!("seed {}: printed source should parse: {}\n{}", seed, e, printed));
+
+        assert_eq!(printed, AstPrinter::default().print(&reparsed), "seed {}:\n{}", seed, src);
+    }
+}
+
+#[test]
+fn generated_programs_never_panic() {
+    for seed in 0..GENERATED {
+        let src = Gen::new(seed).program();
+
+        // errors are expected, as programs are only syntactically valid
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let out = Rc::new(RefCell::new(Writer::Cursor(Cursor::new(Vec::new()))));
+            let mut r = Runner::new(Rc::clone(&out), Rc::clone(&out));
+            let opts = InterpreterOptions { sandbox: true, max_call_depth: 32, ..InterpreterOptions::default() };
+            let mut i = Interpreter::new(opts, Rc::clone(&out), out);
+            let _ = r.run(&mut i, &src);
+        }));
+
+        if res.is_err() {
+            panic!("seed {}: running the program panicked\n{}", seed, src);
+        }
+    }
+}
+
+/// Gen produces random, syntactically valid programs from the grammar. Names
+/// are drawn from a small pool, so programs also exercise shadowing,
+/// redeclaration, undefined variables, and calls of non-callables.
+///
+/// Loops only count up to a small bound, with counters the programs never
+/// assign, so every program terminates. Strings are only printed, never
+/// concatenated, so no program exhausts memory doubling one.
+struct Gen {
+    state: u64,
+    depth: usize,
+    loops: usize,
+    in_loop: bool,
+    in_fun: bool,
+    in_method: bool,
+    in_subclass: bool,
+}
+
+const NAMES: [&str; 6] = ["a", "b", "c", "f", "g", "Point"];
+
+/// Declares every name in the pool, so programs mostly run past their first
+/// reference
+const PRELUDE: &str = "var a = 1;\nvar b = 2;\nvar c = 3;\nfun f() {}\nfun g() {}\nclass Point {}\n";
+const PROPS: [&str; 3] = ["x", "y", "init"];
+const BINARY: [&str; 12] = ["+", "-", "*", "/", "==", "!=", "<", "<=", ">", ">=", "and", "or"];
+
+/// The deepest nesting of generated expressions and statements
+const MAX_DEPTH: usize = 4;
+
+impl Gen {
+    fn new(seed: u64) -> Gen {
+        Gen {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+            depth: 0,
+            loops: 0,
+            in_loop: false,
+            in_fun: false,
+            in_method: false,
+            in_subclass: false,
+        }
+    }
+
+    /// Returns a number in `0..n`, via xorshift
+    fn below(&mut self, n: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, from: &[&'a str]) -> &'a str {
+        from[self.below(from.len())]
+    }
+
+    fn program(&mut self) -> String {
+        let body: String = (0..1 + self.below(8)).map(|_| self.declaration()).collect();
+        format!("{}{}", PRELUDE, body)
+    }
+
+    fn declaration(&mut self) -> String {
+        match self.below(10) {
+            0 => format!("var {} = {};\n", self.pick(&NAMES), self.expr()),
+            1 => {
+                let name = self.pick(&NAMES);
+                self.function(name)
+            }
+            2 => self.class(),
+            _ => self.statement(),
+        }
+    }
+
+    fn statement(&mut self) -> String {
+        if self.depth >= MAX_DEPTH {
+            return format!("print {};\n", self.expr());
+        }
+
+        self.depth += 1;
+        let stmt = match self.below(12) {
+            0 => format!("print \"{}\";\n", self.pick(&NAMES)),
+            1 => format!("{{\n{}}}\n", self.block()),
+            2 => format!("if ({}) {}", self.expr(), self.statement()),
+            3 => format!("if ({}) {} else {}", self.expr(), self.statement(), self.statement()),
+            4 => self.for_loop(),
+            5 => self.while_loop(),
+            6 if self.in_loop => "break;\n".to_owned(),
+            7 if self.in_fun && self.below(2) == 0 => "return;\n".to_owned(),
+            7 if self.in_fun => format!("return {};\n", self.expr()),
+            8 => format!("print {};\n", self.expr()),
+            9 => format!("{};\n", self.assignment()),
+            _ => format!("{};\n", self.expr()),
+        };
+        self.depth -= 1;
+        stmt
+    }
+
+    fn block(&mut self) -> String {
+        (0..self.below(4)).map(|_| self.declaration()).collect()
+    }
+
+    fn for_loop(&mut self) -> String {
+        let i = format!("i{}", self.loops);
+        let bound = 1 + self.below(3);
+
+        self.loops += 1;
+        let in_loop = std::mem::replace(&mut self.in_loop, true);
+        let body = self.statement();
+        self.in_loop = in_loop;
+        self.loops -= 1;
+
+        format!("for (var {0} = 0; {0} < {1}; {0} = {0} + 1) {2}", i, bound, body)
+    }
+
+    fn while_loop(&mut self) -> String {
+        let w = format!("w{}", self.loops);
+        let bound = 1 + self.below(3);
+
+        self.loops += 1;
+        let in_loop = std::mem::replace(&mut self.in_loop, true);
+        let body = self.block();
+        self.in_loop = in_loop;
+        self.loops -= 1;
+
+        format!("{{\nvar {0} = 0;\nwhile ({0} < {1}) {{\n{0} = {0} + 1;\n{2}}}\n}}\n", w, bound, body)
+    }
+
+    fn function(&mut self, name: &str) -> String {
+        let params = &NAMES[..self.below(3)];
+
+        let outer = (self.in_loop, self.in_fun);
+        self.in_loop = false;
+        self.in_fun = true;
+        let body = self.block();
+        self.in_loop = outer.0;
+        self.in_fun = outer.1;
+
+        format!("fun {}({}) {{\n{}}}\n", name, params.join(", "), body)
+    }
+
+    fn class(&mut self) -> String {
+        let name = self.pick(&NAMES);
+        let parent = match self.below(3) {
+            0 => Some(self.pick(&NAMES)),
+            _ => None,
+        };
+
+        let outer = (self.in_method, self.in_subclass);
+        self.in_method = true;
+        self.in_subclass = parent.is_some();
+        let methods: String = (0..self.below(3)).map(|_| {
+            let prop = self.pick(&PROPS);
+            self.function(prop)[4..].to_owned()
+        }).collect();
+        self.in_method = outer.0;
+        self.in_subclass = outer.1;
+
+        match parent {
+            Some(p) => format!("class {} < {} {{\n{}}}\n", name, p, methods),
+            None => format!("class {} {{\n{}}}\n", name, methods),
+        }
+    }
+
+    fn expr(&mut self) -> String {
+        if self.depth >= MAX_DEPTH {
+            return self.primary();
+        }
+
+        self.depth += 1;
+        let expr = match self.below(10) {
+            0 => format!("{}{}", self.pick(&["-", "!"]), self.primary()),
+            1 | 2 => format!("{} {} {}", self.expr(), self.pick(&BINARY), self.expr()),
+            3 => format!("({})", self.expr()),
+            4 => format!("({})", self.assignment()),
+            5 => {
+                // most generated functions take no parameters
+                let args: Vec<String> = (0..self.below(4).saturating_sub(1)).map(|_| self.expr()).collect();
+                format!("{}({})", self.pick(&NAMES), args.join(", "))
+            }
+            6 => format!("{}.{}", self.target(), self.pick(&PROPS)),
+            _ => self.primary(),
+        };
+        self.depth -= 1;
+        expr
+    }
+
+    /// Generates an assignment to a variable or property, which must be
+    /// parenthesized within other expressions.
+    fn assignment(&mut self) -> String {
+        match self.below(2) {
+            0 => format!("{} = {}", self.pick(&NAMES), self.expr()),
+            _ => format!("{}.{} = {}", self.target(), self.pick(&PROPS), self.expr()),
+        }
+    }
+
+    /// Generates the object of a property access, which is usually an
+    /// instance.
+    fn target(&mut self) -> String {
+        match self.below(3) {
+            0 if self.in_method => "this".to_owned(),
+            0 | 1 => "Point()".to_owned(),
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> String {
+        match self.below(8) {
+            0 => format!("{}", self.below(100)),
+            1 => format!("{}.5", self.below(10)),
+            2 => self.pick(&["true", "false", "nil"]).to_owned(),
+            3 if self.in_method => "this".to_owned(),
+            4 if self.in_subclass => format!("super.{}", self.pick(&PROPS)),
+            5 => format!("{}()", self.pick(&NAMES)),
+            _ => self.pick(&NAMES).to_owned(),
+        }
+    }
+}